@@ -0,0 +1,493 @@
+use rctree::Node;
+use std::borrow::Cow;
+use std::rc::Rc;
+use super::GroupType;
+use super::ASTElement;
+use super::{TagRegistry, ParserOptions, ColourMode, UrlPolicy, MediaPolicy, Colour, Url};
+use crate::escape::attribute_value;
+use crate::ast::has_content;
+use crate::block_constructor::{BlockConstructor, BlockState, node_text};
+use crate::plain_text_constructor::list_marker;
+
+/// Struct for generation of text for terminals, styled with ANSI escape codes.
+///
+/// Bold, italic, underline and strikethrough are written as SGR attributes, colours as the nearest
+/// 256-colour or exact 24-bit value, and spoilers in reverse video. Quotes are marked with a gutter,
+/// list items with bullets or numbers, and links are followed by their address. Text is word-wrapped
+/// to the terminal width, measured in characters, except in preformatted elements. With
+/// `ColourMode::Plain`, no escape codes are written at all. Control characters in the input are
+/// written in caret notation, so the input can't write escape codes of its own.
+pub struct AnsiConstructor {
+	output_string: String,
+	pretty_print: bool,
+	registry: Option<Rc<TagRegistry>>,
+	url_policy: UrlPolicy,
+	media_policy: MediaPolicy,
+	colour_mode: ColourMode,
+	width: usize,
+	prefixes: Vec<String>,
+	lists: Vec<(Option<String>, usize)>,
+	/// The SGR parameters of the open styled elements.
+	styles: Vec<String>,
+	/// The SGR parameters in effect at the end of the output.
+	applied: Vec<String>,
+	column: usize,
+	line_empty: bool,
+	space_pending: bool,
+	preformatted: usize,
+	cell_start: Option<bool>,
+	blocks: BlockState,
+}
+impl AnsiConstructor {
+	/// Creates a new AnsiConstructor.
+	pub fn new(out_len: usize, pretty_print: bool) -> AnsiConstructor {
+		let output_string = String::with_capacity(out_len + out_len/2);
+		AnsiConstructor {
+			output_string,
+			pretty_print,
+			registry: None,
+			url_policy: UrlPolicy::default(),
+			media_policy: MediaPolicy::default(),
+			colour_mode: ColourMode::default(),
+			width: 80,
+			prefixes: Vec::new(),
+			lists: Vec::new(),
+			styles: Vec::new(),
+			applied: Vec::new(),
+			column: 0,
+			line_empty: true,
+			space_pending: false,
+			preformatted: 0,
+			cell_start: None,
+			blocks: BlockState::new(),
+		}
+	}
+	/// Creates a new AnsiConstructor that treats custom elements as blocks or inline according to the handlers in the given TagRegistry.
+	pub fn with_registry(out_len: usize, pretty_print: bool, registry: Rc<TagRegistry>) -> AnsiConstructor {
+		let mut constructor = AnsiConstructor::new(out_len, pretty_print);
+		constructor.registry = Some(registry);
+		constructor
+	}
+	/// Creates a new AnsiConstructor configured by the given ParserOptions.
+	pub fn with_options(out_len: usize, options: &ParserOptions) -> AnsiConstructor {
+		let mut constructor = AnsiConstructor::with_registry(out_len, options.pretty_print, options.registry.clone());
+		constructor.url_policy = options.url_policy.clone();
+		constructor.media_policy = options.media_policy.clone();
+		constructor.colour_mode = options.colour_mode;
+		constructor.width = options.terminal_width;
+		constructor
+	}
+
+	/// Generates a string of terminal text from an ASTElement
+	pub fn construct(&mut self, ast: Node<ASTElement>) -> String {
+		self.traverse(ast);
+		self.reset();
+		self.output_string.trim_end().to_string()
+	}
+
+	/// Starts an element.
+	fn start_element(&mut self, node: &Node<ASTElement>) {
+		if self.pretty_print && !has_content(node) {
+			self.blocks.skip = Some(node.clone());
+			return;
+		}
+		let element = node.borrow();
+		if self.blocks.block_ended && !self.is_block(element.ele_type()) {
+			self.open_block();
+		}
+		let valid = attribute_value(&element, &self.url_policy).is_some();
+		let arg = element.argument().clone().unwrap_or_default();
+		match element.ele_type() {
+			GroupType::Text => {
+				if let Some(text) = element.text_contents() {
+					self.push_text(text);
+				}
+			},
+			GroupType::Paragraph => {
+				match self.cell_start {
+					Some(true) => {},
+					Some(false) => self.space_pending = true,
+					None => self.open_block()
+				}
+			},
+			GroupType::Bold | GroupType::Strong => {self.push_style("1")},
+			GroupType::Italic | GroupType::Emphasis => {self.push_style("3")},
+			GroupType::Underline => {self.push_style("4")},
+			GroupType::Strikethrough => {self.push_style("9")},
+			GroupType::Spoiler => {self.push_style("7")},
+			GroupType::Colour if valid => {
+				let colour = Colour::parse(&arg).map(|colour| match self.colour_mode {
+					ColourMode::TrueColour => format!("38;2;{};{};{}", colour.red(), colour.green(), colour.blue()),
+					_ => format!("38;5;{}", palette_index(colour))
+				});
+				self.push_style(&colour.unwrap_or_default());
+			},
+			GroupType::Opacity if valid => {
+				let faint = arg.parse::<f32>().is_ok_and(|opacity| opacity < 1.0);
+				self.push_style(if faint {"2"} else {""});
+			},
+			GroupType::Url | GroupType::Email if valid => {self.push_style("4")},
+			GroupType::Header if valid => {
+				self.open_block();
+				self.push_style(if arg == "1" || arg == "2" {"1;4"} else {"1"});
+			},
+			GroupType::Footnote => {
+				self.push_style("2");
+				self.push_word("[");
+			},
+			GroupType::Image | GroupType::Embed => {
+				if let Some(src) = self.media_source(&element) {
+					let kind = if element.ele_type() == &GroupType::Image {"image"} else {"embed"};
+					self.push_style("2");
+					self.push_text(&format!("[{}: {}]", kind, src));
+					self.pop_style();
+				}
+			},
+			GroupType::Br => {
+				if self.preformatted > 0 || self.cell_start.is_none() {
+					self.newline();
+				} else {
+					self.space_pending = true;
+				}
+			},
+			GroupType::Hr => {
+				self.open_block();
+				let prefix_width = self.prefix().chars().count();
+				let length = if self.width > prefix_width {self.width - prefix_width} else {40};
+				self.push_word(&"─".repeat(length));
+			},
+			GroupType::Scenebreak => {
+				self.open_block();
+				self.push_text("* * *");
+			},
+			GroupType::Quote => {
+				self.open_block();
+				self.output_string.push_str("│ ");
+				self.column += 2;
+				self.prefixes.push("│ ".to_string());
+				if let Some(author) = element.argument() {
+					self.push_style("1");
+					self.push_text(&format!("{} wrote:", author));
+					self.pop_style();
+					self.newline();
+					self.blocks.block_start = true;
+				}
+			},
+			GroupType::CodeBlock | GroupType::Pre | GroupType::MathBlock => {
+				self.open_block();
+				self.output_string.push_str("    ");
+				self.column += 4;
+				self.prefixes.push("    ".to_string());
+				self.preformatted += 1;
+			},
+			GroupType::Center |
+			GroupType::Right |
+			GroupType::Indent |
+			GroupType::Figure |
+			GroupType::Table
+				=> {self.open_block()},
+			GroupType::List => {
+				self.open_block();
+				self.lists.push((element.argument().clone(), 0));
+			},
+			GroupType::ListItem => {
+				if !self.blocks.block_start {
+					self.newline();
+				}
+				let marker = match self.lists.last_mut() {
+					Some((list_type, number)) => {
+						*number += 1;
+						list_marker(list_type.as_deref(), *number)
+					},
+					None => list_marker(None, 1)
+				};
+				let width = marker.chars().count();
+				self.output_string.push_str(&marker);
+				self.column += width;
+				self.prefixes.push(" ".repeat(width));
+				self.blocks.block_start = true;
+			},
+			GroupType::TableRow | GroupType::TableCaption => {
+				if !self.blocks.block_start {
+					self.newline();
+				}
+				self.blocks.block_ended = false;
+				if element.ele_type() == &GroupType::TableCaption {
+					self.cell_start = Some(true);
+				}
+			},
+			GroupType::TableHeader | GroupType::TableData => {
+				if node.previous_sibling().is_some() {
+					self.push_word("│");
+					self.space_pending = true;
+				}
+				if element.ele_type() == &GroupType::TableHeader {
+					self.push_style("1");
+				}
+				self.cell_start = Some(true);
+			},
+			GroupType::Broken(_, tag) if !self.pretty_print => {
+				match element.argument() {
+					Some(arg) => self.push_text(&format!("[{}={}]", tag, arg)),
+					None => self.push_text(&format!("[{}]", tag))
+				}
+				if let Some(text) = element.text_contents() {
+					self.push_text(text);
+				}
+			},
+			GroupType::Custom(_) if self.is_block(element.ele_type()) => {self.open_block()},
+			_ => {}
+		};
+	}
+
+	/// Ends an element.
+	fn end_element(&mut self, node: &Node<ASTElement>) {
+		let element = node.borrow();
+		if self.is_block(element.ele_type()) && self.cell_start.is_none() {
+			self.blocks.block_ended = true;
+		}
+		let valid = attribute_value(&element, &self.url_policy).is_some();
+		match element.ele_type() {
+			GroupType::Bold |
+			GroupType::Strong |
+			GroupType::Italic |
+			GroupType::Emphasis |
+			GroupType::Underline |
+			GroupType::Strikethrough |
+			GroupType::Spoiler
+				=> {self.pop_style()},
+			GroupType::Colour |
+			GroupType::Opacity |
+			GroupType::Header
+				if valid => {self.pop_style()},
+			GroupType::Url | GroupType::Email if valid => {
+				self.pop_style();
+				if let Some(href) = element.argument() {
+					let text = node_text(node);
+					let bare_href = ["mailto:", "https://", "http://"].iter()
+						.find_map(|scheme| href.strip_prefix(scheme))
+						.unwrap_or(href);
+					if text.trim() != href && text.trim() != bare_href {
+						self.space_pending = true;
+						self.push_text(&format!("({})", href));
+					}
+				}
+			},
+			GroupType::Footnote => {
+				self.push_word("]");
+				self.pop_style();
+			},
+			GroupType::Quote | GroupType::ListItem => {
+				self.prefixes.pop();
+				self.blocks.block_start = false;
+			},
+			GroupType::CodeBlock | GroupType::Pre | GroupType::MathBlock => {
+				self.prefixes.pop();
+				self.preformatted -= 1;
+				self.blocks.block_start = false;
+			},
+			GroupType::List => {self.lists.pop();},
+			GroupType::TableHeader | GroupType::TableData | GroupType::TableCaption => {
+				if element.ele_type() == &GroupType::TableHeader {
+					self.pop_style();
+				}
+				self.cell_start = None;
+				self.space_pending = true;
+			},
+			GroupType::TableRow => {self.space_pending = false},
+			GroupType::Broken(_, tag) if !self.pretty_print && !element.is_void() => {
+				self.push_text(&format!("[/{}]", tag));
+			},
+			_ => {}
+		};
+	}
+
+	/// Gets the URL of an image or embed, as given by the MediaPolicy.
+	fn media_source(&self, element: &ASTElement) -> Option<String> {
+		attribute_value(element, &self.url_policy)?;
+		let url = Url::parse(element.argument().as_deref()?).ok()?;
+		self.media_policy.source(&url)
+	}
+
+	/// Gets the prefix of every line in the current block, such as a quote's gutter.
+	fn prefix(&self) -> String {
+		self.prefixes.concat()
+	}
+
+	/// Starts a new line within the current block, continuing its prefix.
+	fn newline(&mut self) {
+		self.reset();
+		let prefix = self.prefix();
+		self.output_string.push('\n');
+		self.output_string.push_str(&prefix);
+		self.column = prefix.chars().count();
+		self.line_empty = true;
+		self.space_pending = false;
+	}
+
+	/// Writes text from the AST, word-wrapping it unless it is preformatted.
+	fn push_text(&mut self, text: &str) {
+		if self.preformatted > 0 {
+			for (index, line) in text.split('\n').enumerate() {
+				if index > 0 {
+					self.newline();
+				}
+				if !line.is_empty() {
+					let line = escape_controls(line);
+					self.sync_styles();
+					self.output_string.push_str(&line);
+					self.column += line.chars().count();
+					self.line_empty = false;
+					self.blocks.block_start = false;
+				}
+			}
+			return;
+		}
+		for (index, word) in text.split([' ', '\n']).enumerate() {
+			if index > 0 {
+				self.space_pending = true;
+			}
+			if !word.is_empty() {
+				self.push_word(word);
+			}
+		}
+	}
+
+	/// Writes a word, first moving to a new line if it wouldn't fit on this one.
+	fn push_word(&mut self, word: &str) {
+		let word = escape_controls(word);
+		let width = word.chars().count();
+		let space = usize::from(self.space_pending && !self.line_empty);
+		if self.width > 0 && !self.line_empty && self.column + space + width > self.width {
+			self.newline();
+		} else if space > 0 {
+			if self.styles.len() < self.applied.len() {
+				self.sync_styles();
+			}
+			self.output_string.push(' ');
+			self.column += 1;
+		}
+		self.sync_styles();
+		self.output_string.push_str(&word);
+		self.column += width;
+		self.line_empty = false;
+		self.space_pending = false;
+		self.blocks.block_start = false;
+		if self.cell_start.is_some() {
+			self.cell_start = Some(false);
+		}
+	}
+
+	/// Starts a style, given as SGR parameters. It is written before the next word.
+	fn push_style(&mut self, parameters: &str) {
+		self.styles.push(parameters.to_string());
+	}
+
+	/// Ends the most recent style.
+	fn pop_style(&mut self) {
+		self.styles.pop();
+	}
+
+	/// Writes the escape codes that change the styles in effect to those of the open elements.
+	fn sync_styles(&mut self) {
+		if self.colour_mode == ColourMode::Plain || self.applied == self.styles {
+			return;
+		}
+		// Styles that only add to those in effect don't need a reset first.
+		let added = if self.styles.starts_with(&self.applied) {
+			&self.styles[self.applied.len()..]
+		} else {
+			self.reset();
+			&self.styles[..]
+		};
+		let parameters: Vec<&str> = added.iter().map(String::as_str).filter(|parameters| !parameters.is_empty()).collect();
+		if !parameters.is_empty() {
+			self.output_string.push_str(&format!("\x1b[{}m", parameters.join(";")));
+		}
+		self.applied = self.styles.clone();
+	}
+
+	/// Writes the escape code that turns off all styles, if any are in effect.
+	fn reset(&mut self) {
+		if self.applied.iter().any(|parameters| !parameters.is_empty()) {
+			self.output_string.push_str("\x1b[0m");
+		}
+		self.applied.clear();
+	}
+}
+impl BlockConstructor for AnsiConstructor {
+	fn blocks(&mut self) -> &mut BlockState {
+		&mut self.blocks
+	}
+	fn registry(&self) -> Option<&Rc<TagRegistry>> {
+		self.registry.as_ref()
+	}
+	fn start(&mut self, node: &Node<ASTElement>) {
+		self.start_element(node);
+	}
+	fn end(&mut self, node: &Node<ASTElement>) {
+		self.end_element(node);
+	}
+	fn separate_blocks(&mut self) {
+		self.reset();
+		let prefix = self.prefix();
+		self.output_string.push('\n');
+		self.output_string.push_str(prefix.trim_end());
+		self.newline();
+	}
+	fn block_opened(&mut self) {
+		self.space_pending = false;
+	}
+}
+
+/// Replaces control characters other than newlines with their caret notation, such as `^[` for
+/// escape, so that text can't write escape codes of its own. C1 controls are written as the escape
+/// sequences they stand for.
+fn escape_controls(text: &str) -> Cow<'_, str> {
+	if !text.chars().any(|ch| ch != '\n' && ch.is_control()) {
+		return Cow::Borrowed(text);
+	}
+	let mut escaped = String::with_capacity(text.len() + 8);
+	for ch in text.chars() {
+		match ch {
+			'\n' => escaped.push(ch),
+			'\0'..='\x1f' => {
+				escaped.push('^');
+				escaped.push(char::from(ch as u8 + 0x40));
+			},
+			'\x7f' => escaped.push_str("^?"),
+			'\u{80}'..='\u{9f}' => {
+				escaped.push_str("^[");
+				escaped.push(char::from(ch as u8 - 0x40));
+			},
+			ch => escaped.push(ch)
+		}
+	}
+	Cow::Owned(escaped)
+}
+
+/// Gets the index of the nearest of the 256 standard terminal colours, from the 6×6×6 colour cube
+/// and the grey ramp.
+fn palette_index(colour: Colour) -> u8 {
+	const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+	let cube = |value: u8| match value {
+		0..=47 => 0,
+		48..=114 => 1,
+		_ => (value - 35) / 40
+	};
+	let (red, green, blue) = (cube(colour.red()), cube(colour.green()), cube(colour.blue()));
+	let cube_colour = (LEVELS[red as usize], LEVELS[green as usize], LEVELS[blue as usize]);
+	let average = (u16::from(colour.red()) + u16::from(colour.green()) + u16::from(colour.blue())) / 3;
+	let grey_index = if average > 238 {23} else {average.saturating_sub(3) / 10} as u8;
+	let grey = 8 + 10 * grey_index;
+	let distance = |(r, g, b): (u8, u8, u8)| {
+		[(r, colour.red()), (g, colour.green()), (b, colour.blue())].iter()
+			.map(|(a, b)| (i32::from(*a) - i32::from(*b)).pow(2))
+			.sum::<i32>()
+	};
+	if distance((grey, grey, grey)) < distance(cube_colour) {
+		232 + grey_index
+	} else {
+		16 + 36 * red + 6 * green + blue
+	}
+}
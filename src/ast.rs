@@ -0,0 +1,225 @@
+use rctree::Node;
+use std::cell::Ref;
+use super::{ASTElement, GroupType, Span, Diagnostic, Colour, ContentStats};
+
+/// A parsed BBCode document.
+///
+/// ```
+///use bbclash::{parse, ParserOptions, GroupType, Attribute};
+///
+///let ast = parse("[url=https://www.penclash.com]Penclash[/url]", &ParserOptions::new());
+///let link = ast.descendants().find(|node| node.kind() == GroupType::Url).unwrap();
+///assert_eq!(link.attribute(), Some(Attribute::Url("https://www.penclash.com".to_string())));
+///assert_eq!(link.first_child().unwrap().text(), Some("Penclash".to_string()));
+///assert_eq!(link.parent().unwrap().kind(), GroupType::Paragraph);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Ast {
+	anchor: Node<ASTElement>,
+	source_len: usize,
+	diagnostics: Vec<Diagnostic>,
+}
+impl Ast {
+	/// Creates a new, empty Ast, for building documents programmatically.
+	pub fn new() -> Ast {
+		let mut anchor = Node::new(ASTElement::new(GroupType::Anchor));
+		anchor.append(Node::new(ASTElement::new(GroupType::Document)));
+		Ast {anchor, source_len: 0, diagnostics: Vec::new()}
+	}
+	/// Wraps the tree produced by `BBCodeLexer::lex`.
+	pub(crate) fn from_lexed(anchor: Node<ASTElement>, source_len: usize, diagnostics: Vec<Diagnostic>) -> Ast {
+		Ast {anchor, source_len, diagnostics}
+	}
+	/// Gets the tree in the form consumed by the constructors.
+	pub(crate) fn anchor(&self) -> Node<ASTElement> {
+		self.anchor.clone()
+	}
+	/// Gets the length of the BBCode the Ast was parsed from.
+	pub(crate) fn source_len(&self) -> usize {
+		self.source_len
+	}
+	/// Gets the document's root node, which has the type `GroupType::Document`.
+	pub fn root(&self) -> AstNode {
+		AstNode {node: self.anchor.first_child().expect("an Ast always has a document node")}
+	}
+	/// Iterates over every node in the document, in document order, starting with the root.
+	pub fn descendants(&self) -> Descendants {
+		self.root().descendants()
+	}
+	/// Gets the Diagnostics raised while parsing the document.
+	pub fn diagnostics(&self) -> &[Diagnostic] {
+		&self.diagnostics
+	}
+	/// Gathers figures about the document's links, media and hidden text.
+	pub fn stats(&self) -> ContentStats {
+		ContentStats::from_ast(self)
+	}
+}
+impl Default for Ast {
+	fn default() -> Self {Ast::new()}
+}
+
+/// A single node of an Ast. AstNodes are cheap handles: cloning one refers to the same node.
+#[derive(Debug, Clone)]
+pub struct AstNode {
+	node: Node<ASTElement>,
+}
+impl AstNode {
+	/// Creates a new, detached AstNode of the given type.
+	pub fn new(kind: GroupType) -> AstNode {
+		AstNode {node: Node::new(ASTElement::new(kind))}
+	}
+	/// Creates a new, detached text node.
+	pub fn new_text(text: &str) -> AstNode {
+		let mut node = AstNode::new(GroupType::Text);
+		node.node.borrow_mut().add_text(text);
+		node
+	}
+	/// Gets the node's type.
+	pub fn kind(&self) -> GroupType {
+		self.node.borrow().ele_type().clone()
+	}
+	/// Gets an immutable reference to the underlying ASTElement.
+	pub fn element(&self) -> Ref<'_, ASTElement> {
+		self.node.borrow()
+	}
+	/// Gets the node's text, if it has any.
+	pub fn text(&self) -> Option<String> {
+		self.node.borrow().text_contents().clone()
+	}
+	/// Gets the node's raw argument, if it has one.
+	pub fn argument(&self) -> Option<String> {
+		self.node.borrow().argument().clone()
+	}
+	/// Gets the node's argument, interpreted according to the node's type.
+	pub fn attribute(&self) -> Option<Attribute> {
+		let element = self.node.borrow();
+		let arg = element.argument().as_ref()?;
+		Some(match element.ele_type() {
+			GroupType::Url | GroupType::Email | GroupType::Image | GroupType::Embed
+				=> Attribute::Url(arg.clone()),
+			GroupType::Colour => Attribute::Colour(Colour::parse(arg)?),
+			GroupType::Opacity => Attribute::Opacity(arg.parse().ok()?),
+			GroupType::Size => Attribute::Size(arg.parse().ok()?),
+			GroupType::Header => Attribute::HeaderLevel(arg.parse().ok()?),
+			GroupType::Indent => Attribute::IndentLevel(arg.parse().ok()?),
+			GroupType::List => Attribute::ListType(arg.clone()),
+			GroupType::Quote => Attribute::Author(arg.clone()),
+			GroupType::Footnote => Attribute::Symbol(arg.clone()),
+			GroupType::CodeBlock => Attribute::Language(arg.clone()),
+			GroupType::Figure => Attribute::FigureSide(arg.clone()),
+			_ => Attribute::Other(arg.clone()),
+		})
+	}
+	/// Gets the Span of the input the node came from. Nodes created programmatically have no Span.
+	pub fn span(&self) -> Option<Span> {
+		self.node.borrow().span()
+	}
+	/// Gets whether the node is a broken tag.
+	pub fn is_broken(&self) -> bool {
+		self.node.borrow().is_broken()
+	}
+	/// Gets the node's parent. The root has no parent.
+	pub fn parent(&self) -> Option<AstNode> {
+		self.node.parent()
+			.filter(|parent| parent.borrow().ele_type() != &GroupType::Anchor)
+			.map(AstNode::from_node)
+	}
+	/// Gets the node's first child.
+	pub fn first_child(&self) -> Option<AstNode> {
+		self.node.first_child().map(AstNode::from_node)
+	}
+	/// Gets the node's last child.
+	pub fn last_child(&self) -> Option<AstNode> {
+		self.node.last_child().map(AstNode::from_node)
+	}
+	/// Gets the node's next sibling.
+	pub fn next_sibling(&self) -> Option<AstNode> {
+		self.node.next_sibling().map(AstNode::from_node)
+	}
+	/// Gets the node's previous sibling.
+	pub fn previous_sibling(&self) -> Option<AstNode> {
+		self.node.previous_sibling().map(AstNode::from_node)
+	}
+	/// Iterates over the node's children.
+	pub fn children(&self) -> Children {
+		Children(self.node.children())
+	}
+	/// Iterates over the node and all of its descendants, in document order.
+	pub fn descendants(&self) -> Descendants {
+		Descendants(self.node.descendants())
+	}
+	/// Gets the text of the node and all of its descendants, concatenated.
+	pub fn text_content(&self) -> String {
+		self.descendants().filter_map(|node| node.text()).collect()
+	}
+	/// Appends a child to the node, detaching it from its previous position.
+	pub fn append(&mut self, mut child: AstNode) {
+		child.node.detach();
+		self.node.append(child.node);
+	}
+	/// Detaches the node, and its descendants, from its tree.
+	pub fn detach(&mut self) {
+		self.node.detach();
+	}
+	/// Sets the node's argument.
+	pub fn set_argument(&mut self, arg: &str) {
+		self.node.borrow_mut().set_arg(arg);
+	}
+	/// Adds text to the node.
+	pub fn add_text(&mut self, text: &str) {
+		self.node.borrow_mut().add_text(text);
+	}
+	fn from_node(node: Node<ASTElement>) -> AstNode {
+		AstNode {node}
+	}
+}
+
+/// Gets whether an element, or any of its descendants, has text or is void.
+pub(crate) fn has_content(node: &Node<ASTElement>) -> bool {
+	node.descendants().any(|descendant| {
+		let element = descendant.borrow();
+		element.is_void() || element.text_contents().as_ref().is_some_and(|text| !text.is_empty())
+	})
+}
+
+/// Iterator over the children of an AstNode.
+pub struct Children(rctree::Children<ASTElement>);
+impl Iterator for Children {
+	type Item = AstNode;
+	fn next(&mut self) -> Option<AstNode> {
+		self.0.next().map(AstNode::from_node)
+	}
+}
+
+/// Iterator over an AstNode and its descendants.
+pub struct Descendants(rctree::Descendants<ASTElement>);
+impl Iterator for Descendants {
+	type Item = AstNode;
+	fn next(&mut self) -> Option<AstNode> {
+		self.0.next().map(AstNode::from_node)
+	}
+}
+
+/// The argument of an AstNode, interpreted according to the node's type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+	/// The target of a link, image, or embed.
+	Url(String),
+	Colour(Colour),
+	Opacity(f32),
+	/// Font size in rem.
+	Size(f32),
+	HeaderLevel(u8),
+	IndentLevel(u8),
+	ListType(String),
+	/// The author of a quote.
+	Author(String),
+	/// The symbol of a footnote.
+	Symbol(String),
+	/// The language of a code block.
+	Language(String),
+	FigureSide(String),
+	/// The argument of any other type of node.
+	Other(String),
+}
@@ -0,0 +1,167 @@
+use rctree::{Node, NodeEdge};
+use super::GroupType;
+use super::ASTElement;
+use super::ParserOptions;
+use crate::ast::has_content;
+
+/// Struct for generation of canonical BBCode strings.
+///
+/// Every element is written with its canonical, lowercase tag, unclosed tags are closed, and
+/// misnested tags are written in the order the lexer resolved them to. Broken tags are removed
+/// in pretty output and escaped in ugly output, so that re-parsing the output gives the same result.
+/// Pretty output also leaves out empty elements.
+pub struct BBCodeConstructor {
+	output_string: String,
+	pretty_print: bool,
+	verbatim_depth: usize,
+}
+impl BBCodeConstructor {
+	/// Creates a new BBCodeConstructor.
+	pub fn new(out_len: usize, pretty_print: bool) -> BBCodeConstructor {
+		let output_string = String::with_capacity(out_len);
+		BBCodeConstructor {
+			output_string,
+			pretty_print,
+			verbatim_depth: 0,
+		}
+	}
+	/// Creates a new BBCodeConstructor configured by the given ParserOptions.
+	pub fn with_options(out_len: usize, options: &ParserOptions) -> BBCodeConstructor {
+		BBCodeConstructor::new(out_len, options.pretty_print)
+	}
+
+	/// Generates a BBCode string from an ASTElement
+	pub fn construct(&mut self, ast: Node<ASTElement>) -> String {
+		for node_edge in ast.traverse() {
+			match node_edge {
+				NodeEdge::Start(node) => {self.start_element(&node)},
+				NodeEdge::End(node) => {self.end_element(&node)}
+			}
+		}
+		self.output_string.clone()
+	}
+
+	/// Opens a BBCode tag.
+	fn start_element(&mut self, node: &Node<ASTElement>) {
+		if self.pretty_print && !has_content(node) {
+			return;
+		}
+		let element = node.borrow();
+		match element.ele_type() {
+			GroupType::Text => {
+				if let Some(text) = element.text_contents() {
+					self.push_text(text);
+				}
+			},
+			GroupType::Paragraph => {
+				let follows_paragraph = node.previous_sibling()
+					.is_some_and(|sibling| sibling.borrow().ele_type() == &GroupType::Paragraph);
+				if follows_paragraph {
+					self.output_string.push_str("\n\n");
+				}
+			},
+			GroupType::Br => {self.output_string.push('\n')},
+			GroupType::Scenebreak => {self.output_string.push_str("\n\n\n")},
+			GroupType::Hr => {self.output_string.push_str("[hr]")},
+			GroupType::ListItem => {self.output_string.push_str("[*]")},
+			GroupType::Header => {
+				if let Some(arg) = element.argument() {
+					self.output_string.push_str(&format!("[h{}]", arg));
+				}
+			},
+			GroupType::Size => {
+				if let Some(arg) = element.argument() {
+					self.output_string.push_str(&format!("[size={}em]", arg));
+				}
+			},
+			GroupType::Email => {self.output_string.push_str("[email]")},
+			GroupType::Image | GroupType::Embed => {
+				if let Some(arg) = element.argument() {
+					let tag = element.ele_type().tag_name().unwrap_or_default();
+					self.output_string.push_str(&format!("[{}]{}[/{}]", tag, arg, tag));
+				}
+			},
+			GroupType::Code | GroupType::CodeBlock | GroupType::Math | GroupType::MathBlock => {
+				self.push_open_tag(&element);
+				self.verbatim_depth += 1;
+			},
+			GroupType::Broken(_, tag) => {
+				if !self.pretty_print {
+					match element.argument() {
+						Some(arg) => self.push_text(&format!("[{}={}]", tag, arg)),
+						None => self.push_text(&format!("[{}]", tag))
+					}
+				}
+				if let Some(text) = element.text_contents() {
+					self.push_text(text);
+				}
+			},
+			_ => {self.push_open_tag(&element)}
+		};
+	}
+
+	/// Closes a BBCode tag.
+	fn end_element(&mut self, node: &Node<ASTElement>) {
+		if self.pretty_print && !has_content(node) {
+			return;
+		}
+		let element = node.borrow();
+		match element.ele_type() {
+			GroupType::Text |
+			GroupType::Paragraph |
+			GroupType::Br |
+			GroupType::Scenebreak |
+			GroupType::Hr |
+			GroupType::ListItem |
+			GroupType::Image |
+			GroupType::Embed
+				=> {},
+			GroupType::Header => {
+				if let Some(arg) = element.argument() {
+					self.output_string.push_str(&format!("[/h{}]", arg));
+				}
+			},
+			GroupType::Code | GroupType::CodeBlock | GroupType::Math | GroupType::MathBlock => {
+				self.verbatim_depth -= 1;
+				self.push_close_tag(&element);
+			},
+			GroupType::Broken(_, tag) => {
+				if !self.pretty_print && !element.is_void() {
+					self.push_text(&format!("[/{}]", tag));
+				}
+			},
+			_ => {self.push_close_tag(&element)}
+		};
+	}
+
+	/// Writes an element's opening tag, with its argument if it has one.
+	/// A `]` would end the argument, so it is percent-encoded; only a URL read from a tag's contents can have one.
+	fn push_open_tag(&mut self, element: &ASTElement) {
+		if let Some(tag) = element.ele_type().tag_name() {
+			match element.argument() {
+				Some(arg) => self.output_string.push_str(&format!("[{}={}]", tag, arg.replace(']', "%5D"))),
+				None => self.output_string.push_str(&format!("[{}]", tag))
+			}
+		}
+	}
+
+	/// Writes an element's closing tag.
+	fn push_close_tag(&mut self, element: &ASTElement) {
+		if let Some(tag) = element.ele_type().tag_name() {
+			self.output_string.push_str(&format!("[/{}]", tag));
+		}
+	}
+
+	/// Writes text, escaping anything that would otherwise be read as a tag.
+	/// Inside code and math, where only the closing tag is read, only closing tags are escaped.
+	fn push_text(&mut self, text: &str) {
+		let mut chars = text.chars().peekable();
+		while let Some(c) = chars.next() {
+			match c {
+				'\\' => self.output_string.push_str("\\\\"),
+				'[' if self.verbatim_depth == 0 || chars.peek() == Some(&'/') => self.output_string.push_str("\\["),
+				_ => self.output_string.push(c)
+			}
+		}
+	}
+}
@@ -1,8 +1,14 @@
 use rctree::Node;
 use phf::{phf_map, phf_set};
+use std::cell::{Ref, RefMut};
+use std::rc::Rc;
 use super::Instruction; 
 use super::GroupType;
 use super::ASTElement;
+use super::Span;
+use super::{Diagnostic, DiagnosticKind};
+use super::{TagRegistry, TagHandler, TagFilter, ParserOptions, LinebreakMode, UrlPolicy, MediaPolicy, Url, Colour};
+use crate::colour::parse_decimal;
 
 /// Struct for lexing BBCode Instructions into an ASTElement tree.
 pub struct BBCodeLexer {
@@ -12,11 +18,35 @@ pub struct BBCodeLexer {
 	ignore_tags: Option<&'static str>,
 	ignore_formatting: bool,
 	linebreaks_allowed: bool,
-	preserve_empty: bool
+	preserve_empty: bool,
+	registry: Rc<TagRegistry>,
+	tag_filter: TagFilter,
+	linebreak_mode: LinebreakMode,
+	url_policy: UrlPolicy,
+	media_policy: MediaPolicy,
+	max_depth: Option<usize>,
+	max_nodes: Option<usize>,
+	max_quote_depth: Option<usize>,
+	node_count: usize,
+	suppressed_tags: Vec<String>,
+	node_limit_reported: bool,
+	current_span: Option<Span>,
+	closing_tag: Option<String>,
+	diagnostics: Vec<Diagnostic>
 }
 impl BBCodeLexer {
-	/// Creates a new BBCodeLexer.
+	/// Creates a new BBCodeLexer using the default tag set.
 	pub fn new(preserve_empty: bool) -> BBCodeLexer {
+		BBCodeLexer::with_options(&ParserOptions::new().pretty_print(!preserve_empty))
+	}
+	/// Creates a new BBCodeLexer that recognises the tags in the given TagRegistry.
+	pub fn with_registry(preserve_empty: bool, registry: Rc<TagRegistry>) -> BBCodeLexer {
+		let mut lexer = BBCodeLexer::new(preserve_empty);
+		lexer.registry = registry;
+		lexer
+	}
+	/// Creates a new BBCodeLexer configured by the given ParserOptions.
+	pub fn with_options(options: &ParserOptions) -> BBCodeLexer {
 		BBCodeLexer {
 			anchor: Node::new(ASTElement::new(GroupType::Anchor)),
 			current_node: Node::new(ASTElement::new(GroupType::Document)),
@@ -24,23 +54,79 @@ impl BBCodeLexer {
 			ignore_tags: None,
 			ignore_formatting: false,
 			linebreaks_allowed: true,
-			preserve_empty
+			preserve_empty: !options.pretty_print,
+			registry: options.registry.clone(),
+			tag_filter: options.tag_filter.clone(),
+			linebreak_mode: options.linebreaks,
+			url_policy: options.url_policy.clone(),
+			media_policy: options.media_policy.clone(),
+			max_depth: options.max_depth,
+			max_nodes: options.max_nodes,
+			max_quote_depth: options.max_quote_depth,
+			node_count: 0,
+			suppressed_tags: Vec::new(),
+			node_limit_reported: false,
+			current_span: None,
+			closing_tag: None,
+			diagnostics: Vec::new()
 		}
 	}
 	/// Lexes a vector of Instructions.
 	pub fn lex(&mut self, instructions: &[Instruction]) -> Node<ASTElement> {
+		self.lex_with_spans(instructions, &[])
+	}
+	/// Lexes a vector of Instructions, recording the Spans they came from (as given by `BBCodeTokenizer::spans`)
+	/// on the ASTElements they create.
+	pub fn lex_with_spans(&mut self, instructions: &[Instruction], spans: &[Span]) -> Node<ASTElement> {
 		self.anchor.append(Node::new(ASTElement::new(GroupType::Document)));
 		self.current_node = self.anchor.first_child().unwrap();
+		self.current_span = spans.first().map(|span| Span {end: span.start, ..*span});
 		self.new_group(GroupType::Paragraph);
-		for instruction in instructions {
+		for (index, instruction) in instructions.iter().enumerate() {
+			if let Some(span) = spans.get(index) {
+				self.current_span = Some(*span);
+			}
 			self.execute(instruction);
 		}
+		self.diagnose_unclosed();
 		self.end_group(GroupType::Paragraph);
 		self.current_node.root()
 	}
+	/// Gets the Diagnostics raised while lexing.
+	pub fn diagnostics(&self) -> &[Diagnostic] {
+		&self.diagnostics
+	}
+	/// Records a Diagnostic for the Instruction being executed.
+	fn diagnose(&mut self, kind: DiagnosticKind, message: String) {
+		self.diagnostics.push(Diagnostic {kind, span: self.current_span, message});
+	}
+	/// Records a Diagnostic for each element still open at the end of the input.
+	fn diagnose_unclosed(&mut self) {
+		let unclosed: Vec<Diagnostic> = self.current_node.ancestors()
+			.filter_map(|node| {
+				let element = node.borrow();
+				if element.is_broken() {
+					return None;
+				}
+				let tag = match element.ele_type() {
+					GroupType::Header => format!("h{}", element.argument().as_deref().unwrap_or_default()),
+					GroupType::ListItem => return None,
+					ele_type => ele_type.tag_name()?.to_string()
+				};
+				Some(Diagnostic {
+					kind: DiagnosticKind::UnclosedTag,
+					span: element.span(),
+					message: format!("`[{}]` is never closed", tag)
+				})
+			})
+			.collect();
+		self.diagnostics.extend(unclosed.into_iter().rev());
+	}
 	/// Matches Instruction types.
 	fn execute(&mut self, instruction: &Instruction) {
-		if let Some(arg_cmd) = self.next_text_as_arg {
+		if self.node_limit_reached() {
+			self.execute_as_text(instruction);
+		} else if let Some(arg_cmd) = self.next_text_as_arg {
 			match instruction {
 				Instruction::Text(param) => {
 					arg_cmd(self, param);
@@ -54,27 +140,30 @@ impl BBCodeLexer {
 			match instruction {
 				Instruction::Text(param) => {
 					self.new_group(GroupType::Text);
-					self.current_node.borrow_mut().add_text(&param);
+					self.current_node.borrow_mut().add_text(param);
 					self.end_group(GroupType::Text);
 				}
 				Instruction::Tag(param, arg) => {
 					if let Some(command) = self.ignore_tags {
-						if param == command {
-							self.parse_tag(&param, arg);
+						if param.eq_ignore_ascii_case(command) {
+							self.parse_tag(param, arg);
 						} else {
-							let tag_text = format!("[{}{}]", param, {if let Some(argu) = arg {argu} else {""}});
+							let tag_text = match arg {
+								Some(argu) => format!("[{}={}]", param, argu),
+								None => format!("[{}]", param)
+							};
 							self.new_group(GroupType::Text);
 							self.current_node.borrow_mut().add_text(&tag_text);
 							self.end_group(GroupType::Text);
 						}	
 					} else {
-						self.parse_tag(&param, arg);
+						self.parse_tag(param, arg);
 					}		
 				},
 				Instruction::Parabreak(param) => {
 					if self.ignore_formatting {
 						self.new_group(GroupType::Text);
-						self.current_node.borrow_mut().add_text(&param);
+						self.current_node.borrow_mut().add_text(param);
 						self.end_group(GroupType::Text);
 					} else {
 						self.end_and_new_group(GroupType::Paragraph, GroupType::Paragraph);
@@ -83,18 +172,29 @@ impl BBCodeLexer {
 				Instruction::Linebreak => {
 					if self.ignore_formatting {
 						self.new_group(GroupType::Text);
-						self.current_node.borrow_mut().add_text(&"\n".to_string());
+						self.current_node.borrow_mut().add_text("\n");
 						self.end_group(GroupType::Text);
 					} else if self.linebreaks_allowed {
-						self.new_group(GroupType::Br);
-						self.current_node.borrow_mut().set_void(true);
-						self.end_group(GroupType::Br);
+						match self.linebreak_mode {
+							LinebreakMode::Br => {
+								self.new_group(GroupType::Br);
+								self.current_node.borrow_mut().set_void(true);
+								self.end_group(GroupType::Br);
+							},
+							LinebreakMode::Space => {
+								self.new_group(GroupType::Text);
+								self.current_node.borrow_mut().add_text(" ");
+								self.current_node.borrow_mut().set_detachable(false);
+								self.end_group(GroupType::Text);
+							},
+							LinebreakMode::Ignore => {}
+						}
 					}
 				}
 				Instruction::Scenebreak => {
 					if self.ignore_formatting {
 						self.new_group(GroupType::Text);
-						self.current_node.borrow_mut().add_text(&"\n\n\n".to_string());
+						self.current_node.borrow_mut().add_text("\n\n\n");
 						self.end_group(GroupType::Text);
 					} else {
 						self.end_group(GroupType::Paragraph);
@@ -108,10 +208,29 @@ impl BBCodeLexer {
 			}
 		}	
 	}
+	/// Gets an immutable reference to the ASTElement currently being built.
+	pub fn current_element(&self) -> Ref<'_, ASTElement> {
+		self.current_node.borrow()
+	}
+	/// Gets a mutable reference to the ASTElement currently being built.
+	pub fn current_element_mut(&mut self) -> RefMut<'_, ASTElement> {
+		self.current_node.borrow_mut()
+	}
 	/// Creates a new ASTElement.
-	fn new_group(&mut self, ele_type: GroupType) {
-		self.current_node.append(Node::new(ASTElement::new(ele_type)));
+	pub fn new_group(&mut self, ele_type: GroupType) {
+		let mut element = ASTElement::new(ele_type);
+		if let Some(span) = self.current_span {
+			element.set_span(span);
+		}
+		self.current_node.append(Node::new(element));
 		self.current_node = self.current_node.last_child().unwrap();
+		self.node_count += 1;
+	}
+	/// Extends the current node's span to the end of the Instruction being executed.
+	fn extend_current_span(&mut self) {
+		if let Some(span) = self.current_span {
+			self.current_node.borrow_mut().extend_span(span.end);
+		}
 	}
 	// Closes groups when the current group is the target group.
 	fn close_same_group(&mut self) {
@@ -135,6 +254,7 @@ impl BBCodeLexer {
 				} else if self.current_node.borrow().ele_type() == &GroupType::Paragraph && !self.current_node.has_children() {
 					self.current_node.detach();
 				}
+				self.extend_current_span();
 				self.current_node = parent;
 			}
 		};
@@ -147,23 +267,28 @@ impl BBCodeLexer {
 			match my_type {
 				GroupType::Paragraph if ele_type != GroupType::Paragraph => {
 					go = false;
+					self.diagnose_close(group_stack, false);
 					if !self.current_node.has_children() {
 						self.current_node.detach();
 					}
 				},
 				GroupType::List if ele_type != GroupType::List => {
 					go = false;
+					self.diagnose_close(group_stack, false);
 				},
 				GroupType::Document if ele_type != GroupType::Document => {
 					go = false;
+					self.diagnose_close(group_stack, false);
 				},
 				_ => {
 					if my_type == ele_type {
 						go = false;
+						self.diagnose_close(group_stack, true);
 					} else if let GroupType::Broken(some_box, _) = my_type.clone() {
 						let unpacked_type = *some_box;
 						if unpacked_type == ele_type {
 							go = false;
+							self.diagnose_close(group_stack, true);
 						} else if unpacked_type != GroupType::ListItem {
 							group_stack.push(GroupShorthand {
 								ele_type: my_type, 
@@ -199,6 +324,7 @@ impl BBCodeLexer {
 							} else if self.current_node.borrow().ele_type() == &GroupType::Paragraph && !self.current_node.has_children() {
 								self.current_node.detach();
 							}
+							self.extend_current_span();
 							self.current_node = parent;
 						}
 					};
@@ -206,10 +332,24 @@ impl BBCodeLexer {
 			}
 		}
 	}
-	// Reopens closed groups after another element has closed.
+	/// Records a Diagnostic if the closing tag being executed did not cleanly close its own element.
+	fn diagnose_close(&mut self, group_stack: &[GroupShorthand], found: bool) {
+		if let Some(tag) = self.closing_tag.take() {
+			if !found {
+				self.diagnose(DiagnosticKind::StrayCloseTag, format!("`[{}]` has no matching opening tag", tag));
+			} else if let Some(group) = group_stack.iter().find(|group| !matches!(group.ele_type, GroupType::Broken(_, _))) {
+				let inner = group.ele_type.tag_name().unwrap_or_default().to_string();
+				self.diagnose(DiagnosticKind::MisnestedTag, format!("`[{}]` closes `[{}]`, which is still open", tag, inner));
+			}
+		}
+	}
+	// Reopens closed groups after another element has closed, leaving closed any that would take the
+	// lexer past the maximum number of elements.
 	fn reopen_groups(&mut self, group_stack: &mut Vec<GroupShorthand>) {
-		while !group_stack.is_empty() {
-			let group = group_stack.pop().unwrap();
+		while let Some(group) = group_stack.pop() {
+			if self.node_limit_reached() {
+				return;
+			}
 			self.new_group(group.ele_type.clone());
 			if let Some(arg) = group.arg {
 				self.current_node.borrow_mut().set_arg(&arg);
@@ -218,7 +358,7 @@ impl BBCodeLexer {
 	}
 	/// Moves current working node up to the current node's parent and then creates a new element, 
 	/// preserving the formatting from the previous.
-	fn end_and_new_group(&mut self, ele_type: GroupType, new_type: GroupType) {
+	pub fn end_and_new_group(&mut self, ele_type: GroupType, new_type: GroupType) {
 		if let Some(mut kid) = self.current_node.last_child() {
 			if kid.borrow().ele_type() == &GroupType::Br {
 				kid.detach();
@@ -253,7 +393,7 @@ impl BBCodeLexer {
 	}
 	/// Moves current working node up to the current node's parent and then creates a new element, 
 	/// *without* preserving formatting from the previous element.
-	fn end_and_kill_new_group(&mut self, ele_type: GroupType, new_type: GroupType) {
+	pub fn end_and_kill_new_group(&mut self, ele_type: GroupType, new_type: GroupType) {
 		if let Some(mut kid) = self.current_node.last_child() {
 			if kid.borrow().ele_type() == &GroupType::Br {
 				kid.detach();
@@ -285,7 +425,7 @@ impl BBCodeLexer {
 		}	
 	}
 	/// Moves current working node up to the current node's parent.
-	fn end_group(&mut self, ele_type: GroupType) {
+	pub fn end_group(&mut self, ele_type: GroupType) {
 		if let Some(mut kid) = self.current_node.last_child() {
 			if kid.borrow().ele_type() == &GroupType::Br {
 				kid.detach();
@@ -315,24 +455,168 @@ impl BBCodeLexer {
 		}	
 	}
 	/// Parses tag Instructions.
-	fn parse_tag(&mut self, tag: &str, args: &Option<String>) {
-		match args {
-			Some(primary_arg) => {
-				match ONE_ARG_CMD.get(tag) {
-					Some(cmd) => cmd(self, primary_arg),
-					None => self.execute(&Instruction::Text(format!("[{}={}]", tag, primary_arg)))
+	fn parse_tag(&mut self, written_tag: &str, args: &Option<String>) {
+		let lowered = written_tag.to_ascii_lowercase();
+		let tag = lowered.as_str();
+		if !self.tag_filter.permits(tag) && self.is_registered(tag) {
+			self.diagnose(DiagnosticKind::DisallowedTag, format!("`[{}]` is not allowed here", tag));
+			if self.preserve_empty {
+				self.tag_as_text(written_tag, args);
+			}
+			return;
+		}
+		if !self.resolves(tag, args) {
+			self.unknown_tag(written_tag, args);
+			return;
+		}
+		if let Some(name) = tag.strip_prefix('/') {
+			let name = canonical_tag(name);
+			if let Some(index) = self.suppressed_tags.iter().rposition(|suppressed| suppressed == name) {
+				self.suppressed_tags.remove(index);
+				self.tag_as_text(written_tag, args);
+				return;
+			}
+		} else if let Some(message) = self.limit_exceeded(tag) {
+			self.diagnose(DiagnosticKind::LimitExceeded, message);
+			if self.has_closing_tag(tag) {
+				self.suppressed_tags.push(canonical_tag(tag).to_string());
+			}
+			self.tag_as_text(written_tag, args);
+			return;
+		}
+		self.closing_tag = if tag.starts_with('/') {Some(tag.to_string())} else {None};
+		if let Some(handler) = self.registry.handler(tag) {
+			self.custom_open(tag, handler, args.as_deref());
+		} else if let (Some(handler), None) = (self.registry.handler(tag.trim_start_matches('/')), args) {
+			self.custom_close(tag.trim_start_matches('/'), handler);
+		} else {
+			match args {
+				Some(primary_arg) => {
+					match self.registry.one_arg_command(tag) {
+						Some(cmd) => cmd(self, primary_arg),
+						None => self.unknown_tag(written_tag, args)
+					}
+				},
+				None => {
+					match self.registry.no_arg_command(tag) {
+						Some(cmd) => cmd(self),
+						None => self.unknown_tag(written_tag, args)
+					}
 				}
+			}
+		}
+		self.closing_tag = None;
+	}
+	/// Writes a tag that will not be executed back out as text.
+	fn tag_as_text(&mut self, tag: &str, args: &Option<String>) {
+		match args {
+			Some(arg) => self.execute(&Instruction::Text(format!("[{}={}]", tag, arg))),
+			None => self.execute(&Instruction::Text(format!("[{}]", tag)))
+		}
+	}
+	/// Handles a tag missing from the TagRegistry, which is treated as text.
+	fn unknown_tag(&mut self, tag: &str, args: &Option<String>) {
+		let name = tag.trim_start_matches('/');
+		if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+			let form = if args.is_some() {"with an argument "} else {""};
+			self.diagnose(DiagnosticKind::UnknownTag, format!("`[{}]` is not a recognised tag {}and will be shown as text", tag, form));
+		}
+		self.tag_as_text(tag, args);
+	}
+	/// Checks whether a tag, in either form, is known to the TagRegistry.
+	fn is_registered(&self, tag: &str) -> bool {
+		self.registry.contains(tag) || self.registry.handler(tag.trim_start_matches('/')).is_some()
+	}
+	/// Checks whether a tag, as written with or without an argument, runs a command or TagHandler.
+	fn resolves(&self, tag: &str, args: &Option<String>) -> bool {
+		self.registry.handler(tag).is_some() || match args {
+			Some(_) => self.registry.one_arg_command(tag).is_some(),
+			None => self.registry.no_arg_command(tag).is_some() || self.registry.handler(tag.trim_start_matches('/')).is_some()
+		}
+	}
+	/// Checks whether a tag is closed by a closing tag of its own, unlike `[hr]` or `[*]`.
+	fn has_closing_tag(&self, tag: &str) -> bool {
+		self.registry.contains(&format!("/{}", tag)) || self.registry.handler(tag).is_some()
+	}
+	/// Checks whether opening a tag at the current node would exceed the maximum nesting depth or
+	/// quote depth, giving the Diagnostic's message if so. Top-level elements, such as paragraphs, have a depth of 1.
+	fn limit_exceeded(&self, tag: &str) -> Option<String> {
+		if self.max_depth.is_some_and(|max_depth| self.current_node.ancestors().count() > max_depth + 1) {
+			return Some(format!("`[{}]` is nested too deeply", tag));
+		}
+		let quote_depth = || self.current_node.ancestors()
+			.filter(|node| node.borrow().ele_type() == &GroupType::Quote)
+			.count();
+		match self.max_quote_depth {
+			Some(max_quote_depth) if tag == "quote" && quote_depth() >= max_quote_depth => {
+				Some(format!("`[quote]` is nested more than {} deep", max_quote_depth))
 			},
-			None => {
-				match NO_ARG_CMD.get(tag) {
-					Some(cmd) => cmd(self),
-					None => self.execute(&Instruction::Text(format!("[{}]", tag)))
+			_ => None
+		}
+	}
+	/// Checks whether the lexer has created the maximum number of elements.
+	fn node_limit_reached(&self) -> bool {
+		self.max_nodes.is_some_and(|max_nodes| self.node_count >= max_nodes)
+	}
+	/// Adds an Instruction to the current element as text, once the maximum number of elements has been created.
+	/// The text is merged into the current element's last child where possible, so that no more elements are needed.
+	fn execute_as_text(&mut self, instruction: &Instruction) {
+		self.next_text_as_arg = None;
+		if !self.node_limit_reported {
+			self.node_limit_reported = true;
+			self.diagnose(DiagnosticKind::LimitExceeded, format!("the input has more than {} elements, so the rest is shown as text", self.max_nodes.unwrap_or_default()));
+		}
+		let text = match instruction {
+			Instruction::Tag(tag, Some(arg)) => format!("[{}={}]", tag, arg),
+			Instruction::Tag(tag, None) => format!("[{}]", tag),
+			Instruction::Text(text) | Instruction::Parabreak(text) => text.clone(),
+			Instruction::Linebreak => "\n".to_string(),
+			Instruction::Scenebreak => "\n\n\n".to_string(),
+			Instruction::Null => return
+		};
+		match self.current_node.last_child() {
+			Some(mut last) if last.borrow().ele_type() == &GroupType::Text => {
+				last.borrow_mut().add_text(&text);
+				if let Some(span) = self.current_span {
+					last.borrow_mut().extend_span(span.end);
 				}
+			},
+			_ => {
+				self.new_group(GroupType::Text);
+				self.current_node.borrow_mut().add_text(&text);
+				self.end_group(GroupType::Text);
 			}
 		}
 	}
 
 	/*-- COMMANDS --*/
+	fn custom_open(&mut self, tag: &str, handler: Rc<dyn TagHandler>, arg: Option<&str>) {
+		let ele_type = GroupType::Custom(tag.to_string());
+		if !handler.validate_arg(arg) {
+			self.new_group(GroupType::Broken(Box::new(ele_type), tag.to_string().into()));
+			self.diagnose(DiagnosticKind::InvalidArgument, format!("`[{}]` has an invalid argument", tag));
+		} else if handler.is_block() {
+			self.end_and_new_group(GroupType::Paragraph, ele_type);
+		} else {
+			self.new_group(ele_type);
+		}
+		if let Some(arg) = arg {
+			self.current_node.borrow_mut().set_arg(arg);
+		}
+		if handler.is_block() && !self.current_node.borrow().is_broken() {
+			self.new_group(GroupType::Paragraph);
+		}
+	}
+	fn custom_close(&mut self, tag: &str, handler: Rc<dyn TagHandler>) {
+		let ele_type = GroupType::Custom(tag.to_string());
+		if handler.is_block() {
+			self.end_group(GroupType::Paragraph);
+			self.end_and_new_group(ele_type, GroupType::Paragraph);
+		} else {
+			self.end_group(ele_type);
+		}
+	}
+
 	fn cmd_bold_open(&mut self) {
 		self.new_group(GroupType::Bold);
 	}
@@ -413,7 +697,7 @@ impl BBCodeLexer {
 	fn cmd_h1_open(&mut self) {
 		self.end_group(GroupType::Paragraph);
 		self.new_group(GroupType::Header);
-		self.current_node.borrow_mut().set_arg(&"1".to_string());
+		self.current_node.borrow_mut().set_arg("1");
 	}
 	fn cmd_h1_close(&mut self) {
 		self.end_group(GroupType::Header);
@@ -422,7 +706,7 @@ impl BBCodeLexer {
 	fn cmd_h2_open(&mut self) {
 		self.end_group(GroupType::Paragraph);
 		self.new_group(GroupType::Header);
-		self.current_node.borrow_mut().set_arg(&"2".to_string());
+		self.current_node.borrow_mut().set_arg("2");
 	}
 	fn cmd_h2_close(&mut self) {
 		self.end_group(GroupType::Header);
@@ -431,7 +715,7 @@ impl BBCodeLexer {
 	fn cmd_h3_open(&mut self) {
 		self.end_group(GroupType::Paragraph);
 		self.new_group(GroupType::Header);
-		self.current_node.borrow_mut().set_arg(&"3".to_string());
+		self.current_node.borrow_mut().set_arg("3");
 	}
 	fn cmd_h3_close(&mut self) {
 		self.end_group(GroupType::Header);
@@ -440,7 +724,7 @@ impl BBCodeLexer {
 	fn cmd_h4_open(&mut self) {
 		self.end_group(GroupType::Paragraph);
 		self.new_group(GroupType::Header);
-		self.current_node.borrow_mut().set_arg(&"4".to_string());
+		self.current_node.borrow_mut().set_arg("4");
 	}
 	fn cmd_h4_close(&mut self) {
 		self.end_group(GroupType::Header);
@@ -449,7 +733,7 @@ impl BBCodeLexer {
 	fn cmd_h5_open(&mut self) {
 		self.end_group(GroupType::Paragraph);
 		self.new_group(GroupType::Header);
-		self.current_node.borrow_mut().set_arg(&"5".to_string());
+		self.current_node.borrow_mut().set_arg("5");
 	}
 	fn cmd_h5_close(&mut self) {
 		self.end_group(GroupType::Header);
@@ -458,7 +742,7 @@ impl BBCodeLexer {
 	fn cmd_h6_open(&mut self) {
 		self.end_group(GroupType::Paragraph);
 		self.new_group(GroupType::Header);
-		self.current_node.borrow_mut().set_arg(&"6".to_string());
+		self.current_node.borrow_mut().set_arg("6");
 	}
 	fn cmd_h6_close(&mut self) {
 		self.end_group(GroupType::Header);
@@ -476,32 +760,32 @@ impl BBCodeLexer {
 	}
 
 	fn cmd_colour_open(&mut self, arg: &str) {
-		if (arg.starts_with('#') && arg.len() == 7 || arg.len() == 4 
-		&& arg.trim_start_matches('#').chars().all(|c| c.is_ascii_hexdigit())) 
-		|| WEB_COLOURS.contains(arg) {
+		if let Some(colour) = Colour::parse(arg) {
 			self.new_group(GroupType::Colour);
-			self.current_node.borrow_mut().set_arg(arg);
+			self.current_node.borrow_mut().set_arg(&colour.to_string());
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::Colour), "colour"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::Colour), "colour".into()));
+			self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid colour", arg));
 			self.current_node.borrow_mut().set_arg(arg);
 		}
 	}
 	fn cmd_colour_bare_open(&mut self) {
-		self.new_group(GroupType::Broken(Box::new(GroupType::Colour), "colour"));
+		self.new_group(GroupType::Broken(Box::new(GroupType::Colour), "colour".into()));
+		self.diagnose(DiagnosticKind::MissingArgument, "`[colour]` needs a colour, such as `[colour=red]`".to_string());
 	}
 	fn cmd_color_open(&mut self, arg: &str) {
-		if (arg.starts_with('#') && arg.len() == 7 || arg.len() == 4 
-		&& arg.trim_start_matches('#').chars().all(|c| c.is_ascii_hexdigit())) 
-		|| WEB_COLOURS.contains(arg) {
+		if let Some(colour) = Colour::parse(arg) {
 			self.new_group(GroupType::Colour);
-			self.current_node.borrow_mut().set_arg(arg);
+			self.current_node.borrow_mut().set_arg(&colour.to_string());
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::Colour), "color"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::Colour), "color".into()));
+			self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid colour", arg));
 			self.current_node.borrow_mut().set_arg(arg);
 		}
 	}
 	fn cmd_color_bare_open(&mut self) {
-		self.new_group(GroupType::Broken(Box::new(GroupType::Colour), "color"));
+		self.new_group(GroupType::Broken(Box::new(GroupType::Colour), "color".into()));
+		self.diagnose(DiagnosticKind::MissingArgument, "`[color]` needs a colour, such as `[color=red]`".to_string());
 	}
 	fn cmd_colour_close(&mut self) {
 		self.end_group(GroupType::Colour);
@@ -512,45 +796,41 @@ impl BBCodeLexer {
 		self.new_group(GroupType::Url);
 	}
 	fn cmd_url_arg(&mut self, arg: &str) {
-		if arg.starts_with("https://") || arg.starts_with("http://") {
-			self.current_node.borrow_mut().set_arg(arg);
-		} else {
-			for c in arg.chars() {
-				if FORBIDDEN_URL_CHARS.contains(&c) {
-					if self.current_node.borrow().ele_type() == &GroupType::Url {
-						self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Url), "url"));
-					} else {
-						self.new_group(GroupType::Broken(Box::new(GroupType::Url), "url"));
-					}
-					self.current_node.borrow_mut().add_text(arg);
-					return;
+		match self.url_policy.resolve(arg) {
+			Ok(url) => {
+				self.current_node.borrow_mut().set_arg(&url);
+				self.new_group(GroupType::Text);
+				self.current_node.borrow_mut().add_text(arg);
+				self.end_group(GroupType::Text);
+			},
+			Err(error) => {
+				if self.current_node.borrow().ele_type() == &GroupType::Url {
+					self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Url), "url".into()));
+				} else {
+					self.new_group(GroupType::Broken(Box::new(GroupType::Url), "url".into()));
 				}
+				self.current_node.borrow_mut().add_text(arg);
+				self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid URL: {}", arg, error));
 			}
-			self.current_node.borrow_mut().set_arg(&format!("http://{}", arg));
 		}
-		self.new_group(GroupType::Text);
-		self.current_node.borrow_mut().add_text(arg);
-		self.end_group(GroupType::Text);
 	}
 	fn cmd_url_open(&mut self, arg: &str) {
-		if arg.starts_with("https://") || arg.starts_with("http://") {
-			self.new_group(GroupType::Url);
-			self.current_node.borrow_mut().set_arg(arg);
-		} else {
-			for c in arg.chars() {
-				if FORBIDDEN_URL_CHARS.contains(&c) {
-					self.new_group(GroupType::Broken(Box::new(GroupType::Url), "url"));
-					self.current_node.borrow_mut().set_arg(arg);
-					return;
-				}
+		match self.url_policy.resolve(arg) {
+			Ok(url) => {
+				self.new_group(GroupType::Url);
+				self.current_node.borrow_mut().set_arg(&url);
+			},
+			Err(error) => {
+				self.new_group(GroupType::Broken(Box::new(GroupType::Url), "url".into()));
+				self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid URL: {}", arg, error));
+				self.current_node.borrow_mut().set_arg(arg);
 			}
-			self.new_group(GroupType::Url);
-			self.current_node.borrow_mut().set_arg(&format!("http://{}", arg));
 		}
 	}
 	fn cmd_url_close(&mut self) {
 		if !self.current_node.borrow().has_arg() && self.current_node.borrow().ele_type() == &GroupType::Url {
-			self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Url), "url"));
+			self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Url), "url".into()));
+			self.diagnose(DiagnosticKind::MissingArgument, "`[url]` has no URL".to_string());
 			self.current_node.borrow_mut().set_detachable(false);
 		} 
 		self.end_group(GroupType::Url);
@@ -561,14 +841,25 @@ impl BBCodeLexer {
 		self.new_group(GroupType::Email);
 	}
 	fn cmd_email_arg(&mut self, arg: &str) {
-		self.current_node.borrow_mut().set_arg(&format!("mailto:{}", arg));
-		self.new_group(GroupType::Text);
-		self.current_node.borrow_mut().add_text(arg);
-		self.end_group(GroupType::Text);
+		if is_valid_email(arg) {
+			self.current_node.borrow_mut().set_arg(&format!("mailto:{}", arg));
+			self.new_group(GroupType::Text);
+			self.current_node.borrow_mut().add_text(arg);
+			self.end_group(GroupType::Text);
+		} else {
+			if self.current_node.borrow().ele_type() == &GroupType::Email {
+				self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Email), "email".into()));
+			} else {
+				self.new_group(GroupType::Broken(Box::new(GroupType::Email), "email".into()));
+			}
+			self.current_node.borrow_mut().add_text(arg);
+			self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid email address", arg));
+		}
 	}
 	fn cmd_email_close(&mut self) {
 		if !self.current_node.borrow().has_arg() && self.current_node.borrow().ele_type() == &GroupType::Email {
-			self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Email), "email"));
+			self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Email), "email".into()));
+			self.diagnose(DiagnosticKind::MissingArgument, "`[email]` has no address".to_string());
 			self.current_node.borrow_mut().set_detachable(false);
 		} 
 		self.end_group(GroupType::Email);
@@ -579,152 +870,93 @@ impl BBCodeLexer {
 		self.new_group(GroupType::Image);
 	}
 	fn cmd_img_arg(&mut self, arg: &str) {
-		if arg.starts_with("https://") || arg.starts_with("http://") {
-			if let Some(index) = arg.rfind('.') {
-				if let Some(suffix) = arg.get(index..) {
-					if ACCEPTED_IMAGE_TYPES.contains(suffix) {
-						self.new_group(GroupType::Image);
-						self.current_node.borrow_mut().set_void(true);
-						self.current_node.borrow_mut().set_arg(arg);
-						self.end_group(GroupType::Image);
-					} else {
-						if self.current_node.borrow().ele_type() == &GroupType::Image {
-							self.end_group(GroupType::Image);
-						}
-						self.new_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-						self.current_node.borrow_mut().add_text(arg);
-						self.end_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-					}
-				} else {
-					if self.current_node.borrow().ele_type() == &GroupType::Image {
-						self.end_group(GroupType::Image);
-					}
-					self.new_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-					self.current_node.borrow_mut().add_text(arg);
-					self.end_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-				}
-			} else {
+		let image_url = self.media_url(arg).and_then(|url| {
+			if is_accepted_image(&url) {Ok(url.to_string())} else {Err("it is not an accepted image type".to_string())}
+		});
+		match image_url {
+			Ok(url) => {
+				self.new_group(GroupType::Image);
+				self.current_node.borrow_mut().set_void(true);
+				self.current_node.borrow_mut().set_arg(&url);
+				self.end_group(GroupType::Image);
+			},
+			Err(error) => {
 				if self.current_node.borrow().ele_type() == &GroupType::Image {
 					self.end_group(GroupType::Image);
 				}
-				self.new_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
+				self.new_group(GroupType::Broken(Box::new(GroupType::Image), "img".into()));
+				self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid image URL: {}", arg, error));
 				self.current_node.borrow_mut().add_text(arg);
-				self.end_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
+				self.end_group(GroupType::Broken(Box::new(GroupType::Image), "img".into()));
 			}
+		}
+	}
+	/// Checks the URL of an image or embed against the UrlPolicy and MediaPolicy, giving the reason if it is not accepted.
+	fn media_url(&self, arg: &str) -> Result<Url, String> {
+		let url = self.url_policy.check(arg).map_err(|error| error.to_string())?;
+		if self.media_policy.permits(&url) {
+			Ok(url)
 		} else {
-			for c in arg.chars() {
-				if FORBIDDEN_URL_CHARS.contains(&c) {
-					if self.current_node.borrow().ele_type() == &GroupType::Image {
-						self.end_group(GroupType::Image);
-					}
-					self.new_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-					self.current_node.borrow_mut().add_text(arg);
-					self.end_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-					return;
-				}
-			}
-			if let Some(index) = arg.rfind('.') {
-				if let Some(suffix) = arg.get(index..) {
-					if ACCEPTED_IMAGE_TYPES.contains(suffix) {
-						self.new_group(GroupType::Image);
-						self.current_node.borrow_mut().set_void(true);
-						self.current_node.borrow_mut().set_arg(&format!("http://{}", arg));
-						self.end_group(GroupType::Image);
-					} else {
-						if self.current_node.borrow().ele_type() == &GroupType::Image {
-							self.end_group(GroupType::Image);
-						}
-						self.new_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-						self.current_node.borrow_mut().add_text(arg);
-						self.end_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-					}
-				} else {
-					if self.current_node.borrow().ele_type() == &GroupType::Image {
-						self.end_group(GroupType::Image);
-					}
-					self.new_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-					self.current_node.borrow_mut().add_text(arg);
-					self.end_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-				}
-			} else {
-				if self.current_node.borrow().ele_type() == &GroupType::Image {
-					self.end_group(GroupType::Image);
-				}
-				self.new_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-				self.current_node.borrow_mut().add_text(arg);
-				self.end_group(GroupType::Broken(Box::new(GroupType::Image), "img"));
-			}
+			Err(format!("content from `{}` is not allowed", url.host().unwrap_or_default()))
 		}
 	}
 	fn cmd_img_close(&mut self) {
-		if !self.current_node.borrow().has_arg() && self.current_node.borrow().ele_type() == &GroupType::Image {
-			self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Image), "img"));
+		if !self.current_node.borrow().has_arg() && self.current_node.borrow().ele_type() == &GroupType::Image
+		&& !self.current_node.has_children() {
+			self.current_node.borrow_mut().set_ele_type(GroupType::Broken(Box::new(GroupType::Image), "img".into()));
+			self.diagnose(DiagnosticKind::MissingArgument, "`[img]` has no image URL".to_string());
 			self.current_node.borrow_mut().set_detachable(false);
 		} 
 		self.end_group(GroupType::Image);
 	}
 
 	fn cmd_opacity_open(&mut self, arg: &str) {
-		let mut divisor = 1.0;
-		let arg_string;
-		if arg.ends_with('%') {
-			arg_string = arg.trim_end_matches('%');
-			divisor = 100.0;
-		} else {
-			arg_string = arg;
-		}
-		match arg_string.parse::<f32>() {
-			Ok(mut val) => {
-				val /= divisor;
-				if val < 0.0 {
-					val = 0.0;
-				} else if val > 1.0 {
-					val = 1.0;
-				}
+		let (arg_string, divisor) = match arg.strip_suffix('%') {
+			Some(percent) => (percent, 100.0),
+			None => (arg, 1.0)
+		};
+		match parse_decimal(arg_string) {
+			Some(mut val) => {
+				val = (val / divisor).clamp(0.0, 1.0);
 				self.new_group(GroupType::Opacity);
 				self.current_node.borrow_mut().set_arg(&val.to_string());
 			}
-			Err(_) => {
-				self.new_group(GroupType::Broken(Box::new(GroupType::Opacity), "opacity"));
+			None => {
+				self.new_group(GroupType::Broken(Box::new(GroupType::Opacity), "opacity".into()));
+				self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid opacity", arg));
 				self.current_node.borrow_mut().set_arg(arg);
 			}
 		}
 	}
 	fn cmd_opacity_bare_open(&mut self) {
-		self.new_group(GroupType::Broken(Box::new(GroupType::Opacity), "opacity"));
+		self.new_group(GroupType::Broken(Box::new(GroupType::Opacity), "opacity".into()));
+		self.diagnose(DiagnosticKind::MissingArgument, "`[opacity]` needs a value, such as `[opacity=50%]`".to_string());
 	}
 	fn cmd_opacity_close(&mut self) {
 		self.end_group(GroupType::Opacity);
 	}
 
 	fn cmd_size_open(&mut self, arg: &str) {
-		let mut divisor = 1.0;
-		let arg_string;
-		if arg.ends_with("em") {
-			arg_string = arg.trim_end_matches("em");
-		} else {
-			arg_string = arg;
-			divisor = 16.0;
-		}
-		match arg_string.parse::<f32>() {
-			Ok(mut val) => {
-				val /= divisor;
-				if val < 0.5 {
-					val = 0.5;
-				} else if val > 2.0 {
-					val = 2.0;
-				}
+		let (arg_string, divisor) = match arg.strip_suffix("em") {
+			Some(em) => (em, 1.0),
+			None => (arg, 16.0)
+		};
+		match parse_decimal(arg_string) {
+			Some(mut val) => {
+				val = (val / divisor).clamp(0.5, 2.0);
 				self.new_group(GroupType::Size);
 				self.current_node.borrow_mut().set_arg(&val.to_string());
 			}
-			Err(_) => {
-				self.new_group(GroupType::Broken(Box::new(GroupType::Size), "size"));
+			None => {
+				self.new_group(GroupType::Broken(Box::new(GroupType::Size), "size".into()));
+				self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid size", arg));
 				self.current_node.borrow_mut().set_arg(arg);
 			}
 		}
 	}
 	fn cmd_size_bare_open(&mut self) {
-		self.new_group(GroupType::Broken(Box::new(GroupType::Size), "size"));
+		self.new_group(GroupType::Broken(Box::new(GroupType::Size), "size".into()));
+		self.diagnose(DiagnosticKind::MissingArgument, "`[size]` needs a value, such as `[size=1.5em]`".to_string());
 	}
 	fn cmd_size_close(&mut self) {
 		self.end_group(GroupType::Size);
@@ -740,7 +972,9 @@ impl BBCodeLexer {
 		self.new_group(GroupType::Paragraph);
 	}
 	fn cmd_quote_close(&mut self) {
-		self.end_group(GroupType::Paragraph);
+		if self.current_node.borrow().ele_type() == &GroupType::Paragraph {
+			self.end_group(GroupType::Paragraph);
+		}
 		self.end_group(GroupType::Quote);
 	}
 
@@ -787,7 +1021,8 @@ impl BBCodeLexer {
 			self.current_node.borrow_mut().set_arg(arg);
 			self.new_group(GroupType::Paragraph);
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::Figure), "figure"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::Figure), "figure".into()));
+			self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid figure position; use `left` or `right`", arg));
 			self.current_node.borrow_mut().set_arg(arg);
 		}
 	}
@@ -802,17 +1037,15 @@ impl BBCodeLexer {
 		self.current_node.borrow_mut().set_void(true);
 	}
 	fn cmd_embed_arg(&mut self, arg: &str) {
-		if arg.starts_with("https://") || arg.starts_with("http://") {
-			self.current_node.borrow_mut().set_arg(arg);
-		} else {
-			for c in arg.chars() {
-				if FORBIDDEN_URL_CHARS.contains(&c) {
-					self.new_group(GroupType::Broken(Box::new(GroupType::Embed), "embed"));
-					self.current_node.borrow_mut().set_arg(arg);
-					return;
-				}
+		match self.media_url(arg) {
+			Ok(url) => {
+				self.current_node.borrow_mut().set_arg(url.as_str());
+			},
+			Err(error) => {
+				self.new_group(GroupType::Broken(Box::new(GroupType::Embed), "embed".into()));
+				self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid embed URL: {}", arg, error));
+				self.current_node.borrow_mut().set_arg(arg);
 			}
-			self.current_node.borrow_mut().set_arg(&format!("http://{}", arg));
 		}
 	}
 	fn cmd_embed_close(&mut self) {
@@ -829,11 +1062,20 @@ impl BBCodeLexer {
 			self.current_node.borrow_mut().set_arg(arg);
 			self.linebreaks_allowed = false;
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::List), "list"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::List), "list".into()));
+			self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid list type", arg));
 			self.current_node.borrow_mut().set_arg(arg);
 		}
 	}
 	fn cmd_list_close(&mut self) {
+		let in_item = self.current_node.parent()
+			.is_some_and(|parent| parent.borrow().ele_type() == &GroupType::ListItem);
+		if in_item && self.current_node.borrow().ele_type() == &GroupType::Paragraph {
+			self.end_group(GroupType::Paragraph);
+		}
+		if self.current_node.borrow().ele_type() == &GroupType::ListItem {
+			self.end_group(GroupType::ListItem);
+		}
 		self.end_and_new_group(GroupType::List, GroupType::Paragraph);
 		self.linebreaks_allowed = true;
 	}
@@ -849,11 +1091,13 @@ impl BBCodeLexer {
 				self.end_and_new_group(GroupType::ListItem, GroupType::ListItem);
 				self.new_group(GroupType::Paragraph);
 			} else {
-				self.new_group(GroupType::Broken(Box::new(GroupType::ListItem), "*"));
+				self.new_group(GroupType::Broken(Box::new(GroupType::ListItem), "*".into()));
+				self.diagnose(DiagnosticKind::MisplacedListItem, "`[*]` must be inside a `[list]`".to_string());
 				self.current_node.borrow_mut().set_void(true);
 			}
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::ListItem), "*"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::ListItem), "*".into()));
+			self.diagnose(DiagnosticKind::MisplacedListItem, "`[*]` must be inside a `[list]`".to_string());
 			self.current_node.borrow_mut().set_void(true);
 		}	
 	}
@@ -870,7 +1114,8 @@ impl BBCodeLexer {
 		if self.current_node.borrow().ele_type() == &GroupType::Table {
 			self.new_group(GroupType::TableRow);
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::TableRow), "tr"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::TableRow), "tr".into()));
+			self.diagnose(DiagnosticKind::MisplacedTableElement, "`[tr]` must be inside a `[table]`".to_string());
 		}	
 	}
 	fn cmd_table_row_close(&mut self) {
@@ -881,7 +1126,8 @@ impl BBCodeLexer {
 			self.new_group(GroupType::TableHeader);
 			self.new_group(GroupType::Paragraph);
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::TableHeader), "th"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::TableHeader), "th".into()));
+			self.diagnose(DiagnosticKind::MisplacedTableElement, "`[th]` must be inside a `[tr]`".to_string());
 		}
 	}
 	fn cmd_table_header_close(&mut self) {
@@ -895,7 +1141,8 @@ impl BBCodeLexer {
 			self.new_group(GroupType::TableData);
 			self.new_group(GroupType::Paragraph);
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::TableData), "td"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::TableData), "td".into()));
+			self.diagnose(DiagnosticKind::MisplacedTableElement, "`[td]` must be inside a `[tr]`".to_string());
 		}
 	}
 	fn cmd_table_data_close(&mut self) {
@@ -909,7 +1156,8 @@ impl BBCodeLexer {
 			self.new_group(GroupType::TableCaption);
 			self.new_group(GroupType::Paragraph);
 		} else {
-			self.new_group(GroupType::Broken(Box::new(GroupType::TableCaption), "caption"));
+			self.new_group(GroupType::Broken(Box::new(GroupType::TableCaption), "caption".into()));
+			self.diagnose(DiagnosticKind::MisplacedTableElement, "`[caption]` must be inside a `[table]`".to_string());
 		}	
 	}
 	fn cmd_table_caption_close(&mut self) {
@@ -966,17 +1214,21 @@ impl BBCodeLexer {
 				self.new_group(GroupType::Paragraph);
 			},
 			_ => {
-				self.new_group(GroupType::Broken(Box::new(GroupType::Indent), "indent"));
+				self.new_group(GroupType::Broken(Box::new(GroupType::Indent), "indent".into()));
+				self.diagnose(DiagnosticKind::InvalidArgument, format!("`{}` is not a valid indent level; use 1 to 4", arg));
 				self.current_node.borrow_mut().set_arg(arg);
 			},
 		}
 	}
 	fn cmd_indent_bare_open(&mut self) {
 		self.end_and_new_group(GroupType::Paragraph, GroupType::Indent);
-		self.current_node.borrow_mut().set_arg(&"1".to_string());
+		self.current_node.borrow_mut().set_arg("1");
 		self.new_group(GroupType::Paragraph);
 	}
-	fn cmd_indent_close(&mut self) {		
+	fn cmd_indent_close(&mut self) {
+		if self.current_node.borrow().ele_type() == &GroupType::Paragraph {
+			self.end_group(GroupType::Paragraph);
+		}
 		self.end_and_new_group(GroupType::Indent, GroupType::Paragraph);
 	}
 
@@ -998,8 +1250,8 @@ impl BBCodeLexer {
 		self.end_and_new_group(GroupType::Right, GroupType::Paragraph);
 	}
 }
-/// Static compile-time map of tags without arguments to lexer commands.
-static NO_ARG_CMD: phf::Map<&'static str, fn(&mut BBCodeLexer)> = phf_map! {
+/// Static compile-time map of tags without arguments to lexer commands. Used to build the default TagRegistry.
+pub(crate) static NO_ARG_CMD: phf::Map<&'static str, fn(&mut BBCodeLexer)> = phf_map! {
     "b" => BBCodeLexer::cmd_bold_open,
     "/b" => BBCodeLexer::cmd_bold_close,
     "i" => BBCodeLexer::cmd_italic_open,
@@ -1088,8 +1340,8 @@ static NO_ARG_CMD: phf::Map<&'static str, fn(&mut BBCodeLexer)> = phf_map! {
 	"email" => BBCodeLexer::cmd_email_open,
 	"/email" => BBCodeLexer::cmd_email_close,
 };
-/// Static compile-time map of tags with single arguments to lexer commands.
-static ONE_ARG_CMD: phf::Map<&'static str, fn(&mut BBCodeLexer, &str)> = phf_map! {
+/// Static compile-time map of tags with single arguments to lexer commands. Used to build the default TagRegistry.
+pub(crate) static ONE_ARG_CMD: phf::Map<&'static str, fn(&mut BBCodeLexer, &str)> = phf_map! {
     "color" => BBCodeLexer::cmd_color_open,
 	"colour" => BBCodeLexer::cmd_colour_open,
 	"url" => BBCodeLexer::cmd_url_open,
@@ -1102,308 +1354,6 @@ static ONE_ARG_CMD: phf::Map<&'static str, fn(&mut BBCodeLexer, &str)> = phf_map
 	"list" => BBCodeLexer::cmd_list_open,
 	"indent" => BBCodeLexer::cmd_indent_open,
 };
-/// Static compile-time set of valid HTML web colours.
-static WEB_COLOURS: phf::Set<&'static str> = phf_set! {
-	"aliceblue",
-    "antiquewhite",
-    "aqua",
-    "aquamarine",
-    "azure",
-    "beige",
-    "bisque",
-    "black",
-    "blanchedalmond",
-    "blue",
-    "blueviolet",
-    "brown",
-    "burlywood",
-    "cadetblue",
-    "chartreuse",
-    "chocolate",
-    "coral",
-    "cornflowerblue",
-    "cornsilk",
-    "crimson",
-    "cyan",
-    "darkblue",
-    "darkcyan",
-    "darkgoldenrod",
-    "darkgray",
-    "darkgrey",
-    "darkgreen",
-    "darkkhaki",
-    "darkmagenta",
-    "darkolivegreen",
-    "darkorange",
-    "darkorchid",
-    "darkred",
-    "darksalmon",
-    "darkseagreen",
-    "darkslateblue",
-    "darkslategray",
-    "darkslategrey",
-    "darkturquoise",
-    "darkviolet",
-    "deeppink",
-    "deepskyblue",
-    "dimgray",
-    "dimgrey",
-    "dodgerblue",
-    "firebrick",
-    "floralwhite",
-    "forestgreen",
-    "fuchsia",
-    "gainsboro",
-    "ghostwhite",
-    "gold",
-    "goldenrod",
-    "gray",
-    "grey",
-    "green",
-    "greenyellow",
-    "honeydew",
-    "hotpink",
-    "indianred ",
-    "indigo ",
-    "ivory",
-    "khaki",
-    "lavender",
-    "lavenderblush",
-    "lawngreen",
-    "lemonchiffon",
-    "lightblue",
-    "lightcoral",
-    "lightcyan",
-    "lightgoldenrodyellow",
-    "lightgray",
-    "lightgrey",
-    "lightgreen",
-    "lightpink",
-    "lightsalmon",
-    "lightseagreen",
-    "lightskyblue",
-    "lightslategray",
-    "lightslategrey",
-    "lightsteelblue",
-    "lightyellow",
-    "lime",
-    "limegreen",
-    "linen",
-    "magenta",
-    "maroon",
-    "mediumaquamarine",
-    "mediumblue",
-    "mediumorchid",
-    "mediumpurple",
-    "mediumseagreen",
-    "mediumslateblue",
-    "mediumspringgreen",
-    "mediumturquoise",
-    "mediumvioletred",
-    "midnightblue",
-    "mintcream",
-    "mistyrose",
-    "moccasin",
-    "navajowhite",
-    "navy",
-    "oldlace",
-    "olive",
-    "olivedrab",
-    "orange",
-    "orangered",
-    "orchid",
-    "palegoldenrod",
-    "palegreen",
-    "paleturquoise",
-    "palevioletred",
-    "papayawhip",
-    "peachpuff",
-    "peru",
-    "pink",
-    "plum",
-    "powderblue",
-    "purple",
-    "rebeccapurple",
-    "red",
-    "rosybrown",
-    "royalblue",
-    "saddlebrown",
-    "salmon",
-    "sandybrown",
-    "seagreen",
-    "seashell",
-    "sienna",
-    "silver",
-    "skyblue",
-    "slateblue",
-    "slategray",
-    "slategrey",
-    "snow",
-    "springgreen",
-    "steelblue",
-    "tan",
-    "teal",
-    "thistle",
-    "tomato",
-    "turquoise",
-    "transparant",
-    "violet",
-    "wheat",
-    "white",
-    "whitesmoke",
-    "yellow",
-    "yellowgreen",
-    "Aliceblue",
-    "Antiquewhite",
-    "Aqua",
-    "Aquamarine",
-    "Azure",
-    "Beige",
-    "Bisque",
-    "Black",
-    "Blanchedalmond",
-    "Blue",
-    "Blueviolet",
-    "Brown",
-    "Burlywood",
-    "Cadetblue",
-    "Chartreuse",
-    "Chocolate",
-    "Coral",
-    "Cornflowerblue",
-    "Cornsilk",
-    "Crimson",
-    "Cyan",
-    "Darkblue",
-    "Darkcyan",
-    "Darkgoldenrod",
-    "Darkgray",
-    "Darkgrey",
-    "Darkgreen",
-    "Darkkhaki",
-    "Darkmagenta",
-    "Darkolivegreen",
-    "Darkorange",
-    "Darkorchid",
-    "Darkred",
-    "Darksalmon",
-    "Darkseagreen",
-    "Darkslateblue",
-    "Darkslategray",
-    "Darkslategrey",
-    "Darkturquoise",
-    "Darkviolet",
-    "Deeppink",
-    "Deepskyblue",
-    "Dimgray",
-    "Dimgrey",
-    "Dodgerblue",
-    "Firebrick",
-    "Floralwhite",
-    "Forestgreen",
-    "Fuchsia",
-    "Gainsboro",
-    "Ghostwhite",
-    "Gold",
-    "Goldenrod",
-    "Gray",
-    "Grey",
-    "Green",
-    "Greenyellow",
-    "Honeydew",
-    "Hotpink",
-    "Indianred ",
-    "Indigo ",
-    "Ivory",
-    "Khaki",
-    "Lavender",
-    "Lavenderblush",
-    "Lawngreen",
-    "Lemonchiffon",
-    "Lightblue",
-    "Lightcoral",
-    "Lightcyan",
-    "Lightgoldenrodyellow",
-    "Lightgray",
-    "Lightgrey",
-    "Lightgreen",
-    "Lightpink",
-    "Lightsalmon",
-    "Lightseagreen",
-    "Lightskyblue",
-    "Lightslategray",
-    "Lightslategrey",
-    "Lightsteelblue",
-    "Lightyellow",
-    "Lime",
-    "Limegreen",
-    "Linen",
-    "Magenta",
-    "Maroon",
-    "Mediumaquamarine",
-    "Mediumblue",
-    "Mediumorchid",
-    "Mediumpurple",
-    "Mediumseagreen",
-    "Mediumslateblue",
-    "Mediumspringgreen",
-    "Mediumturquoise",
-    "Mediumvioletred",
-    "Midnightblue",
-    "Mintcream",
-    "Mistyrose",
-    "Moccasin",
-    "Navajowhite",
-    "Navy",
-    "Oldlace",
-    "Olive",
-    "Olivedrab",
-    "Orange",
-    "Orangered",
-    "Orchid",
-    "Palegoldenrod",
-    "Palegreen",
-    "Paleturquoise",
-    "Palevioletred",
-    "Papayawhip",
-    "Peachpuff",
-    "Peru",
-    "Pink",
-    "Plum",
-    "Powderblue",
-    "Purple",
-    "Rebeccapurple",
-    "Red",
-    "Rosybrown",
-    "Royalblue",
-    "Saddlebrown",
-    "Salmon",
-    "Sandybrown",
-    "Seagreen",
-    "Seashell",
-    "Sienna",
-    "Silver",
-    "Skyblue",
-    "Slateblue",
-    "Slategray",
-    "Slategrey",
-    "Snow",
-    "Springgreen",
-    "Steelblue",
-    "Tan",
-    "Teal",
-    "Thistle",
-    "Tomato",
-    "Turquoise",
-    "Transparant",
-    "Violet",
-    "Wheat",
-    "White",
-    "Whitesmoke",
-    "Yellow",
-    "Yellowgreen"
-};
-
 /// Static compile-time set of accepted image types.
 static ACCEPTED_IMAGE_TYPES: phf::Set<&'static str> = phf_set! {
 	".jpg",
@@ -1419,24 +1369,8 @@ static ACCEPTED_IMAGE_TYPES: phf::Set<&'static str> = phf_set! {
 	".webp",
 };
 
-/// Static compile-time set of forbidden URL characters.
-static FORBIDDEN_URL_CHARS: phf::Set<char> = phf_set! {
-	':',
-	';',
-	'*',
-	'#',
-	'{',
-	'}',
-	'|',
-	'^',
-	'~',
-	'[',
-	']',
-	'`',
-};
-
 /// Static compile-time set of accepted list types.
-static LIST_TYPES: phf::Set<&'static str> = phf_set! {
+pub(crate) static LIST_TYPES: phf::Set<&'static str> = phf_set! {
 	"1",
 	"a",
 	"A",
@@ -1451,4 +1385,28 @@ static LIST_TYPES: phf::Set<&'static str> = phf_set! {
 pub struct GroupShorthand {
 	pub ele_type: GroupType,
 	pub arg: Option<String>
+}
+
+/// Gets whether an address has a non-empty local part and domain, separated by the only `@`, and no
+/// whitespace or control characters.
+fn is_valid_email(address: &str) -> bool {
+	match address.split_once('@') {
+		Some((local, domain)) => !local.is_empty() && !domain.is_empty() && !domain.contains('@')
+			&& !address.chars().any(|c| c.is_whitespace() || c.is_control()),
+		None => false
+	}
+}
+
+/// Gets whether a URL's path ends with the suffix of an accepted image type.
+pub(crate) fn is_accepted_image(url: &Url) -> bool {
+	let path = url.path().to_ascii_lowercase();
+	path.rfind('.').and_then(|index| path.get(index..)).is_some_and(|suffix| ACCEPTED_IMAGE_TYPES.contains(suffix))
+}
+
+/// Gets the name a tag is tracked by, so that `[color]` is closed by `[/colour]`.
+pub(crate) fn canonical_tag(tag: &str) -> &str {
+	match tag {
+		"color" => "colour",
+		tag => tag
+	}
 }
\ No newline at end of file
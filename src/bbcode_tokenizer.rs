@@ -1,8 +1,9 @@
-use super::Instruction;
+use super::{Instruction, Span};
 
 /// Tokenizer modes.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Default)]
 enum ReadMode {
+	#[default]
 	Text,
 	Escape,
 	Tag,
@@ -10,8 +11,26 @@ enum ReadMode {
 	Parabreak,
 	Scenebreak,
 }
-impl Default for ReadMode {
-    fn default() -> Self {ReadMode::Text}
+
+/// A position in the BBCode input.
+#[derive(Debug, Clone, Copy)]
+struct Cursor {
+	offset: usize,
+	line: usize,
+	column: usize,
+}
+impl Default for Cursor {
+	fn default() -> Self {Cursor {offset: 0, line: 1, column: 1}}
+}
+impl Cursor {
+	/// Gets the position after the given character, which starts at this position.
+	fn advance(self, character: char) -> Cursor {
+		if character == '\n' {
+			Cursor {offset: self.offset + 1, line: self.line + 1, column: 1}
+		} else {
+			Cursor {offset: self.offset + character.len_utf8(), line: self.line, column: self.column + 1}
+		}
+	}
 }
 
 /// Struct for BBCode tokenization.
@@ -19,7 +38,11 @@ impl Default for ReadMode {
 pub struct BBCodeTokenizer {
 	mode: ReadMode,
 	current_instruction: Instruction,
-	instructions: Vec<Instruction>
+	instructions: Vec<Instruction>,
+	spans: Vec<Span>,
+	instruction_start: Cursor,
+	here: Cursor,
+	next: Cursor
 }
 impl BBCodeTokenizer {
 	/// Creates a new BBCodeTokenizer
@@ -30,6 +53,8 @@ impl BBCodeTokenizer {
 	pub fn tokenize(&mut self, bbcode: &str) -> &Vec<Instruction> {
 		let bbcode_chars = bbcode.chars();
 		for character in bbcode_chars {
+			self.here = self.next;
+			self.next = self.here.advance(character);
 			match &self.mode {
 				ReadMode::Text => {self.parse_text(character);},
 				ReadMode::Escape => {self.parse_escape(character);},
@@ -39,9 +64,17 @@ impl BBCodeTokenizer {
 				ReadMode::Scenebreak => {self.parse_scenebreak(character);},
 			}
 		}
-		self.set_cur_instruction();
+		self.set_cur_instruction(self.next);
+		&self.instructions
+	}
+	/// Gets the Instructions tokenized so far.
+	pub fn instructions(&self) -> &[Instruction] {
 		&self.instructions
 	}
+	/// Gets the Spans of the input that each tokenized Instruction came from, in the same order.
+	pub fn spans(&self) -> &[Span] {
+		&self.spans
+	}
 	/// s characters.
 	fn parse_text(&mut self, character: char) {
 		match character {
@@ -49,41 +82,21 @@ impl BBCodeTokenizer {
 				self.mode = ReadMode::Escape
 			},
 			'[' => {
-				self.set_cur_instruction();
+				self.set_cur_instruction(self.here);
 				self.mode = ReadMode::Tag;
 			},
 			'\n' | '\r' => {
-				self.set_cur_instruction();
+				self.set_cur_instruction(self.here);
 				self.mode = ReadMode::Parabreak;
 			},
-			'>' | '<' | '&' | '"' | '\'' => {
-				let san_char = self.sanitize(character);
-				match self.current_instruction {
-					Instruction::Text(ref mut contents) => {
-						contents.push_str(&san_char);
-					},
-					_ => {
-						self.current_instruction = Instruction::Text(san_char);
-					}
-				}
-			},
-			_ => {
-				match self.current_instruction {
-					Instruction::Text(ref mut contents) => {
-						contents.push(character);
-					},
-					_ => {
-						self.current_instruction = Instruction::Text(character.to_string());
-					}
-				}
-			}
+			_ => {self.push_text(character)}
 		}
 	}
 	/// s paragraph breaks.
 	fn parse_parabreak(&mut self, character: char) {
 		match character {
 			'\t' => {
-				self.set_new_instruction(Instruction::Parabreak("\n\t".to_string()));
+				self.set_new_instruction(Instruction::Parabreak("\n\t".to_string()), self.next);
 				self.mode = ReadMode::Text;
 			},
 			'\n' | '\r' => {
@@ -91,7 +104,7 @@ impl BBCodeTokenizer {
 			},
 			' ' => {},
 			_ => {
-				self.set_new_instruction(Instruction::Linebreak);
+				self.set_new_instruction(Instruction::Linebreak, self.here);
 				self.mode = ReadMode::Text;
 				self.parse_text(character);
 			}
@@ -102,12 +115,12 @@ impl BBCodeTokenizer {
 	fn parse_scenebreak(&mut self, character: char) {
 		match character {
 			'\n' | '\r' => {
-				self.set_new_instruction(Instruction::Scenebreak);
+				self.set_new_instruction(Instruction::Scenebreak, self.next);
 				self.mode = ReadMode::Text;
 			},
 			' ' => {},
 			_ => {
-				self.set_new_instruction(Instruction::Parabreak("\n\n".to_string()));
+				self.set_new_instruction(Instruction::Parabreak("\n\n".to_string()), self.here);
 				self.mode = ReadMode::Text;
 				self.parse_text(character);
 			}
@@ -117,51 +130,29 @@ impl BBCodeTokenizer {
 	/// s escaped charcters.
 	fn parse_escape(&mut self, character: char) {
 		self.mode = ReadMode::Text;
-		match character {
-			'>' | '<' | '&' | '"' | '\'' | '\\' => {
-				let san_char = self.sanitize(character);
-				match self.current_instruction {
-					Instruction::Tag(ref mut contents, _) => {
-						contents.push_str(&san_char);
-					},
-					_ => {
-						self.current_instruction = Instruction::Text(san_char);
-					}
-				}
+		self.push_text(character);
+	}
+	/// Adds a character to the current text Instruction, starting one if necessary.
+	fn push_text(&mut self, character: char) {
+		match self.current_instruction {
+			Instruction::Text(ref mut contents) => {
+				contents.push(character);
 			},
 			_ => {
-				match self.current_instruction {
-					Instruction::Text(ref mut contents) => {
-						contents.push(character);
-					},
-					_ => {
-						self.current_instruction = Instruction::Text(character.to_string());
-					}
-				}
+				self.current_instruction = Instruction::Text(character.to_string());
 			}
-		}	
+		}
 	}
 	/// s BBCode tags.
 	fn parse_tag(&mut self, character: char) {
 		match character {
 			']' => {
-				self.set_cur_instruction();
+				self.set_cur_instruction(self.next);
 				self.mode = ReadMode::Text;
 			},
 			'=' => {
 				self.mode = ReadMode::TagPrimaryArg;
 			},
-			'>' | '<' | '&' | '"' | '\'' | '\\' => {
-				let san_char = self.sanitize(character);
-				match self.current_instruction {
-					Instruction::Tag(ref mut contents, _) => {
-						contents.push_str(&san_char);
-					},
-					_ => {
-						self.current_instruction = Instruction::Tag(san_char, None);
-					}
-				}
-			},
 			_ => {
 				match self.current_instruction {
 					Instruction::Tag(ref mut contents, _) => {
@@ -178,27 +169,9 @@ impl BBCodeTokenizer {
 	fn parse_tag_primary_arg(&mut self, character: char) {
 		match character {
 			']' => {
-				self.set_cur_instruction();
+				self.set_cur_instruction(self.next);
 				self.mode = ReadMode::Text;
 			},
-			'>' | '<' | '&' | '"' | '\'' | '\\' => {
-				let san_char = self.sanitize(character);
-				match self.current_instruction {
-					Instruction::Tag(ref mut contents, ref mut args) => {
-						match args {
-							Some(ref mut primarg) => {
-								primarg.push_str(&san_char);
-							},
-							None => {
-								self.current_instruction = Instruction::Tag((*contents).to_string(), Some(san_char));
-							}
-						}
-					},
-					_ => {
-						unreachable!();
-					}
-				}
-			},
 			_ => {
 				match self.current_instruction {
 					Instruction::Tag(ref mut contents, ref mut args) => {
@@ -218,28 +191,24 @@ impl BBCodeTokenizer {
 			}
 		}
 	}
-	/// Adds current instruction to instruction vector and restes current instruction.
-	fn set_cur_instruction(&mut self) {
+	/// Adds current instruction, ending at the given position, to instruction vector and restes current instruction.
+	fn set_cur_instruction(&mut self, end: Cursor) {
 		if self.current_instruction != Instruction::Null {
 			self.instructions.push(self.current_instruction.clone());
+			self.push_span(end);
 			self.current_instruction = Instruction::Null;
 		}
 	}
-	/// Adds a given instruction to instruction vector and resets current instruction.
-	fn set_new_instruction(&mut self, instruction: Instruction) {
+	/// Adds a given instruction, ending at the given position, to instruction vector and resets current instruction.
+	fn set_new_instruction(&mut self, instruction: Instruction, end: Cursor) {
 		self.instructions.push(instruction);
+		self.push_span(end);
 		self.current_instruction = Instruction::Null;
 	}
-	/// Sanitizes characters for HTML.
-	fn sanitize(&mut self, character: char) -> String {
-		match character {
-			'<' => "&lt",
-			'>' => "&gt",
-			'&' => "&amp",
-			'"' => "&quot",
-			'\'' => "&#x27",
-			'\\' => "&#x2F",
-			_ => unreachable!()
-		}.to_string()
+	/// Records the span of the latest instruction and starts the next one where it ended.
+	fn push_span(&mut self, end: Cursor) {
+		let start = self.instruction_start;
+		self.spans.push(Span {start: start.offset, end: end.offset, line: start.line, column: start.column});
+		self.instruction_start = end;
 	}
-}
\ No newline at end of file
+}
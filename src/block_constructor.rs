@@ -0,0 +1,121 @@
+use rctree::{Node, NodeEdge};
+use std::rc::Rc;
+use super::GroupType;
+use super::ASTElement;
+use super::TagRegistry;
+
+/// The block layout state of a BlockConstructor.
+#[derive(Default)]
+pub(crate) struct BlockState {
+	/// Whether nothing has been written in the current block yet.
+	pub(crate) block_start: bool,
+	/// Whether a block has ended and the next inline content needs a new one.
+	pub(crate) block_ended: bool,
+	/// An element whose contents have already been written, and are skipped until it ends.
+	pub(crate) skip: Option<Node<ASTElement>>,
+}
+impl BlockState {
+	/// Creates the state at the start of a document.
+	pub(crate) fn new() -> BlockState {
+		BlockState {
+			block_start: true,
+			..BlockState::default()
+		}
+	}
+}
+
+/// Shared traversal and block layout for the constructors that write text formats, which separate blocks
+/// themselves rather than leaving it to markup.
+pub(crate) trait BlockConstructor {
+	/// Gets the block layout state.
+	fn blocks(&mut self) -> &mut BlockState;
+	/// Gets the TagRegistry whose handlers decide whether custom elements are blocks.
+	fn registry(&self) -> Option<&Rc<TagRegistry>>;
+	/// Starts an element.
+	fn start(&mut self, node: &Node<ASTElement>);
+	/// Ends an element.
+	fn end(&mut self, node: &Node<ASTElement>);
+	/// Writes the separator between the previous block and a new one.
+	fn separate_blocks(&mut self);
+	/// Resets any other state once a new block is opened.
+	fn block_opened(&mut self) {}
+
+	/// Starts and ends every element of an AST in order, except the contents of skipped elements.
+	fn traverse(&mut self, ast: Node<ASTElement>) {
+		for node_edge in ast.traverse() {
+			match node_edge {
+				NodeEdge::Start(node) => {
+					if self.blocks().skip.is_none() {
+						self.start(&node);
+					}
+				},
+				NodeEdge::End(node) => {
+					match self.blocks().skip {
+						Some(ref skipped) => {
+							if *skipped == node {
+								self.blocks().skip = None;
+							}
+						},
+						None => {self.end(&node)}
+					}
+				}
+			}
+		}
+	}
+
+	/// Gets whether elements of a type are written as blocks.
+	fn is_block(&self, ele_type: &GroupType) -> bool {
+		match ele_type {
+			GroupType::Custom(tag) => {
+				self.registry()
+					.and_then(|registry| registry.handler(tag))
+					.is_some_and(|handler| handler.is_block())
+			},
+			GroupType::Broken(_, _) => false,
+			ele_type => BLOCK_TYPES.contains(ele_type)
+		}
+	}
+
+	/// Starts a new block, separated from the previous one.
+	fn open_block(&mut self) {
+		if !self.blocks().block_start {
+			self.separate_blocks();
+		}
+		let blocks = self.blocks();
+		blocks.block_start = true;
+		blocks.block_ended = false;
+		self.block_opened();
+	}
+}
+
+/// Types of element that are written as blocks.
+static BLOCK_TYPES: [GroupType; 21] = [
+	GroupType::Document,
+	GroupType::Paragraph,
+	GroupType::Header,
+	GroupType::Hr,
+	GroupType::Scenebreak,
+	GroupType::Quote,
+	GroupType::List,
+	GroupType::ListItem,
+	GroupType::CodeBlock,
+	GroupType::Pre,
+	GroupType::MathBlock,
+	GroupType::Center,
+	GroupType::Right,
+	GroupType::Indent,
+	GroupType::Figure,
+	GroupType::Table,
+	GroupType::TableRow,
+	GroupType::TableHeader,
+	GroupType::TableData,
+	GroupType::TableCaption,
+	GroupType::Embed,
+];
+
+/// Gets the text of an element and all of its descendants.
+pub(crate) fn node_text(node: &Node<ASTElement>) -> String {
+	node.descendants()
+		.filter_map(|descendant| descendant.borrow().text_contents().clone())
+		.collect()
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeSet;
+use super::{Ast, AstNode, Attribute, GroupType, Url};
+
+/// The font size, in rem, at or below which text counts as tiny. `[size]` clamps sizes to no less than this.
+const TINY_SIZE: f32 = 0.5;
+
+/// Figures about the content of an Ast, for anti-spam and moderation rules.
+///
+/// Text is measured in characters, not counting whitespace. Broken tags are not counted, but their
+/// text is.
+///
+/// ```
+///use bbclash::{parse, ParserOptions};
+///
+///let ast = parse("[url=https://a.example]One[/url] [url=https://b.example/x]two[/url] [url=https://a.example/y]three[/url] \
+///        [opacity=0]hidden[/opacity] [size=1]tiny[/size]", &ParserOptions::new());
+///let stats = ast.stats();
+///assert_eq!(stats.links, 3);
+///assert_eq!(stats.link_domains.len(), 2);
+///assert_eq!(stats.hidden_text, 6);
+///assert_eq!(stats.tiny_text, 4);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct ContentStats {
+	/// The number of `[url]` links.
+	pub links: usize,
+	/// The distinct hosts that links point to, lowercased.
+	pub link_domains: BTreeSet<String>,
+	/// The number of `[email]` links.
+	pub emails: usize,
+	/// The number of images.
+	pub images: usize,
+	/// The number of embeds.
+	pub embeds: usize,
+	/// The length of all of the text.
+	pub text_length: usize,
+	/// The length of the text inside spoilers.
+	pub spoiler_text: usize,
+	/// The length of the text that can't be seen, because its opacity is 0 or its colour is fully transparent.
+	pub hidden_text: usize,
+	/// The length of the text at the smallest size.
+	pub tiny_text: usize,
+}
+impl ContentStats {
+	/// Gathers the ContentStats of an Ast.
+	pub fn from_ast(ast: &Ast) -> ContentStats {
+		let mut stats = ContentStats::default();
+		for node in ast.descendants() {
+			match node.kind() {
+				GroupType::Url => {
+					stats.links += 1;
+					let host = node.argument().and_then(|arg| Url::parse(&arg).ok()).and_then(|url| url.host().map(str::to_string));
+					if let Some(host) = host {
+						stats.link_domains.insert(host.trim_end_matches('.').to_string());
+					}
+				},
+				GroupType::Email => {stats.emails += 1},
+				GroupType::Image if node.argument().is_some() => {stats.images += 1},
+				GroupType::Embed if node.argument().is_some() => {stats.embeds += 1},
+				_ => {}
+			}
+			let length = match node.text() {
+				Some(text) => text.chars().filter(|c| !c.is_whitespace()).count(),
+				None => continue
+			};
+			stats.text_length += length;
+			let ancestors: Vec<AstNode> = std::iter::successors(node.parent(), |node| node.parent()).collect();
+			if ancestors.iter().any(|ancestor| ancestor.kind() == GroupType::Spoiler) {
+				stats.spoiler_text += length;
+			}
+			if ancestors.iter().any(is_hidden) {
+				stats.hidden_text += length;
+			}
+			if ancestors.iter().any(is_tiny) {
+				stats.tiny_text += length;
+			}
+		}
+		stats
+	}
+}
+
+/// Gets whether a node makes its text invisible.
+fn is_hidden(node: &AstNode) -> bool {
+	match node.attribute() {
+		Some(Attribute::Opacity(opacity)) => opacity <= 0.0,
+		Some(Attribute::Colour(colour)) => colour.alpha() == 0,
+		_ => false
+	}
+}
+
+/// Gets whether a node makes its text tiny.
+fn is_tiny(node: &AstNode) -> bool {
+	matches!(node.attribute(), Some(Attribute::Size(size)) if size <= TINY_SIZE)
+}
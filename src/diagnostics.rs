@@ -0,0 +1,44 @@
+use std::fmt;
+use super::Span;
+
+/// Kinds of problem found while lexing BBCode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+	/// Something that looks like a tag but is not recognised.
+	UnknownTag,
+	/// A recognised tag that is not allowed in this context.
+	DisallowedTag,
+	/// A tag whose argument is not valid.
+	InvalidArgument,
+	/// A tag that needs an argument or contents but was given none.
+	MissingArgument,
+	/// A tag that was never closed.
+	UnclosedTag,
+	/// A closing tag with no matching opening tag.
+	StrayCloseTag,
+	/// A closing tag that closed other, still-open tags before its own.
+	MisnestedTag,
+	/// A `[*]` outside of a `[list]`.
+	MisplacedListItem,
+	/// A `[tr]`, `[th]`, `[td]` or `[caption]` outside of the element it belongs in.
+	MisplacedTableElement,
+	/// Markup beyond one of the configured limits.
+	LimitExceeded,
+}
+
+/// A problem found while lexing BBCode, with the Span of the input it concerns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+	pub kind: DiagnosticKind,
+	pub span: Option<Span>,
+	pub message: String,
+}
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.span {
+			Some(span) => write!(f, "{}:{}: {}", span.line, span.column, self.message),
+			None => write!(f, "{}", self.message)
+		}
+	}
+}
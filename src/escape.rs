@@ -0,0 +1,76 @@
+use std::borrow::Cow;
+use super::{ASTElement, GroupType, Url, UrlPolicy, Colour};
+use crate::bbcode_lexer::{LIST_TYPES, is_accepted_image};
+use crate::colour::parse_decimal;
+
+/// Escapes text for use as HTML text content: `&`, `<` and `>` become `&amp;`, `&lt;` and `&gt;`.
+///
+/// ```
+///use bbclash::escape_text;
+///
+///assert_eq!(escape_text("Fish & chips <3 'n' \"peas\""), "Fish &amp; chips &lt;3 'n' \"peas\"");
+/// ```
+pub fn escape_text(text: &str) -> Cow<'_, str> {
+	escape(text, |c| match c {
+		'&' => Some("&amp;"),
+		'<' => Some("&lt;"),
+		'>' => Some("&gt;"),
+		_ => None
+	})
+}
+
+/// Escapes text for use inside a double- or single-quoted HTML attribute value.
+/// As well as the characters escaped by `escape_text`, `"` and `'` become `&quot;` and `&#x27;`.
+///
+/// ```
+///use bbclash::escape_attribute;
+///
+///assert_eq!(escape_attribute("a\" onclick='x'"), "a&quot; onclick=&#x27;x&#x27;");
+/// ```
+pub fn escape_attribute(value: &str) -> Cow<'_, str> {
+	escape(value, |c| match c {
+		'&' => Some("&amp;"),
+		'<' => Some("&lt;"),
+		'>' => Some("&gt;"),
+		'"' => Some("&quot;"),
+		'\'' => Some("&#x27;"),
+		_ => None
+	})
+}
+
+/// Gets an element's argument, validated for where it is written and escaped, or None if it is invalid.
+pub(crate) fn attribute_value<'a>(element: &'a ASTElement, url_policy: &UrlPolicy) -> Option<Cow<'a, str>> {
+	let arg = element.argument().as_deref()?;
+	let valid = match element.ele_type() {
+		GroupType::Url | GroupType::Embed => url_policy.check(arg).is_ok_and(|url| url.as_str() == arg),
+		GroupType::Image => url_policy.check(arg).is_ok_and(|url| url.as_str() == arg && is_accepted_image(&url)),
+		GroupType::Email => Url::parse(arg).is_ok_and(|url| url.scheme() == Some("mailto")),
+		GroupType::Colour => return Colour::parse(arg).map(|colour| Cow::Owned(colour.to_string())),
+		GroupType::Opacity => parse_decimal(arg).is_some_and(|value| (0.0..=1.0).contains(&value)),
+		GroupType::Size => parse_decimal(arg).is_some_and(|value| value > 0.0),
+		GroupType::Header => matches!(arg, "1" | "2" | "3" | "4" | "5" | "6"),
+		GroupType::Indent => matches!(arg, "1" | "2" | "3" | "4"),
+		GroupType::Figure => matches!(arg, "left" | "right"),
+		GroupType::List => LIST_TYPES.contains(arg),
+		_ => true
+	};
+	if valid {Some(escape_attribute(arg))} else {None}
+}
+
+/// Replaces each character that has an entity, borrowing the input if nothing needs replacing.
+fn escape(input: &str, entity: fn(char) -> Option<&'static str>) -> Cow<'_, str> {
+	match input.find(|c| entity(c).is_some()) {
+		None => Cow::Borrowed(input),
+		Some(first) => {
+			let mut output = String::with_capacity(input.len() + 8);
+			output.push_str(&input[..first]);
+			for c in input[first..].chars() {
+				match entity(c) {
+					Some(replacement) => output.push_str(replacement),
+					None => output.push(c)
+				}
+			}
+			Cow::Owned(output)
+		}
+	}
+}
@@ -1,12 +1,28 @@
 use rctree::{Node, NodeEdge};
 use std::cell::Ref;
+use std::rc::Rc;
 use super::GroupType;
 use super::ASTElement; 
+use super::{TagRegistry, ParserOptions, OutputFlavour, UrlPolicy, LinkPolicy, MediaPolicy, StylePolicy, Url};
+use super::{escape_text, escape_attribute};
+use crate::escape::attribute_value;
 
 /// Struct for generation of HTML strings.
 pub struct HTMLConstructor {
 	output_string: String,
 	pretty_print: bool,
+	registry: Option<Rc<TagRegistry>>,
+	url_policy: UrlPolicy,
+	link_policy: LinkPolicy,
+	media_policy: MediaPolicy,
+	style_policy: StylePolicy,
+	xhtml: bool,
+	/// The number of footnotes written, in XHTML.
+	footnote_count: usize,
+	/// The output from outside each open footnote, with the footnote's number, in XHTML.
+	open_footnotes: Vec<(usize, String)>,
+	/// The asides of the closed footnotes, written at the end of the output in XHTML.
+	footnotes: Vec<String>,
 }
 impl HTMLConstructor {
 	/// Creates a new HTMLConstructor.
@@ -15,8 +31,58 @@ impl HTMLConstructor {
 		HTMLConstructor {
 			output_string, 
 			pretty_print,
+			registry: None,
+			url_policy: UrlPolicy::default(),
+			link_policy: LinkPolicy::default(),
+			media_policy: MediaPolicy::default(),
+			style_policy: StylePolicy::default(),
+			xhtml: false,
+			footnote_count: 0,
+			open_footnotes: Vec::new(),
+			footnotes: Vec::new(),
 		}
 	}
+	/// Creates a new HTMLConstructor that renders custom elements with the handlers in the given TagRegistry.
+	pub fn with_registry(out_len: usize, pretty_print: bool, registry: Rc<TagRegistry>) -> HTMLConstructor {
+		let mut constructor = HTMLConstructor::new(out_len, pretty_print);
+		constructor.registry = Some(registry);
+		constructor
+	}
+	/// Creates a new HTMLConstructor configured by the given ParserOptions.
+	pub fn with_options(out_len: usize, options: &ParserOptions) -> HTMLConstructor {
+		HTMLConstructor::with_registry(out_len, options.pretty_print, options.registry.clone())
+			.url_policy(options.url_policy.clone())
+			.link_policy(options.link_policy.clone())
+			.media_policy(options.media_policy.clone())
+			.style_policy(options.style_policy.clone())
+			.xhtml(options.flavour == OutputFlavour::Xhtml)
+	}
+	/// Sets the rules that URLs must meet to be written. By default, this is the default UrlPolicy.
+	pub(crate) fn url_policy(mut self, url_policy: UrlPolicy) -> HTMLConstructor {
+		self.url_policy = url_policy;
+		self
+	}
+	/// Sets the rules for the attributes of links. By default, this is the default LinkPolicy.
+	pub(crate) fn link_policy(mut self, link_policy: LinkPolicy) -> HTMLConstructor {
+		self.link_policy = link_policy;
+		self
+	}
+	/// Sets the rules for images and embedded content. By default, this is the default MediaPolicy.
+	pub(crate) fn media_policy(mut self, media_policy: MediaPolicy) -> HTMLConstructor {
+		self.media_policy = media_policy;
+		self
+	}
+	/// Sets how styles are written. By default, this is the default StylePolicy.
+	pub(crate) fn style_policy(mut self, style_policy: StylePolicy) -> HTMLConstructor {
+		self.style_policy = style_policy;
+		self
+	}
+
+	/// Sets whether well-formed XHTML for EPUB is written, instead of HTML. By default, this is false.
+	pub(crate) fn xhtml(mut self, xhtml: bool) -> HTMLConstructor {
+		self.xhtml = xhtml;
+		self
+	}
 
 	/// Generates an HTML string from an ASTElement
 	pub fn construct(&mut self, ast: Node<ASTElement>) -> String {
@@ -26,6 +92,13 @@ impl HTMLConstructor {
 				NodeEdge::End(node) => {self.end_element(node.borrow())}
 			}
 		}	
+		for aside in self.footnotes.drain(..) {
+			self.output_string.push_str(&aside);
+		}
+		// Characters XML forbids can come from text, arguments or TagHandlers, so they are removed last.
+		if self.xhtml {
+			self.output_string.retain(is_xml_char);
+		}
 		self.output_string.clone()
 	}
 
@@ -34,7 +107,7 @@ impl HTMLConstructor {
 		match element.ele_type() {
 			GroupType::Text => {
 				if let Some(text) = element.text_contents() {
-					self.output_string.push_str(text)
+					self.output_string.push_str(&escape_text(text));
 				}	
 			},
 			GroupType::Paragraph => {self.output_string.push_str("<p>")},
@@ -49,8 +122,11 @@ impl HTMLConstructor {
 			GroupType::Subscript => {self.output_string.push_str("<sub>")},
 			GroupType::Superscript => {self.output_string.push_str("<sup>")},
 			GroupType::Spoiler => {self.output_string.push_str("<span class=\"spoiler\">")},
+			GroupType::Hr if self.xhtml => {self.output_string.push_str("<hr/>")},
 			GroupType::Hr => {self.output_string.push_str("<hr>")},
+			GroupType::Br if self.xhtml => {self.output_string.push_str("<br/>")},
 			GroupType::Br => {self.output_string.push_str("<br>")},
+			GroupType::Scenebreak if self.xhtml => {self.output_string.push_str("<hr class=\"scenebreak\"/>")},
 			GroupType::Scenebreak => {self.output_string.push_str("<br><br><br>")},
 			GroupType::Center => {self.output_string.push_str("<div class=\"center\">")},
 			GroupType::Right => {self.output_string.push_str("<div class=\"right\">")},
@@ -62,74 +138,83 @@ impl HTMLConstructor {
 			GroupType::TableData => {self.output_string.push_str("<td>")},
 			GroupType::TableCaption => {self.output_string.push_str("<caption>")},
 			GroupType::Header => {
-				if let Some(arg) = element.argument() {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
 					self.output_string.push_str(&format!("<h{}>", arg));
 				}
 			},
 			GroupType::Colour => {
-				if let Some(arg) = element.argument() {
-					self.output_string.push_str(&format!("<span style=\"color:{};\">", arg));
-				}	
-			},
-			GroupType::Url => {
-				if let Some(arg) = element.argument() {
-					self.output_string.push_str(&format!("<a href=\"{}\" rel=\"nofollow\">", arg));
-				}	
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					self.push_span(self.style_policy.colour(&arg));
+				}
 			},
-			GroupType::Email => {
-				if let Some(arg) = element.argument() {
-					self.output_string.push_str(&format!("<a href=\"{}\">", arg));
+			GroupType::Url | GroupType::Email => {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					self.output_string.push_str(&format!("<a href=\"{}\"", arg));
+					self.push_link_attributes(element.argument().as_deref().unwrap_or_default());
+					self.output_string.push('>');
 				}	
 			},
 			GroupType::Opacity => {
-				if let Some(arg) = element.argument() {
-					self.output_string.push_str(&format!("<span style=\"opacity:{};\">", arg));
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					self.push_span(self.style_policy.opacity(&arg));
 				}
 			},
 			GroupType::Size => {
-				if let Some(arg) = element.argument() {
-					self.output_string.push_str(&format!("<span style=\"font-size:{}rem;\">", arg));
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					self.push_span(self.style_policy.size(&arg));
 				}
 			},
 			GroupType::Image => {
-				if let Some(arg) = element.argument() {
-					self.output_string.push_str(&format!("<img src=\"{}\">", arg));
+				if let Some(src) = self.media_source(&element) {
+					if self.xhtml {
+						self.output_string.push_str(&format!("<img src=\"{}\" alt=\"\"/>", src));
+					} else {
+						self.output_string.push_str(&format!("<img src=\"{}\">", src));
+					}
 				}
 			},
 			GroupType::Figure => {
-				if let Some(arg) = element.argument() {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
 					self.output_string.push_str(&format!("<figure class=\"figure-{}\">", arg));
 				}
 			},
 			GroupType::Quote => {
-				if let Some(arg) = element.argument() {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
 					self.output_string.push_str(&format!("<blockquote data-author=\"{}\">", arg));
 				} else {
-					self.output_string.push_str(&"<blockquote>".to_string());
+					self.output_string.push_str("<blockquote>");
 				}
 			},
+			GroupType::Footnote if self.xhtml => {
+				self.footnote_count += 1;
+				let number = self.footnote_count;
+				let symbol = attribute_value(&element, &self.url_policy).unwrap_or_else(|| number.to_string().into());
+				self.output_string.push_str(&format!("<a epub:type=\"noteref\" href=\"#fn-{}\" id=\"fnref-{}\">{}</a>", number, number, symbol));
+				let outside = std::mem::take(&mut self.output_string);
+				self.open_footnotes.push((number, outside));
+			},
 			GroupType::Footnote => {
-				if let Some(arg) = element.argument() {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
 					self.output_string.push_str(&format!("<span class=\"footnote\" data-symbol=\"{}\">", arg));
 				} else {
-					self.output_string.push_str(&"<span class=\"footnote\">".to_string());
+					self.output_string.push_str("<span class=\"footnote\">");
 				}
 			},
 			GroupType::CodeBlock => {
-				if let Some(arg) = element.argument() {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
 					self.output_string.push_str(&format!("<pre data-language=\"{}\">", arg));
 				} else {
-					self.output_string.push_str(&"<pre>".to_string());
+					self.output_string.push_str("<pre>");
 				}
 			},
 			GroupType::List => {
-				if let Some(arg) = element.argument() {
-					match arg as &str {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					match &*arg {
 						"1" | "a" | "A" | "i" | "I" => {
 							self.output_string.push_str(&format!("<ol type=\"{}\">", arg));
 						},
 						"circle" | "square" | "none" => {
-							self.output_string.push_str(&format!("<ul style=\"list-style-type:{};\">", arg));
+							self.output_string.push_str(&format!("<ul {}>", self.style_policy.list_style(&arg)));
 						},
 						_ => {self.output_string.push_str("<ul>")}
 					}
@@ -138,7 +223,7 @@ impl HTMLConstructor {
 				}
 			},
 			GroupType::Indent => {
-				if let Some(arg) = element.argument() {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
 					self.output_string.push_str(&format!("<div class=\"indent-{}\">", arg));
 				}
 			},
@@ -146,19 +231,24 @@ impl HTMLConstructor {
 			GroupType::Math => {self.output_string.push_str("<span class=\"math_container\">")},
 			GroupType::MathBlock => {self.output_string.push_str("<div class=\"math_container\">")},
 			GroupType::Embed => {
-				if let Some(arg) = element.argument() {
-					self.output_string.push_str(&format!("<div class=\"embed\" data-content=\"{}\">", arg));
+				if let Some(src) = self.media_source(&element) {
+					self.output_string.push_str(&format!("<div class=\"embed\" data-content=\"{}\">", src));
 				}	
 			},
+			GroupType::Custom(tag) => {
+				if let Some(handler) = self.registry.as_ref().and_then(|registry| registry.handler(tag)) {
+					self.output_string.push_str(&handler.render_open(element.argument().as_deref().map(escape_attribute).as_deref()));
+				}
+			},
 			GroupType::Broken(_, tag) if !self.pretty_print => {
 				if let Some(text) = element.text_contents() {
 					if let Some(arg) = element.argument() {
-						self.output_string.push_str(&format!("[{}={}]{}", tag, arg, text));
+						self.output_string.push_str(&escape_text(&format!("[{}={}]{}", tag, arg, text)));
 					} else {
-						self.output_string.push_str(&format!("[{}]{}", tag, text));
+						self.output_string.push_str(&escape_text(&format!("[{}]{}", tag, text)));
 					}
 				} else if let Some(arg) = element.argument() {
-					self.output_string.push_str(&format!("[{}={}]", tag, arg));
+					self.output_string.push_str(&escape_text(&format!("[{}={}]", tag, arg)));
 				} else {
 					self.output_string.push_str(&format!("[{}]", tag));
 				}
@@ -167,9 +257,57 @@ impl HTMLConstructor {
 		};
 	}
 
+	/// Gets the escaped URL to load an image or embed from, as given by the MediaPolicy.
+	fn media_source(&self, element: &ASTElement) -> Option<String> {
+		attribute_value(element, &self.url_policy)?;
+		let url = Url::parse(element.argument().as_deref()?).ok()?;
+		self.media_policy.source(&url).map(|src| escape_attribute(&src).into_owned())
+	}
+
+	/// Opens a span with the given style attribute, or without one if the StylePolicy gave none.
+	fn push_span(&mut self, attribute: Option<String>) {
+		match attribute {
+			Some(attribute) => self.output_string.push_str(&format!("<span {}>", attribute)),
+			None => self.output_string.push_str("<span>")
+		}
+	}
+
+	/// Writes the attributes the LinkPolicy gives a link to the given URL.
+	fn push_link_attributes(&mut self, url: &str) {
+		if let Ok(url) = Url::parse(url) {
+			let attributes = self.link_policy.attributes(&url);
+			let named = [
+				("rel", attributes.rel),
+				("target", attributes.target),
+				("referrerpolicy", attributes.referrer_policy),
+				("class", attributes.class),
+			];
+			for (name, value) in named.iter() {
+				if let Some(value) = value {
+					self.output_string.push_str(&format!(" {}=\"{}\"", name, escape_attribute(value)));
+				}
+			}
+		}
+	}
+
 	/// Closes an HTML tag.
 	fn end_element(&mut self, element: Ref<ASTElement>) {
 		match element.ele_type() {
+			GroupType::Url |
+			GroupType::Email |
+			GroupType::Colour |
+			GroupType::Opacity |
+			GroupType::Size |
+			GroupType::Figure |
+			GroupType::Indent
+				if attribute_value(&element, &self.url_policy).is_none() => {},
+			GroupType::Embed if self.media_source(&element).is_none() => {},
+			GroupType::Footnote if self.xhtml => {
+				if let Some((number, outside)) = self.open_footnotes.pop() {
+					let content = std::mem::replace(&mut self.output_string, outside);
+					self.footnotes.push(format!("<aside epub:type=\"footnote\" id=\"fn-{}\"><p>{}</p></aside>", number, content));
+				}
+			},
 			GroupType::Paragraph => {self.output_string.push_str("</p>")},
 			GroupType::Bold => {self.output_string.push_str("</b>")},
 			GroupType::Strong => {self.output_string.push_str("</strong>")},
@@ -187,8 +325,8 @@ impl HTMLConstructor {
 			GroupType::TableData => {self.output_string.push_str("</td>")},
 			GroupType::TableCaption => {self.output_string.push_str("</caption>")},
 			GroupType::List => {
-				if let Some(arg) = element.argument() {
-					match arg as &str{
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					match &*arg {
 						"1" | "a" | "A" | "i" | "I" => {self.output_string.push_str("</ol>")},
 						"circle" | "square" | "none" => {self.output_string.push_str("</ul>")},
 						_ => {self.output_string.push_str("</ul>")}
@@ -199,7 +337,7 @@ impl HTMLConstructor {
 			},
 			GroupType::ListItem => {self.output_string.push_str("</li>")},
 			GroupType::Header => {
-				if let Some(arg) = element.argument() {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
 					self.output_string.push_str(&format!("</h{}>", arg));
 				}
 			},
@@ -225,12 +363,20 @@ impl HTMLConstructor {
 			GroupType::MathBlock |
 			GroupType::Embed
 				=> {self.output_string.push_str("</div>")}
-			GroupType::Broken(_, tag) if !self.pretty_print => {
-				if !element.is_void() {
-					self.output_string.push_str(&format!("[/{}]", tag));
-				}	
+			GroupType::Custom(tag) => {
+				if let Some(handler) = self.registry.as_ref().and_then(|registry| registry.handler(tag)) {
+					self.output_string.push_str(&handler.render_close(element.argument().as_deref().map(escape_attribute).as_deref()));
+				}
+			},
+			GroupType::Broken(_, tag) if !self.pretty_print && !element.is_void() => {
+				self.output_string.push_str(&format!("[/{}]", tag));
 			},
 			_ => {}
 		};
 	}
-}
\ No newline at end of file
+}
+
+/// Checks whether a character is allowed in XML 1.0 documents.
+fn is_xml_char(c: char) -> bool {
+	matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'..)
+}
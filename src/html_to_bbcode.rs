@@ -0,0 +1,582 @@
+use phf::{phf_map, phf_set};
+use super::{render, Colour, ParserOptions, OutputFlavour, UrlPolicy};
+use crate::bbcode_lexer::is_accepted_image;
+
+/// Converts HTML to BBCode.
+///
+/// The elements and classes HTMLConstructor writes are mapped back to their tags, as are the inline
+/// styles common in rich text pasted from word processors and browsers, such as `font-weight:bold`
+/// and `color`. Unknown elements are dropped but their text is kept, and scripts, styles and other
+/// non-content elements are dropped entirely. Links and images are checked against the default UrlPolicy, and
+/// only their text is kept if it rejects them. The result is normalised, so it is the same canonical
+/// BBCode that `OutputFlavour::BBCode` writes, and converting BBClash's own HTML output gives BBCode
+/// that renders to the same HTML.
+///
+/// ```
+///use bbclash::html_to_bbcode;
+///
+///assert_eq!(html_to_bbcode("<p>Some <b>bold</b> and <span style=\"font-style: italic\">italic</span> text</p><p>Again</p>"),
+///        "Some [b]bold[/b] and [i]italic[/i] text\n\nAgain");
+///assert_eq!(html_to_bbcode("<blockquote data-author=\"Enda\"><p>Hi</p></blockquote><script>alert(1)</script>"),
+///        "[quote=Enda]Hi[/quote]");
+/// ```
+pub fn html_to_bbcode(html: &str) -> String {
+	let mut converter = Converter::default();
+	for token in tokenize(html) {
+		match token {
+			HtmlToken::Start(name, attributes) => converter.start_element(&name, &attributes),
+			HtmlToken::End(name) => converter.end_element(&name),
+			HtmlToken::Text(text) => converter.text(&text),
+		}
+	}
+	while !converter.open.is_empty() {
+		converter.close_top();
+	}
+	render(&converter.output, &ParserOptions::new().flavour(OutputFlavour::BBCode).pretty_print(false))
+}
+
+/// A piece of HTML.
+enum HtmlToken {
+	/// An opening tag, with its lowercased name and its attributes, with lowercased names and decoded values.
+	Start(String, Vec<(String, String)>),
+	/// A closing tag, with its lowercased name.
+	End(String),
+	/// Text, with its character references decoded.
+	Text(String),
+}
+
+/// Static compile-time set of the elements that have no closing tag.
+static VOID_ELEMENTS: phf::Set<&'static str> = phf_set! {
+	"area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+};
+
+/// Static compile-time set of the elements whose content is dropped along with them.
+static DROPPED_ELEMENTS: phf::Set<&'static str> = phf_set! {
+	"head", "iframe", "noscript", "object", "script", "select", "style", "svg", "template", "textarea", "title",
+};
+
+/// Static compile-time set of the elements whose content is raw text, rather than HTML.
+static RAW_TEXT_ELEMENTS: phf::Set<&'static str> = phf_set! {
+	"script", "style", "textarea", "title",
+};
+
+/// Static compile-time set of the elements that start a new block of text, besides those with tags of their own.
+static BLOCK_ELEMENTS: phf::Set<&'static str> = phf_set! {
+	"address", "article", "aside", "dd", "details", "div", "dl", "dt", "figcaption", "footer", "form",
+	"header", "main", "nav", "p", "section", "summary",
+};
+
+/// Static compile-time map of the named character references that are decoded.
+static ENTITIES: phf::Map<&'static str, char> = phf_map! {
+	"amp" => '&',
+	"lt" => '<',
+	"gt" => '>',
+	"quot" => '"',
+	"apos" => '\'',
+	"nbsp" => ' ',
+	"ensp" => ' ',
+	"emsp" => ' ',
+	"thinsp" => ' ',
+	"shy" => '\u{ad}',
+	"ndash" => '–',
+	"mdash" => '—',
+	"lsquo" => '‘',
+	"rsquo" => '’',
+	"sbquo" => '‚',
+	"ldquo" => '“',
+	"rdquo" => '”',
+	"bdquo" => '„',
+	"laquo" => '«',
+	"raquo" => '»',
+	"hellip" => '…',
+	"bull" => '•',
+	"middot" => '·',
+	"copy" => '©',
+	"reg" => '®',
+	"trade" => '™',
+	"deg" => '°',
+	"times" => '×',
+	"divide" => '÷',
+	"para" => '¶',
+	"sect" => '§',
+	"euro" => '€',
+	"pound" => '£',
+};
+
+/// Splits HTML into HtmlTokens. Comments, doctypes and processing instructions are skipped, and a
+/// `<` that doesn't start a tag is read as text.
+fn tokenize(html: &str) -> Vec<HtmlToken> {
+	let mut tokens = Vec::new();
+	let mut rest = html;
+	while !rest.is_empty() {
+		let text_end = rest.find('<').unwrap_or(rest.len());
+		if text_end > 0 {
+			tokens.push(HtmlToken::Text(decode(&rest[..text_end])));
+			rest = &rest[text_end..];
+			continue;
+		}
+		if let Some(comment) = rest.strip_prefix("<!--") {
+			rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+			continue;
+		}
+		if rest.starts_with("<!") || rest.starts_with("<?") {
+			rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+			continue;
+		}
+		let closing = rest.starts_with("</");
+		let tag = if closing {&rest[2..]} else {&rest[1..]};
+		if !tag.starts_with(|c: char| c.is_ascii_alphabetic()) {
+			tokens.push(HtmlToken::Text("<".to_string()));
+			rest = &rest[1..];
+			continue;
+		}
+		let name_end = tag.find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(tag.len());
+		let name = tag[..name_end].to_ascii_lowercase();
+		let (attributes, after) = read_attributes(&tag[name_end..]);
+		rest = after;
+		if closing {
+			tokens.push(HtmlToken::End(name));
+			continue;
+		}
+		if RAW_TEXT_ELEMENTS.contains(name.as_str()) {
+			let close = format!("</{}", name);
+			let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+			tokens.push(HtmlToken::Start(name.clone(), attributes));
+			tokens.push(HtmlToken::Text(decode(&rest[..end])));
+			tokens.push(HtmlToken::End(name));
+			rest = rest[end..].find('>').map_or("", |close_end| &rest[end + close_end + 1..]);
+			continue;
+		}
+		tokens.push(HtmlToken::Start(name, attributes));
+	}
+	tokens
+}
+
+/// Reads the attributes of a tag, up to and including its `>`, returning them and the HTML after the tag.
+fn read_attributes(mut tag: &str) -> (Vec<(String, String)>, &str) {
+	let mut attributes = Vec::new();
+	loop {
+		tag = tag.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+		if tag.is_empty() {
+			return (attributes, tag);
+		}
+		if let Some(after) = tag.strip_prefix('>') {
+			return (attributes, after);
+		}
+		let name_end = tag.find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/').unwrap_or(tag.len());
+		let name = tag[..name_end].to_ascii_lowercase();
+		tag = tag[name_end..].trim_start();
+		let value = match tag.strip_prefix('=') {
+			Some(value) => {
+				let value = value.trim_start();
+				let (raw, after) = match value.chars().next() {
+					Some(quote) if quote == '"' || quote == '\'' => {
+						let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+						(&value[1..end], value.get(end + 1..).unwrap_or_default())
+					},
+					_ => {
+						let end = value.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(value.len());
+						value.split_at(end)
+					}
+				};
+				tag = after;
+				decode(raw)
+			},
+			None => String::new()
+		};
+		attributes.push((name, value));
+	}
+}
+
+/// Decodes the character references in HTML text. References that aren't recognised are left as written.
+fn decode(text: &str) -> String {
+	let mut decoded = String::with_capacity(text.len());
+	let mut rest = text;
+	while let Some(start) = rest.find('&') {
+		decoded.push_str(&rest[..start]);
+		rest = &rest[start..];
+		let reference = rest[1..].find(';').filter(|end| *end <= 32).and_then(|end| {
+			let name = &rest[1..end + 1];
+			let character = match name.strip_prefix('#') {
+				Some(number) => match number.strip_prefix(['x', 'X']) {
+					Some(hex) => u32::from_str_radix(hex, 16).ok(),
+					None => number.parse().ok()
+				}.and_then(char::from_u32),
+				None => ENTITIES.get(name).copied()
+			};
+			character.map(|character| (character, end + 2))
+		});
+		match reference {
+			Some((character, length)) => {
+				decoded.push(character);
+				rest = &rest[length..];
+			},
+			None => {
+				decoded.push('&');
+				rest = &rest[1..];
+			}
+		}
+	}
+	decoded.push_str(rest);
+	decoded
+}
+
+/// How an open element is converted.
+#[derive(Default)]
+struct Conversion {
+	/// The BBCode tags the element opens, with their closing tags.
+	tags: Vec<(String, String)>,
+	/// Whether the element's text is a separate block.
+	block: bool,
+	/// Whether the element's content is dropped.
+	dropped: bool,
+	/// Whether the element's text is only read for closing tags, as in `[code]`.
+	verbatim: bool,
+	/// Whether the element's whitespace is kept as written.
+	preformatted: bool,
+}
+impl Conversion {
+	fn tag(mut self, open: String, close: &str) -> Conversion {
+		self.tags.push((open, close.to_string()));
+		self
+	}
+	fn simple(self, tag: &str) -> Conversion {
+		self.tag(format!("[{}]", tag), &format!("[/{}]", tag))
+	}
+	fn block(mut self) -> Conversion {
+		self.block = true;
+		self
+	}
+}
+
+/// Writes BBCode for a stream of HtmlTokens.
+#[derive(Default)]
+struct Converter {
+	output: String,
+	open: Vec<(String, Conversion)>,
+	dropped_depth: usize,
+	verbatim_depth: usize,
+	preformatted_depth: usize,
+	/// Whether inline content has been written since the last block started or ended.
+	inline_written: bool,
+	paragraph_pending: bool,
+	space_pending: bool,
+}
+impl Converter {
+	/// Opens an element, writing its tags.
+	fn start_element(&mut self, name: &str, attributes: &[(String, String)]) {
+		if self.dropped_depth > 0 {
+			if !VOID_ELEMENTS.contains(name) {
+				self.open.push((name.to_string(), Conversion {dropped: true, ..Conversion::default()}));
+				self.dropped_depth += 1;
+			}
+			return;
+		}
+		let attribute = |wanted: &str| attributes.iter().find(|(name, _)| name == wanted).map(|(_, value)| value.as_str());
+		let classes: Vec<&str> = attribute("class").unwrap_or_default().split_whitespace().collect();
+		let styles = declarations(attribute("style").unwrap_or_default());
+		match name {
+			"br" => {
+				self.output.push('\n');
+				self.inline_written = true;
+				self.space_pending = false;
+				return;
+			},
+			"hr" => {
+				self.push_block("[hr]");
+				return;
+			},
+			"img" => {
+				let src = attribute("src").and_then(argument).and_then(|src| UrlPolicy::new().check(src).ok());
+				if let Some(src) = src.filter(is_accepted_image) {
+					self.push_inline(&format!("[img]{}[/img]", src));
+				}
+				return;
+			},
+			_ if VOID_ELEMENTS.contains(name) => return,
+			_ => {}
+		}
+		let mut conversion = match name {
+			_ if DROPPED_ELEMENTS.contains(name) => Conversion {dropped: true, ..Conversion::default()},
+			"b" => Conversion::default().simple("b"),
+			"strong" => Conversion::default().simple("strong"),
+			"i" | "cite" | "dfn" | "var" => Conversion::default().simple("i"),
+			"em" => Conversion::default().simple("em"),
+			"u" | "ins" => Conversion::default().simple("u"),
+			"s" | "strike" | "del" => Conversion::default().simple("s"),
+			"sub" => Conversion::default().simple("sub"),
+			"sup" => Conversion::default().simple("sup"),
+			"tt" | "kbd" | "samp" => Conversion::default().simple("mono"),
+			"code" if self.preformatted_depth == 0 => Conversion {verbatim: true, ..Conversion::default().simple("code")},
+			"a" => match attribute("href") {
+				Some(href) if href.to_ascii_lowercase().starts_with("mailto:") => {
+					if let Some(address) = argument(&href[7..]) {
+						self.push_inline(&format!("[email]{}[/email]", address));
+					}
+					Conversion {dropped: true, ..Conversion::default()}
+				},
+				Some(href) => match argument(href).and_then(|href| UrlPolicy::new().check(href).ok()) {
+					Some(href) => Conversion::default().tag(format!("[url={}]", href), "[/url]"),
+					None => Conversion::default()
+				},
+				None => Conversion::default()
+			},
+			"font" => match attribute("color").and_then(Colour::parse) {
+				Some(colour) => Conversion::default().tag(format!("[color={}]", colour), "[/color]"),
+				None => Conversion::default()
+			},
+			"blockquote" => match attribute("data-author").and_then(argument) {
+				Some(author) => Conversion::default().tag(format!("[quote={}]", author), "[/quote]").block(),
+				None => Conversion::default().simple("quote").block()
+			},
+			"pre" => match attribute("data-language").and_then(argument) {
+				Some(language) => Conversion {verbatim: true, preformatted: true, ..Conversion::default()
+					.tag(format!("[codeblock={}]", language), "[/codeblock]").block()},
+				None => Conversion {preformatted: true, ..Conversion::default().simple("pre").block()}
+			},
+			"h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Conversion::default().simple(name).block(),
+			"ul" | "ol" => {
+				let list_type = match attribute("type") {
+					Some(list_type) if name == "ol" && ["1", "a", "A", "i", "I"].contains(&list_type) => Some(list_type.to_string()),
+					_ if name == "ol" => Some("1".to_string()),
+					_ => styles.iter().find(|(property, _)| property == "list-style-type").map(|(_, value)| value.clone())
+						.or_else(|| classes.iter().find_map(|class| class.strip_prefix("bb-list-")).map(str::to_string))
+						.filter(|list_type| ["circle", "square", "none"].contains(&list_type.as_str()))
+				};
+				match list_type {
+					Some(list_type) => Conversion::default().tag(format!("[list={}]", list_type), "[/list]").block(),
+					None => Conversion::default().simple("list").block()
+				}
+			},
+			"li" => Conversion::default().tag("[*]".to_string(), "").block(),
+			"table" => Conversion::default().simple("table").block(),
+			"tr" => Conversion::default().simple("tr").block(),
+			"th" => Conversion::default().simple("th").block(),
+			"td" => Conversion::default().simple("td").block(),
+			"caption" => Conversion::default().simple("caption").block(),
+			"figure" => match classes.iter().find_map(|class| class.strip_prefix("figure-")) {
+				Some(side) if side == "left" || side == "right" => Conversion::default().tag(format!("[figure={}]", side), "[/figure]").block(),
+				_ => Conversion::default().block()
+			},
+			"div" if classes.contains(&"embed") => {
+				if let Some(content) = attribute("data-content").and_then(argument) {
+					self.push_block(&format!("[embed]{}[/embed]", content));
+				}
+				Conversion {dropped: true, ..Conversion::default()}
+			},
+			"div" if classes.contains(&"math_container") => Conversion {verbatim: true, ..Conversion::default().simple("mathblock").block()},
+			"span" if classes.contains(&"math_container") => Conversion {verbatim: true, ..Conversion::default().simple("math")},
+			"span" if classes.contains(&"footnote") => match attribute("data-symbol").and_then(argument) {
+				Some(symbol) => Conversion::default().tag(format!("[footnote={}]", symbol), "[/footnote]"),
+				None => Conversion::default().simple("footnote")
+			},
+			_ if BLOCK_ELEMENTS.contains(name) => Conversion::default().block(),
+			_ => Conversion::default()
+		};
+		if !conversion.dropped {
+			add_class_tags(&mut conversion, &classes);
+			add_style_tags(&mut conversion, &styles);
+		}
+		self.open_element(name, conversion);
+	}
+
+	/// Writes an element's opening tags and adds it to the open elements.
+	fn open_element(&mut self, name: &str, conversion: Conversion) {
+		if conversion.block {
+			self.paragraph_pending = true;
+		}
+		for (open, _) in &conversion.tags {
+			if conversion.block {
+				self.push_block(open);
+			} else {
+				self.push_inline(open);
+			}
+		}
+		self.dropped_depth += usize::from(conversion.dropped);
+		self.verbatim_depth += usize::from(conversion.verbatim);
+		self.preformatted_depth += usize::from(conversion.preformatted);
+		self.open.push((name.to_string(), conversion));
+	}
+
+	/// Closes an element, and any elements opened inside it that are still open. Closing tags that
+	/// don't match an open element are ignored.
+	fn end_element(&mut self, name: &str) {
+		if self.open.iter().any(|(open, _)| open == name) {
+			while let Some((open, _)) = self.open.last() {
+				let matched = open == name;
+				self.close_top();
+				if matched {
+					break;
+				}
+			}
+		}
+	}
+
+	/// Closes the most recently opened element.
+	fn close_top(&mut self) {
+		if let Some((_, conversion)) = self.open.pop() {
+			self.dropped_depth -= usize::from(conversion.dropped);
+			self.verbatim_depth -= usize::from(conversion.verbatim);
+			self.preformatted_depth -= usize::from(conversion.preformatted);
+			for (_, close) in conversion.tags.iter().rev() {
+				if conversion.block {
+					self.push_block(close);
+				} else {
+					self.output.push_str(close);
+				}
+			}
+			if conversion.block {
+				self.paragraph_pending = true;
+			}
+		}
+	}
+
+	/// Writes text, collapsing whitespace unless it is preformatted.
+	fn text(&mut self, text: &str) {
+		if self.dropped_depth > 0 {
+			return;
+		}
+		if self.preformatted_depth > 0 {
+			self.flush_pending();
+			self.push_escaped(text);
+			self.inline_written = true;
+			return;
+		}
+		for (index, word) in text.split(|c: char| c.is_ascii_whitespace()).enumerate() {
+			if index > 0 && self.inline_written && !self.output.ends_with('\n') {
+				self.space_pending = true;
+			}
+			if !word.is_empty() {
+				self.flush_pending();
+				self.push_escaped(word);
+				self.inline_written = true;
+			}
+		}
+	}
+
+	/// Writes an inline tag, after any pending paragraph break or space.
+	fn push_inline(&mut self, bbcode: &str) {
+		self.flush_pending();
+		self.output.push_str(bbcode);
+		self.inline_written = true;
+	}
+
+	/// Writes a block tag, which needs no paragraph break or space around it.
+	fn push_block(&mut self, bbcode: &str) {
+		self.output.push_str(bbcode);
+		self.inline_written = false;
+		self.paragraph_pending = false;
+		self.space_pending = false;
+	}
+
+	/// Writes a pending paragraph break between inline content, or a pending space.
+	fn flush_pending(&mut self) {
+		if self.paragraph_pending && self.inline_written {
+			self.output.push_str("\n\n");
+		} else if self.space_pending {
+			self.output.push(' ');
+		}
+		self.paragraph_pending = false;
+		self.space_pending = false;
+	}
+
+	/// Writes text, escaping anything that would otherwise be read as a tag, as BBCodeConstructor does.
+	fn push_escaped(&mut self, text: &str) {
+		let mut chars = text.chars().peekable();
+		while let Some(c) = chars.next() {
+			match c {
+				'\\' => self.output.push_str("\\\\"),
+				'[' if self.verbatim_depth == 0 || chars.peek() == Some(&'/') => self.output.push_str("\\["),
+				'\r' => {},
+				_ => self.output.push(c)
+			}
+		}
+	}
+}
+
+/// Adds the tags for the classes HTMLConstructor writes to style inline text.
+fn add_class_tags(conversion: &mut Conversion, classes: &[&str]) {
+	for class in classes {
+		let tag = match *class {
+			"underline" => Some(("[u]".to_string(), "[/u]")),
+			"smallcaps" => Some(("[smcaps]".to_string(), "[/smcaps]")),
+			"monospace" => Some(("[mono]".to_string(), "[/mono]")),
+			"spoiler" => Some(("[spoiler]".to_string(), "[/spoiler]")),
+			"center" => Some(("[center]".to_string(), "[/center]")),
+			"right" => Some(("[right]".to_string(), "[/right]")),
+			_ => class.strip_prefix("indent-")
+				.filter(|level| ["1", "2", "3", "4"].contains(level))
+				.map(|level| (format!("[indent={}]", level), "[/indent]"))
+				.or_else(|| class.strip_prefix("bb-color-")
+					.and_then(|colour| Colour::parse(colour).or_else(|| Colour::parse(&format!("#{}", colour))))
+					.map(|colour| (format!("[color={}]", colour), "[/color]")))
+				.or_else(|| class.strip_prefix("bb-size-")
+					.and_then(|size| size.replace('_', ".").parse::<f32>().ok())
+					.map(|size| (format!("[size={}em]", size), "[/size]")))
+				.or_else(|| class.strip_prefix("bb-opacity-")
+					.and_then(|opacity| opacity.replace('_', ".").parse::<f32>().ok())
+					.map(|opacity| (format!("[opacity={}]", opacity), "[/opacity]")))
+		};
+		if let Some((open, close)) = tag {
+			if ["[center]", "[right]"].contains(&open.as_str()) || open.starts_with("[indent") {
+				conversion.block = true;
+			}
+			conversion.tags.push((open, close.to_string()));
+		}
+	}
+}
+
+/// Adds the tags for inline styles, such as those of rich text pasted from word processors.
+fn add_style_tags(conversion: &mut Conversion, styles: &[(String, String)]) {
+	for (property, value) in styles {
+		let tag = match property.as_str() {
+			"color" => Colour::parse(value).map(|colour| (format!("[color={}]", colour), "[/color]")),
+			"opacity" => value.parse::<f32>().ok().filter(|opacity| opacity.is_finite()).map(|opacity| (format!("[opacity={}]", opacity), "[/opacity]")),
+			"font-size" => font_size(value).map(|size| (format!("[size={}em]", size), "[/size]")),
+			"font-weight" if value == "bold" || value == "bolder" || value.parse::<u16>().is_ok_and(|weight| weight >= 600) => {
+				Some(("[b]".to_string(), "[/b]"))
+			},
+			"font-style" if value == "italic" || value == "oblique" => Some(("[i]".to_string(), "[/i]")),
+			"font-variant" if value == "small-caps" => Some(("[smcaps]".to_string(), "[/smcaps]")),
+			"text-decoration" | "text-decoration-line" if value.contains("underline") => Some(("[u]".to_string(), "[/u]")),
+			"text-decoration" | "text-decoration-line" if value.contains("line-through") => Some(("[s]".to_string(), "[/s]")),
+			"text-align" if conversion.block && value == "center" => Some(("[center]".to_string(), "[/center]")),
+			"text-align" if conversion.block && value == "right" => Some(("[right]".to_string(), "[/right]")),
+			_ => None
+		};
+		if let Some((open, close)) = tag {
+			conversion.tags.push((open, close.to_string()));
+		}
+	}
+}
+
+/// Parses a `style` attribute into its properties and values, lowercased.
+fn declarations(style: &str) -> Vec<(String, String)> {
+	style.split(';')
+		.filter_map(|declaration| declaration.split_once(':'))
+		.map(|(property, value)| {
+			let value = value.trim().trim_end_matches("!important").trim();
+			(property.trim().to_ascii_lowercase(), value.to_ascii_lowercase())
+		})
+		.collect()
+}
+
+/// Converts a CSS font size in `rem`, `em`, `px` or `pt` to em.
+fn font_size(value: &str) -> Option<f32> {
+	let (number, scale) = if let Some(number) = value.strip_suffix("rem") {
+		(number, 1.0)
+	} else if let Some(number) = value.strip_suffix("em") {
+		(number, 1.0)
+	} else if let Some(number) = value.strip_suffix("px") {
+		(number, 16.0)
+	} else {
+		(value.strip_suffix("pt")?, 12.0)
+	};
+	number.trim().parse::<f32>().ok().filter(|size| size.is_finite()).map(|size| size / scale)
+}
+
+/// Gets a value that can be written as a tag's argument, or None if it would end the tag early.
+pub(crate) fn argument(value: &str) -> Option<&str> {
+	let value = value.trim();
+	if value.is_empty() || value.contains(['[', ']', '\n', '\r']) {None} else {Some(value)}
+}
@@ -0,0 +1,486 @@
+use rctree::Node;
+use phf::phf_set;
+use std::rc::Rc;
+use super::GroupType;
+use super::ASTElement;
+use super::{TagRegistry, ParserOptions, UrlPolicy, MediaPolicy, Colour, Url};
+use crate::escape::attribute_value;
+use crate::ast::has_content;
+use crate::block_constructor::{BlockConstructor, BlockState, node_text};
+
+/// Struct for generation of LaTeX strings, for print exports.
+///
+/// The output is the body of a document, and uses the `hyperref`, `xcolor`, `ulem` (with the
+/// `normalem` option), `enumitem` and `alltt` packages. Maths is written as-is if it only uses known
+/// maths commands and environments, and is escaped as text otherwise, so that it can't define macros,
+/// change category codes, use files or end its maths mode early.
+pub struct LatexConstructor {
+	output_string: String,
+	pretty_print: bool,
+	registry: Option<Rc<TagRegistry>>,
+	url_policy: UrlPolicy,
+	media_policy: MediaPolicy,
+	cell_start: Option<bool>,
+	in_header: bool,
+	verbatim: bool,
+	blocks: BlockState,
+}
+impl LatexConstructor {
+	/// Creates a new LatexConstructor.
+	pub fn new(out_len: usize, pretty_print: bool) -> LatexConstructor {
+		let output_string = String::with_capacity(out_len + out_len/2);
+		LatexConstructor {
+			output_string,
+			pretty_print,
+			registry: None,
+			url_policy: UrlPolicy::default(),
+			media_policy: MediaPolicy::default(),
+			cell_start: None,
+			in_header: false,
+			verbatim: false,
+			blocks: BlockState::new(),
+		}
+	}
+	/// Creates a new LatexConstructor that treats custom elements as blocks or inline according to the handlers in the given TagRegistry.
+	pub fn with_registry(out_len: usize, pretty_print: bool, registry: Rc<TagRegistry>) -> LatexConstructor {
+		let mut constructor = LatexConstructor::new(out_len, pretty_print);
+		constructor.registry = Some(registry);
+		constructor
+	}
+	/// Creates a new LatexConstructor configured by the given ParserOptions.
+	pub fn with_options(out_len: usize, options: &ParserOptions) -> LatexConstructor {
+		let mut constructor = LatexConstructor::with_registry(out_len, options.pretty_print, options.registry.clone());
+		constructor.url_policy = options.url_policy.clone();
+		constructor.media_policy = options.media_policy.clone();
+		constructor
+	}
+
+	/// Generates a LaTeX string from an ASTElement
+	pub fn construct(&mut self, ast: Node<ASTElement>) -> String {
+		self.traverse(ast);
+		self.output_string.trim_end().to_string()
+	}
+
+	/// Starts an element.
+	fn start_element(&mut self, node: &Node<ASTElement>) {
+		if self.pretty_print && !has_content(node) {
+			self.blocks.skip = Some(node.clone());
+			return;
+		}
+		let element = node.borrow();
+		if self.blocks.block_ended && !self.is_block(element.ele_type()) {
+			self.open_block();
+		}
+		let valid = attribute_value(&element, &self.url_policy).is_some();
+		let arg = element.argument().clone().unwrap_or_default();
+		match element.ele_type() {
+			GroupType::Text => {
+				if let Some(text) = element.text_contents() {
+					if self.verbatim {
+						self.push(&text.replace("\\end{verbatim}", "\\end {verbatim}"));
+					} else {
+						self.push(&escape_latex(text));
+					}
+				}
+			},
+			GroupType::Paragraph => {
+				match self.cell_start {
+					Some(true) => {},
+					Some(false) => self.push(" "),
+					None => self.open_block()
+				}
+			},
+			GroupType::Bold | GroupType::Strong => {self.push("\\textbf{")},
+			GroupType::Italic => {self.push("\\textit{")},
+			GroupType::Emphasis => {self.push("\\emph{")},
+			GroupType::Underline => {self.push("\\underline{")},
+			GroupType::Strikethrough => {self.push("\\sout{")},
+			GroupType::Smallcaps => {self.push("\\textsc{")},
+			GroupType::Monospace | GroupType::Code => {self.push("\\texttt{")},
+			GroupType::Subscript => {self.push("\\textsubscript{")},
+			GroupType::Superscript => {self.push("\\textsuperscript{")},
+			GroupType::Colour if valid => {
+				match Colour::parse(&arg) {
+					Some(colour) => self.push(&format!("\\textcolor[RGB]{{{},{},{}}}{{", colour.red(), colour.green(), colour.blue())),
+					None => self.push("{")
+				}
+			},
+			GroupType::Size if valid => {
+				let size = arg.parse::<f32>().unwrap_or(1.0);
+				self.push(&format!("{{{} ", size_command(size)));
+			},
+			GroupType::Url | GroupType::Email if valid => {self.push(&format!("\\href{{{}}}{{", escape_url(&arg)))},
+			GroupType::Image | GroupType::Embed => {
+				if let Some(src) = self.media_source(&element) {
+					self.push(&format!("\\url{{{}}}", escape_url(&src)));
+				}
+			},
+			GroupType::Footnote => {self.push("\\footnote{")},
+			GroupType::Math if safe_maths(&node_text(node)) => {
+				self.push("$");
+				self.verbatim = true;
+			},
+			GroupType::MathBlock => {
+				self.open_block();
+				if safe_maths(&node_text(node)) {
+					self.push("\\[");
+					self.verbatim = true;
+				}
+			},
+			GroupType::Header if valid => {
+				self.open_block();
+				let command = match arg.as_str() {
+					"1" => "section",
+					"2" => "subsection",
+					"3" => "subsubsection",
+					"4" => "paragraph",
+					_ => "subparagraph"
+				};
+				self.push(&format!("\\{}*{{", command));
+				self.in_header = true;
+			},
+			GroupType::Br => {
+				if self.in_header || self.cell_start.is_some() {
+					self.push(" ");
+				} else if !self.blocks.block_start {
+					self.push("\\newline\n");
+				}
+			},
+			GroupType::Hr => {
+				self.open_block();
+				self.push("\\noindent\\rule{\\linewidth}{0.4pt}");
+			},
+			GroupType::Scenebreak => {
+				self.open_block();
+				self.push("\\begin{center}*\\quad*\\quad*\\end{center}");
+			},
+			GroupType::Quote => {self.begin("quote", "")},
+			GroupType::Center => {self.begin("center", "")},
+			GroupType::Right => {self.begin("flushright", "")},
+			GroupType::Indent if valid => {
+				self.open_block();
+				self.push(&format!("{{\\leftskip={}em\\relax\n", arg.parse::<usize>().unwrap_or(1) * 2));
+				self.blocks.block_start = true;
+			},
+			GroupType::Pre => {
+				self.begin("alltt", "");
+			},
+			GroupType::CodeBlock => {
+				self.begin("verbatim", "");
+				self.verbatim = true;
+			},
+			GroupType::List => {
+				let environment = match arg.as_str() {
+					"1" | "a" | "A" | "i" | "I" if valid => "enumerate",
+					_ => "itemize"
+				};
+				let label = match arg.as_str() {
+					"a" => "[label=\\alph*.]",
+					"A" => "[label=\\Alph*.]",
+					"i" => "[label=\\roman*.]",
+					"I" => "[label=\\Roman*.]",
+					"circle" => "[label=$\\circ$]",
+					"square" => "[label=\\rule[0.2ex]{0.8ex}{0.8ex}]",
+					"none" => "[label={}]",
+					_ => ""
+				};
+				self.begin(environment, label);
+			},
+			GroupType::ListItem => {
+				if !self.output_string.ends_with('\n') {
+					self.output_string.push('\n');
+				}
+				self.output_string.push_str("\\item ");
+				self.blocks.block_start = true;
+				self.blocks.block_ended = false;
+			},
+			GroupType::Table => {
+				let columns = node.children()
+					.filter(|child| child.borrow().ele_type() == &GroupType::TableRow)
+					.map(|row| row.children().count())
+					.max()
+					.unwrap_or(1)
+					.max(1);
+				self.begin("center", "");
+				self.push(&format!("\\begin{{tabular}}{{{}}}\n\\hline", "l".repeat(columns)));
+			},
+			GroupType::TableRow => {
+				self.output_string.push('\n');
+				self.blocks.block_ended = false;
+			},
+			GroupType::TableHeader | GroupType::TableData => {
+				if node.previous_sibling().is_some() {
+					self.push(" & ");
+				}
+				if element.ele_type() == &GroupType::TableHeader {
+					self.push("\\textbf{");
+				}
+				self.cell_start = Some(true);
+			},
+			GroupType::TableCaption => {
+				self.blocks.skip = Some(node.clone());
+			},
+			GroupType::Broken(_, tag) if !self.pretty_print => {
+				match element.argument() {
+					Some(arg) => self.push(&escape_latex(&format!("[{}={}]", tag, arg))),
+					None => self.push(&escape_latex(&format!("[{}]", tag)))
+				}
+				if let Some(text) = element.text_contents() {
+					self.push(&escape_latex(text));
+				}
+			},
+			GroupType::Custom(_) if self.is_block(element.ele_type()) => {self.open_block()},
+			_ => {}
+		};
+	}
+
+	/// Ends an element.
+	fn end_element(&mut self, node: &Node<ASTElement>) {
+		let element = node.borrow();
+		if self.is_block(element.ele_type()) && self.cell_start.is_none() {
+			self.blocks.block_ended = true;
+		}
+		let valid = attribute_value(&element, &self.url_policy).is_some();
+		match element.ele_type() {
+			GroupType::Bold |
+			GroupType::Strong |
+			GroupType::Italic |
+			GroupType::Emphasis |
+			GroupType::Underline |
+			GroupType::Strikethrough |
+			GroupType::Smallcaps |
+			GroupType::Monospace |
+			GroupType::Code |
+			GroupType::Subscript |
+			GroupType::Superscript |
+			GroupType::Footnote
+				=> {self.output_string.push('}')},
+			GroupType::Colour |
+			GroupType::Size |
+			GroupType::Url |
+			GroupType::Email
+				if valid => {self.output_string.push('}')},
+			GroupType::Math if self.verbatim => {
+				self.output_string.push('$');
+				self.verbatim = false;
+			},
+			GroupType::MathBlock if self.verbatim => {
+				self.output_string.push_str("\\]");
+				self.verbatim = false;
+			},
+			GroupType::Header if valid => {
+				self.output_string.push('}');
+				self.in_header = false;
+			},
+			GroupType::Quote => {
+				if let Some(author) = element.argument() {
+					self.output_string.push_str(&format!("\n\n\\hfill--- {}", escape_latex(author)));
+				}
+				self.end("quote");
+			},
+			GroupType::Center => {self.end("center")},
+			GroupType::Right => {self.end("flushright")},
+			GroupType::Indent if valid => {
+				self.output_string.push_str("\\par}");
+				self.blocks.block_start = false;
+			},
+			GroupType::Pre => {
+				self.end("alltt");
+			},
+			GroupType::CodeBlock => {
+				self.end("verbatim");
+				self.verbatim = false;
+			},
+			GroupType::List => {
+				let environment = match element.argument().as_deref() {
+					Some("1" | "a" | "A" | "i" | "I") if valid => "enumerate",
+					_ => "itemize"
+				};
+				self.end(environment);
+			},
+			GroupType::TableRow => {self.output_string.push_str(" \\\\")},
+			GroupType::TableHeader | GroupType::TableData => {
+				if element.ele_type() == &GroupType::TableHeader {
+					self.output_string.push('}');
+				}
+				self.cell_start = None;
+			},
+			GroupType::Table => {
+				self.output_string.push_str("\n\\hline\n\\end{tabular}");
+				let caption: String = node.children()
+					.filter(|child| child.borrow().ele_type() == &GroupType::TableCaption)
+					.map(|caption| node_text(&caption))
+					.collect();
+				if !caption.trim().is_empty() {
+					self.output_string.push_str(&format!("\n\n{}", escape_latex(caption.trim())));
+				}
+				self.end("center");
+			},
+			GroupType::Broken(_, tag) if !self.pretty_print && !element.is_void() => {
+				self.push(&escape_latex(&format!("[/{}]", tag)));
+			},
+			_ => {}
+		};
+	}
+
+	/// Gets the escaped URL to load an image or embed from, as given by the MediaPolicy.
+	fn media_source(&self, element: &ASTElement) -> Option<String> {
+		attribute_value(element, &self.url_policy)?;
+		let url = Url::parse(element.argument().as_deref()?).ok()?;
+		self.media_policy.source(&url)
+	}
+
+	/// Opens an environment as a new block, with the given options.
+	fn begin(&mut self, environment: &str, options: &str) {
+		self.open_block();
+		self.output_string.push_str(&format!("\\begin{{{}}}{}\n", environment, options));
+		self.blocks.block_start = true;
+	}
+
+	/// Closes an environment.
+	fn end(&mut self, environment: &str) {
+		self.output_string.push_str(&format!("\n\\end{{{}}}", environment));
+		self.blocks.block_start = false;
+	}
+
+	/// Writes LaTeX as-is.
+	fn push(&mut self, latex: &str) {
+		self.output_string.push_str(latex);
+		self.blocks.block_start = false;
+		if self.cell_start.is_some() {
+			self.cell_start = Some(false);
+		}
+	}
+}
+impl BlockConstructor for LatexConstructor {
+	fn blocks(&mut self) -> &mut BlockState {
+		&mut self.blocks
+	}
+	fn registry(&self) -> Option<&Rc<TagRegistry>> {
+		self.registry.as_ref()
+	}
+	fn start(&mut self, node: &Node<ASTElement>) {
+		self.start_element(node);
+	}
+	fn end(&mut self, node: &Node<ASTElement>) {
+		self.end_element(node);
+	}
+	fn separate_blocks(&mut self) {
+		self.output_string.push_str("\n\n");
+	}
+}
+
+/// Gets whether maths can be written as-is: every command in it is a known maths command, and it has
+/// no characters that could end its maths mode early, start a comment or run a macro, such as `$`,
+/// `%`, `~` or `^^` notation.
+fn safe_maths(maths: &str) -> bool {
+	let mut rest = maths;
+	while let Some(c) = rest.chars().next() {
+		rest = &rest[c.len_utf8()..];
+		match c {
+			'$' | '%' | '#' | '~' => return false,
+			'^' if rest.starts_with('^') => return false,
+			'\\' => {
+				let name_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+				let (name, after) = rest.split_at(name_len);
+				rest = match name {
+					"" => match after.chars().next() {
+						Some(symbol) if MATHS_SYMBOLS.contains(&symbol) => &after[1..],
+						_ => return false
+					},
+					"begin" | "end" => match after.strip_prefix('{').and_then(|after| after.split_once('}')) {
+						Some((environment, after)) if MATHS_ENVIRONMENTS.contains(environment) => after,
+						_ => return false
+					},
+					name if MATHS_COMMANDS.contains(name) => after,
+					_ => return false
+				};
+			},
+			_ => {}
+		}
+	}
+	true
+}
+
+/// The characters that can follow a backslash in maths, for spacing and escaped characters.
+const MATHS_SYMBOLS: [char; 14] = [',', ';', ':', '!', '>', ' ', '{', '}', '|', '\\', '&', '%', '#', '_'];
+
+/// Static compile-time set of the environments that can be used in maths.
+static MATHS_ENVIRONMENTS: phf::Set<&'static str> = phf_set! {
+	"matrix", "pmatrix", "bmatrix", "Bmatrix", "vmatrix", "Vmatrix", "smallmatrix", "cases", "aligned",
+	"gathered", "split", "array",
+};
+
+/// Static compile-time set of the commands that can be used in maths: symbols, operators, accents,
+/// delimiters, fonts and spacing, none of which can define macros, change category codes or use files.
+static MATHS_COMMANDS: phf::Set<&'static str> = phf_set! {
+	"alpha", "beta", "gamma", "delta", "epsilon", "varepsilon", "zeta", "eta", "theta", "vartheta", "iota",
+	"kappa", "lambda", "mu", "nu", "xi", "pi", "varpi", "rho", "varrho", "sigma", "varsigma", "tau",
+	"upsilon", "phi", "varphi", "chi", "psi", "omega", "Gamma", "Delta", "Theta", "Lambda", "Xi", "Pi",
+	"Sigma", "Upsilon", "Phi", "Psi", "Omega",
+	"pm", "mp", "times", "div", "cdot", "ast", "star", "circ", "bullet", "cap", "cup", "vee", "wedge",
+	"setminus", "oplus", "ominus", "otimes", "oslash", "odot", "dagger", "ddagger",
+	"leq", "le", "leqslant", "geq", "ge", "geqslant", "neq", "ne", "equiv", "approx", "sim", "simeq",
+	"cong", "propto", "subset", "supset", "subseteq", "supseteq", "in", "notin", "ni", "ll", "gg", "perp",
+	"parallel", "mid", "not", "colon",
+	"to", "gets", "leftarrow", "rightarrow", "Leftarrow", "Rightarrow", "leftrightarrow", "Leftrightarrow",
+	"longrightarrow", "Longrightarrow", "mapsto", "implies", "iff", "uparrow", "downarrow",
+	"infty", "partial", "nabla", "forall", "exists", "neg", "lnot", "emptyset", "varnothing", "prime",
+	"angle", "triangle", "hbar", "ell", "Re", "Im", "aleph", "ldots", "cdots", "vdots", "ddots", "dots",
+	"sum", "prod", "coprod", "int", "iint", "iiint", "oint", "bigcup", "bigcap", "bigvee", "bigwedge",
+	"bigoplus", "bigotimes", "limits", "nolimits",
+	"lim", "limsup", "liminf", "sup", "inf", "max", "min", "arg", "det", "dim", "exp", "gcd", "hom", "ker",
+	"log", "ln", "lg", "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh",
+	"cosh", "tanh", "deg", "Pr", "bmod", "pmod", "operatorname",
+	"frac", "dfrac", "tfrac", "sqrt", "binom", "choose", "over", "overline", "underline", "overbrace",
+	"underbrace", "overset", "underset", "stackrel", "boxed", "phantom",
+	"hat", "bar", "vec", "dot", "ddot", "tilde", "widetilde", "widehat", "check", "breve", "acute", "grave",
+	"left", "right", "middle", "big", "Big", "bigg", "Bigg", "langle", "rangle", "lfloor", "rfloor",
+	"lceil", "rceil", "lbrace", "rbrace", "vert", "Vert", "lvert", "rvert", "lVert", "rVert", "backslash",
+	"quad", "qquad", "displaystyle", "textstyle", "scriptstyle", "hline",
+	"text", "textrm", "textbf", "textit", "mathrm", "mathbf", "mathit", "mathsf", "mathtt", "mathcal",
+	"mathbb", "mathfrak", "boldsymbol",
+};
+
+/// Escapes the characters LaTeX treats specially. Square brackets are braced, so that text can't be
+/// read as the optional argument of a command such as `\item`.
+fn escape_latex(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'\\' => escaped.push_str("\\textbackslash{}"),
+			'{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+				escaped.push('\\');
+				escaped.push(c);
+			},
+			'^' => escaped.push_str("\\textasciicircum{}"),
+			'~' => escaped.push_str("\\textasciitilde{}"),
+			'<' => escaped.push_str("\\textless{}"),
+			'>' => escaped.push_str("\\textgreater{}"),
+			'|' => escaped.push_str("\\textbar{}"),
+			'[' => escaped.push_str("{[}"),
+			']' => escaped.push_str("{]}"),
+			_ => escaped.push(c)
+		}
+	}
+	escaped
+}
+
+/// Escapes a URL for `\href` and `\url`. Braces and backslashes are percent-encoded, as they can't
+/// be escaped there.
+fn escape_url(url: &str) -> String {
+	url.replace('\\', "%5C")
+		.replace('{', "%7B")
+		.replace('}', "%7D")
+		.replace('%', "\\%")
+		.replace('#', "\\#")
+}
+
+/// Gets the LaTeX size command nearest to a size in em.
+fn size_command(size: f32) -> &'static str {
+	let sizes = [
+		(0.55, "\\tiny"), (0.75, "\\scriptsize"), (0.85, "\\footnotesize"), (0.95, "\\small"),
+		(1.1, "\\normalsize"), (1.3, "\\large"), (1.55, "\\Large"), (1.85, "\\LARGE"), (2.2, "\\huge"),
+	];
+	sizes.iter().find(|(limit, _)| size < *limit).map_or("\\Huge", |(_, command)| command)
+}
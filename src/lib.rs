@@ -12,7 +12,7 @@ Our BBCode specification can be found [here](https://github.com/EndaHallahan/BBC
 use bbclash::bbcode_to_html;
 
 assert_eq!(bbcode_to_html("I'm [i]italic[/i] and [b]bold![/b]"), 
-		"<p>I&#x27m <i>italic</i> and <b>bold!</b></p>");
+		"<p>I'm <i>italic</i> and <b>bold!</b></p>");
 ```
 
 ## Pretty and Ugly Output
@@ -23,7 +23,7 @@ BBBClash has two main modes of operation: *pretty* and *ugly*. Pretty output use
 use bbclash::bbcode_to_html;
 
 assert_eq!(bbcode_to_html("I'm [colour]missing an argument![/colour]"), 
-		"<p>I&#x27m missing an argument!</p>");
+		"<p>I'm missing an argument!</p>");
 
 assert_eq!(bbcode_to_html("[quote][/quote]"), 
 		"");
@@ -35,7 +35,7 @@ Ugly uses the `bbcode_to_html_ugly` function, and leaves improperly formatted BB
 use bbclash::bbcode_to_html_ugly;
 
 assert_eq!(bbcode_to_html_ugly("I'm [colour]missing an argument![/colour]"), 
-		"<p>I&#x27m [colour]missing an argument![/colour]</p>");
+		"<p>I'm [colour]missing an argument![/colour]</p>");
 
 assert_eq!(bbcode_to_html_ugly("[quote][/quote]"), 
 		"<blockquote></blockquote>");
@@ -43,24 +43,468 @@ assert_eq!(bbcode_to_html_ugly("[quote][/quote]"),
 
 Note that neither mode arbitrarily strips any text in square brackets. This only affects improperly-written BBCode tags; `[non tags]` will not be affected.
 
+## Escaping
+
+Text is stored as written and escaped only when HTML is generated, using standard entities with terminating semicolons. Text content escapes `&`, `<` and `>`; attribute values also escape both kinds of quote. Arguments are also validated for the part of the HTML they are written into when rendering, so an Ast built or modified programmatically can't produce a `javascript:` link or inject CSS; an element whose argument is invalid is written as its contents alone. The same functions are exported as `escape_text` and `escape_attribute` for use in custom tag handlers:
+
+```rust
+use bbclash::bbcode_to_html;
+
+assert_eq!(bbcode_to_html("Fish & chips <3 [quote=\"Ann\"]Hi[/quote]"), 
+		"<p>Fish &amp; chips &lt;3 </p><blockquote data-author=\"&quot;Ann&quot;\"><p>Hi</p></blockquote>");
+```
+
+## Sanitised HTML
+
+HTML output only contains these elements and attributes: `a` (`href`, `rel`, `target`, `referrerpolicy`, `class`), `b`, `blockquote` (`data-author`), `br`, `caption`, `code`, `div` (`class`, `data-content`), `em`, `figure` (`class`), `h1` to `h6`, `hr`, `i`, `img` (`src`), `li`, `ol` (`type`), `p`, `pre` (`data-language`), `s`, `span` (`class`, `style`, `data-symbol`), `strong`, `sub`, `sup`, `table`, `td`, `th`, `tr` and `ul` (`class`, `style`). URLs are accepted by the `UrlPolicy`, and `style` attributes only set `color`, `opacity`, `font-size` and `list-style-type`. Custom TagHandlers are responsible for their own markup.
+
+`verify_html` checks HTML against this allowlist, and `ParserOptions::verify_html` runs it after every render, replacing output that fails with escaped plain text:
+
+```rust
+use bbclash::{render, verify_html, ParserOptions};
+
+let options = ParserOptions::new().verify_html(true);
+let html = render("[url=https://www.penclash.com]Link[/url] <script>", &options);
+assert_eq!(verify_html(&html, &options), Ok(()));
+```
+
+## Options
+
+`bbcode_to_html` and `bbcode_to_html_ugly` are shorthands for the most common configurations. For everything else, build a `ParserOptions` and pass it to `render`. The builder covers pretty or ugly output, the recognised tag set and the subset of it honoured in a given context, linebreak handling, URL policy, maximum nesting depth and output flavour:
+
+```rust
+use bbclash::{render, ParserOptions, LinebreakMode, UrlPolicy};
+
+let options = ParserOptions::new()
+    .linebreaks(LinebreakMode::Space)
+    .url_policy(UrlPolicy::new().require_scheme(true))
+    .max_depth(8);
+
+assert_eq!(render("[url=www.penclash.com]Penclash[/url]\nhome", &options),
+		"<p>Penclash home</p>");
+```
+
+## URLs
+
+URLs in `[url]`, `[img]` and `[embed]` tags are parsed, and checked against the `UrlPolicy`. By default, `http` and `https` URLs are accepted, and URLs without a scheme, such as `www.penclash.com:8080`, are given `http://`. The policy's scheme allowlist can be replaced or extended, and relative URLs can be allowed. Rejected URLs are reported as diagnostics with the reason, which `UrlPolicy::check` also returns as a `UrlError`:
+
+```rust
+use bbclash::{render, ParserOptions, UrlPolicy};
+
+let options = ParserOptions::new()
+    .url_policy(UrlPolicy::new().schemes(&["https", "mailto"]).allow_relative(true));
+
+assert_eq!(render("[url=mailto:enda@penclash.com]Mail[/url] or [url=/about#team]About[/url]", &options),
+		"<p><a href=\"mailto:enda@penclash.com\">Mail</a> or <a href=\"/about#team\">About</a></p>");
+```
+
+## Links
+
+The attributes of links written by `[url]` and `[email]` are decided by the `LinkPolicy`. Links are internal if their URL has no host, or points at one of the policy's internal domains or a subdomain of one; every other link is external. By default, external links get `rel="nofollow"`. The `rel`, `target`, `referrerpolicy` and `class` attributes can be set separately for internal and external links, or decided per URL by a `LinkHandler`:
+
+```rust
+use bbclash::{render, ParserOptions, LinkPolicy, LinkAttributes};
+
+let options = ParserOptions::new().link_policy(LinkPolicy::new()
+    .internal_domains(&["penclash.com"])
+    .external(LinkAttributes::new().rel("nofollow noopener ugc")));
+
+assert_eq!(render("[url=penclash.com]Home[/url] or [url=example.com]away[/url]", &options),
+		"<p><a href=\"http://penclash.com\">Home</a> or <a href=\"http://example.com\" rel=\"nofollow noopener ugc\">away</a></p>");
+```
+
+Markdown links can't carry attributes, so the policy only applies to HTML output.
+
+## Images and Embeds
+
+The `MediaPolicy` controls where `[img]` and `[embed]` content can be loaded from. A `HostFilter` allows or denies hosts, including their subdomains; tags whose host isn't permitted are treated as broken. A `UrlRewriter` can rewrite content URLs when rendering, such as to route them through an image proxy, so that readers' browsers never contact the original host:
+
+```rust
+use bbclash::{render, ParserOptions, MediaPolicy, HostFilter};
+
+let options = ParserOptions::new()
+    .media_policy(MediaPolicy::new().hosts(HostFilter::allow(&["imgur.com"])));
+
+assert_eq!(render("[img]https://i.imgur.com/cat.png[/img][img]https://tracker.example/pixel.gif[/img]", &options),
+		"<p><img src=\"https://i.imgur.com/cat.png\"></p>");
+```
+
+## Colours, Sizes and Opacity
+
+`[colour]` accepts named web colours, 3-, 4-, 6- and 8-digit hex, and the `rgb()` and `hsl()` functions, in any case. Colours are parsed into a `Colour` and written in a canonical form, so an argument can never add other CSS to the `style` attribute. `[size]` and `[opacity]` only accept plain decimal numbers:
+
+```rust
+use bbclash::bbcode_to_html;
+
+assert_eq!(bbcode_to_html("[colour=RGB(0, 128, 0)]Green[/colour] and [colour=Red]red[/colour]"),
+		"<p><span style=\"color:#008000;\">Green</span> and <span style=\"color:red;\">red</span></p>");
+```
+
+## Styling With Classes
+
+Colours, sizes, opacities and list styles are written as inline `style` attributes by default, which a Content-Security-Policy without `unsafe-inline` blocks. `StylePolicy::classes` writes classes instead, quantising values to the nearest entry of a configurable palette and scales. `stylesheet` gets the matching CSS to serve from a file:
+
+```rust
+use bbclash::{render, ParserOptions, StylePolicy};
+
+let options = ParserOptions::new().style_policy(StylePolicy::classes());
+assert_eq!(render("[colour=#e01020]Red[/colour] and [opacity=40%]faint[/opacity]", &options),
+		"<p><span class=\"bb-color-red\">Red</span> and <span class=\"bb-opacity-0_5\">faint</span></p>");
+```
+
+## Limits
+
+Untrusted input can be bounded with `max_depth`, `max_nodes`, `max_quote_depth`, and `max_input_length`. Markup past a limit isn't dropped: it's shown as text, and a `LimitExceeded` diagnostic is reported:
+
+```rust
+use bbclash::{render, ParserOptions};
+
+let options = ParserOptions::new().max_quote_depth(1);
+assert_eq!(render("[quote][quote]Nested[/quote][/quote]", &options),
+		"<blockquote><p>[quote]Nested[/quote]</p></blockquote>");
+```
+
+## Inspecting Parsed BBCode
+
+`parse` produces an `Ast` that can be walked, inspected, and modified without rendering it, and `render_ast` renders it:
+
+```rust
+use bbclash::{parse, render_ast, ParserOptions, GroupType};
+
+let options = ParserOptions::new();
+let ast = parse("[b]Bold[/b] and [url=https://www.penclash.com]a link[/url]", &options);
+let links = ast.descendants().filter(|node| node.kind() == GroupType::Url).count();
+assert_eq!(links, 1);
+assert_eq!(ast.root().text_content(), "Bold and a link");
+assert_eq!(render_ast(&ast, &options), 
+		"<p><b>Bold</b> and <a href=\"https://www.penclash.com\" rel=\"nofollow\">a link</a></p>");
+```
+
+## Content Statistics
+
+`Ast::stats` gathers figures for anti-spam and moderation rules: the number of links and their distinct domains, images and embeds, and how much text is in spoilers, invisible, or tiny:
+
+```rust
+use bbclash::{parse, ParserOptions};
+
+let stats = parse("[url=https://spam.example]Buy[/url] [opacity=0]hidden keywords[/opacity]", &ParserOptions::new()).stats();
+assert_eq!(stats.links, 1);
+assert!(stats.link_domains.contains("spam.example"));
+assert_eq!(stats.hidden_text, 14);
+```
+
+## Normalising BBCode
+
+With `OutputFlavour::BBCode`, `render` writes canonical BBCode instead of HTML: tags are lowercased, unclosed tags are closed, misnested tags are reordered, and broken tags are removed (or escaped, in ugly mode). Normalised BBCode renders to the same HTML and normalises to itself, so it can be stored and re-edited safely:
+
+```rust
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+let options = ParserOptions::new().flavour(OutputFlavour::BBCode);
+assert_eq!(render("[B]Bold [i]both[/b] italic", &options),
+		"[b]Bold [i]both[/i][/b][i] italic[/i]");
+```
+
+## Converting HTML to BBCode
+
+`html_to_bbcode` converts HTML back to BBCode, such as rich text pasted into an editor. The elements and classes BBClash writes map back to their tags, as do common inline styles like `font-weight:bold` and `color`; unknown elements are dropped but their text is kept, and scripts and styles are dropped entirely. Links and images whose URLs the default `UrlPolicy` rejects keep only their text. The result is normalised, and converting BBClash's own HTML gives BBCode that renders to the same HTML:
+
+```rust
+use bbclash::html_to_bbcode;
+
+assert_eq!(html_to_bbcode("<p><span style=\"font-weight:bold\">Bold</span> and <blink>plain</blink></p>"),
+		"[b]Bold[/b] and plain");
+```
+
+## Converting Markdown to BBCode
+
+`markdown_to_bbcode` converts CommonMark to BBCode, with GitHub-style tables, footnotes and strikethrough. Fenced code becomes `[codeblock=lang]`, quotes become `[quote]`, ordered lists become `[list=1]`, tables become `[table]`, and footnotes are written inline at their first reference as `[footnote]`. Links and images whose URLs the default `UrlPolicy` rejects keep only their text. It depends on `pulldown-cmark`, and can be left out by turning off the default `markdown` feature:
+
+```rust
+# #[cfg(feature = "markdown")] {
+use bbclash::markdown_to_bbcode;
+
+assert_eq!(markdown_to_bbcode("# Title\n\n```rust\nlet x = 1;\n```\n\n1. One\n2. Two"),
+		"[h1]Title[/h1][codeblock=rust]let x = 1;[/codeblock][list=1][*]One[*]Two[/list]");
+# }
+```
+
+## Markdown Output
+
+With `OutputFlavour::Markdown`, `render` writes CommonMark. Constructs Markdown lacks, such as underline and spoilers, are written as inline HTML, and blocks such as tables and centred text as HTML blocks:
+
+```rust
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+let options = ParserOptions::new().flavour(OutputFlavour::Markdown);
+assert_eq!(render("[h2]Hello[/h2][list=1][*][b]Bold[/b][*][u]Underlined[/u][/list]", &options),
+		"## Hello\n\n1. **Bold**\n2. <span class=\"underline\">Underlined</span>");
+```
+
+## Plain Text Output
+
+With `OutputFlavour::PlainText`, `render` strips all formatting, for previews, notifications and search indexing. Blocks are separated by blank lines, list items become bullet lines, and links are followed by their address:
+
+```rust
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+let options = ParserOptions::new().flavour(OutputFlavour::PlainText);
+assert_eq!(render("[b]Fish & chips[/b] at [url=https://www.penclash.com]Penclash[/url][list][*]One[*]Two[/list]", &options),
+		"Fish & chips at Penclash (https://www.penclash.com)\n\n• One\n• Two");
+```
+
+## LaTeX Output
+
+With `OutputFlavour::Latex`, `render` writes the body of a LaTeX document for print exports, using the `hyperref`, `xcolor`, `ulem` (with `normalem`), `enumitem` and `alltt` packages. Headers become starred sectioning commands, quotes, lists and tables become `quote`, `itemize`/`enumerate` and `tabular` environments, footnotes become `\footnote`, and text is escaped. Maths is passed through as written if it only uses an allowlist of maths commands and environments, such as `\frac`, `\alpha` and `\begin{pmatrix}`, and none of `$`, `%`, `#`, `~` or `^^`. Other maths, such as any using `\input`, `\csname`, `\def` or `\catcode`, is escaped as text instead:
+
+```rust
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+let options = ParserOptions::new().flavour(OutputFlavour::Latex);
+assert_eq!(render("[h1]Costs[/h1]About 50% [b]more[/b] than [math]x^2[/math]", &options),
+		"\\section*{Costs}\n\nAbout 50\\% \\textbf{more} than $x^2$");
+```
+
+## XHTML and EPUB Output
+
+With `OutputFlavour::Xhtml`, `render` writes well-formed XHTML for EPUB. Void elements are self-closed, images get an empty `alt`, characters XML forbids are removed, scene breaks become `<hr class="scenebreak"/>`, and footnotes become `epub:type="noteref"` links, with their text in `epub:type="footnote"` asides at the end of the output. The output is a fragment for a document body, whose root must declare the `epub` namespace:
+
+```rust
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+let options = ParserOptions::new().flavour(OutputFlavour::Xhtml);
+assert_eq!(render("The end.[footnote]For now.[/footnote]\n\n\nEpilogue.", &options),
+		"<p>The end.<a epub:type=\"noteref\" href=\"#fn-1\" id=\"fnref-1\">1</a></p><hr class=\"scenebreak\"/><p>Epilogue.</p>\
+		<aside epub:type=\"footnote\" id=\"fn-1\"><p>For now.</p></aside>");
+```
+
+## Terminal Output
+
+With `OutputFlavour::Ansi`, `render` writes text for terminals, styled with ANSI escape codes. Bold, italic and underline become SGR attributes, colours become the nearest of the 256 standard colours, or exact 24-bit colours with `ColourMode::TrueColour`, quotes get a `│` gutter and list items get bullets or numbers. Text is word-wrapped to `terminal_width` characters, 80 by default, and `ColourMode::Plain` leaves out the escape codes for terminals without colour:
+
+```rust
+use bbclash::{render, ParserOptions, OutputFlavour, ColourMode};
+
+let options = ParserOptions::new().flavour(OutputFlavour::Ansi);
+assert_eq!(render("A [b]bold[/b] and [color=red]red[/color] claim", &options),
+		"A \x1b[1mbold\x1b[0m and \x1b[38;5;196mred\x1b[0m claim");
+
+let options = options.colour_mode(ColourMode::Plain).terminal_width(12);
+assert_eq!(render("[quote]The quick brown fox[/quote]", &options),
+		"│ The quick\n│ brown fox");
+```
+
+## Diagnostics
+
+`check` reports problems such as unknown, unclosed, or misnested tags and invalid arguments, each with the Span of the input it concerns. The same Diagnostics are available from `Ast::diagnostics` after parsing:
+
+```rust
+use bbclash::{check, ParserOptions, DiagnosticKind};
+
+let diagnostics = check("[i]Italic[/b]", &ParserOptions::new());
+assert_eq!(diagnostics[0].kind, DiagnosticKind::StrayCloseTag);
+assert_eq!(diagnostics[0].to_string(), "1:10: `[/b]` has no matching opening tag");
+```
+
 ## Custom Usage:
 
-Because this package was built for an existing application, and because it is performance-focused, BBClash's BBCode implementation is entirely hard-coded. Because of this, it is reccommended that you download a local copy and modify it to suit your needs. 
+BBClash's tag set lives in a `TagRegistry`, which maps tag names to lexer commands. The default registry reproduces the tags described in the specification; tags can be added, overridden, or removed at runtime and handed to a `BBCodeLexer`:
+
+```rust
+use std::rc::Rc;
+use bbclash::{BBCodeTokenizer, BBCodeLexer, HTMLConstructor, TagRegistry, GroupType};
+
+fn bold_open(lexer: &mut BBCodeLexer) {
+    lexer.new_group(GroupType::Bold);
+}
+fn bold_close(lexer: &mut BBCodeLexer) {
+    lexer.end_group(GroupType::Bold);
+}
+
+let mut registry = TagRegistry::default();
+registry.register("bold", bold_open);
+registry.register("/bold", bold_close);
+registry.remove("spoiler");
+registry.remove("/spoiler");
+
+let input = "[bold]Loud[/bold] and [spoiler]secret[/spoiler]";
+let mut tokenizer = BBCodeTokenizer::new();
+let mut lexer = BBCodeLexer::with_registry(false, Rc::new(registry));
+let mut constructor = HTMLConstructor::new(input.len(), true);
+assert_eq!(constructor.construct(lexer.lex(tokenizer.tokenize(input))),
+		"<p><b>Loud</b> and [spoiler]secret[/spoiler]</p>");
+```
+
+Tags that need their own HTML can implement the `TagHandler` trait, which validates the tag's argument, decides whether it is a block or inline element, and renders its opening and closing HTML. Elements created by a handler carry the type `GroupType::Custom`; pass the same registry to `HTMLConstructor::with_registry` to render them.
+
+For deeper changes it is still reasonable to download a local copy and modify it to suit your needs. 
 
 Building is as simple as running `$ cargo build`. Tests and benchmarks can be run with `$ cargo test` and `$ cargo bench`, respectively.
 */
 
 #![feature(proc_macro_hygiene)]
+#![allow(clippy::tabs_in_doc_comments)]
 extern crate rctree;
 extern crate phf;
 
+use std::borrow::Cow;
+
 mod bbcode_tokenizer;
 mod bbcode_lexer;
 mod html_constructor;
+mod bbcode_constructor;
+mod markdown_constructor;
+mod plain_text_constructor;
+mod latex_constructor;
+mod ansi_constructor;
+mod block_constructor;
+mod tag_registry;
+mod parser_options;
+mod tag_filter;
+mod ast;
+mod diagnostics;
+mod escape;
+mod url;
+mod link_policy;
+mod media_policy;
+mod colour;
+mod style_policy;
+mod sanitise;
+mod content_stats;
+mod html_to_bbcode;
+#[cfg(feature = "markdown")]
+mod markdown_to_bbcode;
 
 pub use crate::bbcode_tokenizer::BBCodeTokenizer;
 pub use crate::bbcode_lexer::BBCodeLexer;
 pub use crate::html_constructor::HTMLConstructor;
+pub use crate::bbcode_constructor::BBCodeConstructor;
+pub use crate::markdown_constructor::MarkdownConstructor;
+pub use crate::plain_text_constructor::PlainTextConstructor;
+pub use crate::latex_constructor::LatexConstructor;
+pub use crate::ansi_constructor::AnsiConstructor;
+pub use crate::tag_registry::{TagRegistry, TagHandler, NoArgCommand, OneArgCommand};
+pub use crate::parser_options::{ParserOptions, OutputFlavour, LinebreakMode, ColourMode, UrlPolicy};
+pub use crate::tag_filter::TagFilter;
+pub use crate::ast::{Ast, AstNode, Attribute, Children, Descendants};
+pub use crate::diagnostics::{Diagnostic, DiagnosticKind};
+pub use crate::escape::{escape_text, escape_attribute};
+pub use crate::url::{Url, UrlError};
+pub use crate::link_policy::{LinkPolicy, LinkAttributes, LinkHandler};
+pub use crate::media_policy::{MediaPolicy, HostFilter, UrlRewriter};
+pub use crate::colour::Colour;
+pub use crate::style_policy::StylePolicy;
+pub use crate::sanitise::{verify_html, HtmlViolation};
+pub use crate::content_stats::ContentStats;
+pub use crate::html_to_bbcode::html_to_bbcode;
+#[cfg(feature = "markdown")]
+pub use crate::markdown_to_bbcode::markdown_to_bbcode;
+
+/// Generates a string of output from an &str of BBCode, as configured by a set of ParserOptions.
+/// # Examples
+///
+/// ```
+///use bbclash::{render, ParserOptions};
+///
+///assert_eq!(render("I'm [i]italic[/i] and [b]bold![/b]", &ParserOptions::new()), 
+///		"<p>I'm <i>italic</i> and <b>bold!</b></p>");
+///
+///assert_eq!(render("[quote][/quote]", &ParserOptions::new().pretty_print(false)), 
+///		"<blockquote></blockquote>");
+/// ```
+pub fn render(input: &str, options: &ParserOptions) -> String {
+	render_ast(&parse(input, options), options)
+}
+
+/// Parses an &str of BBCode into an Ast, as configured by a set of ParserOptions.
+pub fn parse(input: &str, options: &ParserOptions) -> Ast {
+	let mut tokenizer = BBCodeTokenizer::new();
+	let mut lexer = BBCodeLexer::with_options(options);
+	let max_length = options.max_input_length.unwrap_or(usize::MAX);
+	if input.len() <= max_length {
+		tokenizer.tokenize(input);
+		let anchor = lexer.lex_with_spans(tokenizer.instructions(), tokenizer.spans());
+		return Ast::from_lexed(anchor, input.len(), lexer.diagnostics().to_vec());
+	}
+	let mut end = max_length;
+	while !input.is_char_boundary(end) {
+		end -= 1;
+	}
+	let (markup, excess) = input.split_at(end);
+	tokenizer.tokenize(markup);
+	let excess_span = Span {
+		start: end,
+		end: input.len(),
+		line: markup.matches('\n').count() + 1,
+		column: markup.rsplit('\n').next().unwrap_or_default().chars().count() + 1,
+	};
+	let mut instructions = tokenizer.instructions().to_vec();
+	let mut spans = tokenizer.spans().to_vec();
+	instructions.push(Instruction::Text(excess.to_string()));
+	spans.push(excess_span);
+	let anchor = lexer.lex_with_spans(&instructions, &spans);
+	let mut diagnostics = lexer.diagnostics().to_vec();
+	diagnostics.push(Diagnostic {
+		kind: DiagnosticKind::LimitExceeded,
+		span: Some(excess_span),
+		message: format!("the input is longer than {} bytes, so the rest is shown as text", max_length),
+	});
+	Ast::from_lexed(anchor, input.len(), diagnostics)
+}
+
+/// Checks an &str of BBCode for problems without rendering it, as configured by a set of ParserOptions.
+/// # Examples
+///
+/// ```
+///use bbclash::{check, ParserOptions, DiagnosticKind};
+///
+///let diagnostics = check("[b]Bold [colour=nope]text[/colour]", &ParserOptions::new());
+///assert_eq!(diagnostics[0].kind, DiagnosticKind::InvalidArgument);
+///assert_eq!(diagnostics[1].kind, DiagnosticKind::UnclosedTag);
+///assert_eq!(diagnostics[1].to_string(), "1:1: `[b]` is never closed");
+/// ```
+pub fn check(input: &str, options: &ParserOptions) -> Vec<Diagnostic> {
+	parse(input, options).diagnostics().to_vec()
+}
+
+/// Generates a string of output from an Ast, as configured by a set of ParserOptions.
+pub fn render_ast(ast: &Ast, options: &ParserOptions) -> String {
+	match options.flavour {
+		OutputFlavour::Html => {
+			let mut constructor = HTMLConstructor::with_options(ast.source_len(), options);
+			let html = constructor.construct(ast.anchor());
+			if options.verify_html && verify_html(&html, options).is_err() {
+				let text = PlainTextConstructor::with_options(ast.source_len(), options).construct(ast.anchor());
+				return if text.is_empty() {text} else {format!("<p>{}</p>", escape_text(&text))};
+			}
+			html
+		},
+		OutputFlavour::BBCode => {
+			let mut constructor = BBCodeConstructor::with_options(ast.source_len(), options);
+			constructor.construct(ast.anchor())
+		},
+		OutputFlavour::Markdown => {
+			let mut constructor = MarkdownConstructor::with_options(ast.source_len(), options);
+			constructor.construct(ast.anchor())
+		},
+		OutputFlavour::PlainText => {
+			let mut constructor = PlainTextConstructor::with_options(ast.source_len(), options);
+			constructor.construct(ast.anchor())
+		},
+		OutputFlavour::Latex => {
+			let mut constructor = LatexConstructor::with_options(ast.source_len(), options);
+			constructor.construct(ast.anchor())
+		},
+		OutputFlavour::Ansi => {
+			let mut constructor = AnsiConstructor::with_options(ast.source_len(), options);
+			constructor.construct(ast.anchor())
+		},
+		OutputFlavour::Xhtml => {
+			let mut constructor = HTMLConstructor::with_options(ast.source_len(), options);
+			constructor.construct(ast.anchor())
+		}
+	}
+}
 
 /// Generates a string of HTML from an &str of BBCode.
 /// This function produces *pretty* output, meaning that any eroneously written BBCode encountered or empty tags will be removed from the final output.
@@ -70,7 +514,7 @@ pub use crate::html_constructor::HTMLConstructor;
 ///use bbclash::bbcode_to_html;
 ///
 ///assert_eq!(bbcode_to_html("I'm [i]italic[/i] and [b]bold![/b]"), 
-///		"<p>I&#x27m <i>italic</i> and <b>bold!</b></p>");
+///		"<p>I'm <i>italic</i> and <b>bold!</b></p>");
 ///
 ///assert_eq!(bbcode_to_html("[quote][/quote]"), 
 ///		"");
@@ -91,7 +535,7 @@ pub fn bbcode_to_html(input: &str) -> String {
 ///use bbclash::bbcode_to_html_ugly;
 ///
 ///assert_eq!(bbcode_to_html_ugly("I'm [colour]missing an argument![/colour]"), 
-///		"<p>I&#x27m [colour]missing an argument![/colour]</p>");
+///		"<p>I'm [colour]missing an argument![/colour]</p>");
 ///
 ///assert_eq!(bbcode_to_html_ugly("[quote][/quote]"), 
 ///		"<blockquote></blockquote>");
@@ -113,6 +557,7 @@ pub struct ASTElement {
 	is_void: bool,
 	detachable: bool,
 	broken: bool,
+	span: Option<Span>,
 }
 impl ASTElement {
 	/// Creates a new ASTElement.
@@ -121,18 +566,13 @@ impl ASTElement {
 		let argument = None;
 		let is_void = false;
 		let detachable = true;
-		let broken = match ele_type {
-			GroupType::Broken(_,_) => true,
-			_ => false
-		};
-		ASTElement{ele_type, text_contents, argument, is_void, detachable, broken}
+		let broken = matches!(ele_type, GroupType::Broken(_,_));
+		let span = None;
+		ASTElement{ele_type, text_contents, argument, is_void, detachable, broken, span}
 	}
 	/// Sets an ASTElement's type.
 	pub fn set_ele_type(&mut self, new_type: GroupType) {
-		self.broken = match new_type {
-			GroupType::Broken(_,_) => true,
-			_ => false
-		};
+		self.broken = matches!(new_type, GroupType::Broken(_,_));
 		self.ele_type = new_type;
 	}
 	/// Gets an immutable reference to an ASTElement's type.
@@ -149,12 +589,10 @@ impl ASTElement {
 	}
 	/// Adds text to an ASTElement.
 	pub fn add_text(&mut self, new_text: &str) {
-		if let Some(text) = &self.text_contents {
-			self.text_contents = Some(format!("{}{}", text, new_text));
-		} else {
-			self.text_contents = Some(new_text.to_string());
+		match self.text_contents {
+			Some(ref mut text) => text.push_str(new_text),
+			None => self.text_contents = Some(new_text.to_string())
 		}
-		
 	}
 	/// Gets whether or not an ASTElement has text.
 	pub fn has_text(&self) -> bool {
@@ -196,11 +634,44 @@ impl ASTElement {
 	pub fn is_broken(&self) -> bool {
 		self.broken
 	}
+	/// Sets the Span of the input an ASTElement came from.
+	pub fn set_span(&mut self, span: Span) {
+		self.span = Some(span);
+	}
+	/// Extends an ASTElement's Span to the given end offset.
+	pub fn extend_span(&mut self, end: usize) {
+		if let Some(span) = &mut self.span {
+			if end > span.end {
+				span.end = end;
+			}
+		}
+	}
+	/// Gets the Span of the input an ASTElement came from, if known.
+	pub fn span(&self) -> Option<Span> {
+		self.span
+	}
+}
+
+/// A region of the BBCode input: a range of byte offsets, along with the line and column (in characters) of its start.
+/// Lines and columns start at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize,
+	pub line: usize,
+	pub column: usize,
+}
+impl Span {
+	/// Gets the part of the input the Span covers.
+	pub fn slice<'a>(&self, input: &'a str) -> &'a str {
+		&input[self.start..self.end]
+	}
 }
 
 /// A single Instruction output by the tokenizer.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Instruction {
+	#[default]
 	Null,
 	Tag(String, Option<String>), 
 	Text(String),
@@ -208,9 +679,6 @@ pub enum Instruction {
 	Linebreak,
 	Scenebreak
 }
-impl Default for Instruction {
-    fn default() -> Self {Instruction::Null}
-}
 
 /// Types of ASTElement.
 #[derive(Debug, PartialEq, Clone)]
@@ -260,9 +728,59 @@ pub enum GroupType{
 	Paragraph,
 	Scenebreak,
 	Null,
-	Broken(Box<GroupType>, &'static str),
+	Broken(Box<GroupType>, Cow<'static, str>),
 	Document,
-	Anchor
+	Anchor,
+	Custom(String)
+}
+impl GroupType {
+	/// Gets the name of the BBCode tag that produces this type of element, if there is one.
+	/// Headers are excluded, as their tag depends on their level.
+	pub fn tag_name(&self) -> Option<&str> {
+		Some(match self {
+			GroupType::Bold => "b",
+			GroupType::Strong => "strong",
+			GroupType::Italic => "i",
+			GroupType::Emphasis => "em",
+			GroupType::Underline => "u",
+			GroupType::Smallcaps => "smcaps",
+			GroupType::Strikethrough => "s",
+			GroupType::Monospace => "mono",
+			GroupType::Superscript => "sup",
+			GroupType::Subscript => "sub",
+			GroupType::Spoiler => "spoiler",
+			GroupType::Colour => "color",
+			GroupType::Url => "url",
+			GroupType::Email => "email",
+			GroupType::Opacity => "opacity",
+			GroupType::Size => "size",
+			GroupType::Center => "center",
+			GroupType::Right => "right",
+			GroupType::Image => "img",
+			GroupType::Quote => "quote",
+			GroupType::Footnote => "footnote",
+			GroupType::Indent => "indent",
+			GroupType::Pre => "pre",
+			GroupType::PreLine => "pre-line",
+			GroupType::Figure => "figure",
+			GroupType::List => "list",
+			GroupType::ListItem => "*",
+			GroupType::Embed => "embed",
+			GroupType::Code => "code",
+			GroupType::CodeBlock => "codeblock",
+			GroupType::Math => "math",
+			GroupType::MathBlock => "mathblock",
+			GroupType::Table => "table",
+			GroupType::TableRow => "tr",
+			GroupType::TableData => "td",
+			GroupType::TableHeader => "th",
+			GroupType::TableCaption => "caption",
+			GroupType::Hr => "hr",
+			GroupType::Custom(tag) => tag,
+			GroupType::Broken(_, tag) => tag,
+			_ => return None
+		})
+	}
 }
 
 ///Types of argument for Instructions.
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::rc::Rc;
+use super::Url;
+use crate::url::normalise_domain;
+
+/// Attributes written on a link's `<a>` element, besides its `href`.
+///
+/// ```
+///use bbclash::LinkAttributes;
+///
+///let attributes = LinkAttributes::new().rel("nofollow noopener ugc").target("_blank");
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LinkAttributes {
+	pub(crate) rel: Option<String>,
+	pub(crate) target: Option<String>,
+	pub(crate) referrer_policy: Option<String>,
+	pub(crate) class: Option<String>,
+}
+impl LinkAttributes {
+	/// Creates a new LinkAttributes, with no attributes.
+	pub fn new() -> LinkAttributes {
+		Default::default()
+	}
+	/// Sets the link's `rel` attribute.
+	pub fn rel(mut self, rel: &str) -> LinkAttributes {
+		self.rel = Some(rel.to_string());
+		self
+	}
+	/// Sets the link's `target` attribute.
+	pub fn target(mut self, target: &str) -> LinkAttributes {
+		self.target = Some(target.to_string());
+		self
+	}
+	/// Sets the link's `referrerpolicy` attribute.
+	pub fn referrer_policy(mut self, referrer_policy: &str) -> LinkAttributes {
+		self.referrer_policy = Some(referrer_policy.to_string());
+		self
+	}
+	/// Sets the link's `class` attribute.
+	pub fn class(mut self, class: &str) -> LinkAttributes {
+		self.class = Some(class.to_string());
+		self
+	}
+}
+
+/// A user-defined hook that decides the attributes of each link.
+pub trait LinkHandler {
+	/// Gets the attributes for a link to the given URL. `internal` is whether the URL points at one of
+	/// the LinkPolicy's internal domains, or has no host, as relative and `mailto:` URLs do.
+	fn attributes(&self, url: &Url, internal: bool) -> LinkAttributes;
+}
+
+/// Rules for the attributes of links written by `[url]` and `[email]` tags.
+///
+/// Links are internal if their URL has no host, or its host is one of the internal domains or a
+/// subdomain of one. By default, there are no internal domains, external links get `rel="nofollow"`
+/// and internal links get no attributes. A LinkHandler can be set to decide the attributes instead.
+///
+/// ```
+///use bbclash::{render, ParserOptions, LinkPolicy, LinkAttributes};
+///
+///let policy = LinkPolicy::new()
+///    .internal_domains(&["penclash.com"])
+///    .external(LinkAttributes::new().rel("nofollow noopener ugc").target("_blank"));
+///let options = ParserOptions::new().link_policy(policy);
+///
+///assert_eq!(render("[url=https://www.penclash.com]Home[/url]", &options),
+///        "<p><a href=\"https://www.penclash.com\">Home</a></p>");
+///assert_eq!(render("[url=https://example.com]Away[/url]", &options),
+///        "<p><a href=\"https://example.com\" rel=\"nofollow noopener ugc\" target=\"_blank\">Away</a></p>");
+/// ```
+#[derive(Clone)]
+pub struct LinkPolicy {
+	internal_domains: Vec<String>,
+	internal: LinkAttributes,
+	external: LinkAttributes,
+	handler: Option<Rc<dyn LinkHandler>>,
+}
+impl LinkPolicy {
+	/// Creates a new LinkPolicy.
+	pub fn new() -> LinkPolicy {
+		Default::default()
+	}
+	/// Sets the domains whose links, including those to their subdomains, are internal.
+	pub fn internal_domains(mut self, domains: &[&str]) -> LinkPolicy {
+		self.internal_domains = domains.iter().map(|domain| normalise_domain(domain)).collect();
+		self
+	}
+	/// Sets the attributes of internal links.
+	pub fn internal(mut self, attributes: LinkAttributes) -> LinkPolicy {
+		self.internal = attributes;
+		self
+	}
+	/// Sets the attributes of external links.
+	pub fn external(mut self, attributes: LinkAttributes) -> LinkPolicy {
+		self.external = attributes;
+		self
+	}
+	/// Sets a LinkHandler to decide the attributes of every link, in place of the internal and external attributes.
+	pub fn handler<H: LinkHandler + 'static>(mut self, handler: H) -> LinkPolicy {
+		self.handler = Some(Rc::new(handler));
+		self
+	}
+	/// Gets whether a link to the given URL is internal.
+	pub fn is_internal(&self, url: &Url) -> bool {
+		url.host().is_none() || self.internal_domains.iter().any(|domain| url.host_matches(domain))
+	}
+	/// Gets the attributes for a link to the given URL.
+	pub fn attributes(&self, url: &Url) -> LinkAttributes {
+		let internal = self.is_internal(url);
+		match self.handler {
+			Some(ref handler) => handler.attributes(url, internal),
+			None if internal => self.internal.clone(),
+			None => self.external.clone()
+		}
+	}
+}
+impl Default for LinkPolicy {
+	fn default() -> LinkPolicy {
+		LinkPolicy {
+			internal_domains: Vec::new(),
+			internal: LinkAttributes::new(),
+			external: LinkAttributes::new().rel("nofollow"),
+			handler: None,
+		}
+	}
+}
+impl fmt::Debug for LinkPolicy {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("LinkPolicy")
+			.field("internal_domains", &self.internal_domains)
+			.field("internal", &self.internal)
+			.field("external", &self.external)
+			.field("handler", &self.handler.is_some())
+			.finish()
+	}
+}
@@ -0,0 +1,484 @@
+use rctree::Node;
+use std::rc::Rc;
+use super::GroupType;
+use super::ASTElement;
+use super::{TagRegistry, ParserOptions, HTMLConstructor, UrlPolicy, LinkPolicy, MediaPolicy, StylePolicy, Url};
+use super::{escape_text, escape_attribute};
+use crate::escape::attribute_value;
+use crate::ast::has_content;
+use crate::block_constructor::{BlockConstructor, BlockState, node_text};
+
+/// Struct for generation of CommonMark strings.
+///
+/// Constructs that Markdown lacks are written as inline HTML, or, for blocks such as tables and
+/// centred text, as HTML blocks rendered by the HTMLConstructor. Bold and italic text whose `**` or
+/// `*` CommonMark wouldn't read as emphasis is written as `<strong>` or `<em>`.
+pub struct MarkdownConstructor {
+	output_string: String,
+	pretty_print: bool,
+	registry: Option<Rc<TagRegistry>>,
+	url_policy: UrlPolicy,
+	link_policy: LinkPolicy,
+	media_policy: MediaPolicy,
+	style_policy: StylePolicy,
+	prefixes: Vec<String>,
+	list_numbers: Vec<Option<usize>>,
+	line_start: bool,
+	in_header: bool,
+	/// The positions of the open emphasis delimiters, and whether each was written at the start of a line.
+	open_emphasis: Vec<(usize, bool)>,
+	/// The delimiter and the positions of the opening and closing delimiters of each closed emphasis.
+	emphasis: Vec<(&'static str, usize, usize)>,
+	blocks: BlockState,
+}
+impl MarkdownConstructor {
+	/// Creates a new MarkdownConstructor.
+	pub fn new(out_len: usize, pretty_print: bool) -> MarkdownConstructor {
+		let output_string = String::with_capacity(out_len + out_len/4);
+		MarkdownConstructor {
+			output_string,
+			pretty_print,
+			registry: None,
+			url_policy: UrlPolicy::default(),
+			link_policy: LinkPolicy::default(),
+			media_policy: MediaPolicy::default(),
+			style_policy: StylePolicy::default(),
+			prefixes: Vec::new(),
+			list_numbers: Vec::new(),
+			line_start: true,
+			in_header: false,
+			open_emphasis: Vec::new(),
+			emphasis: Vec::new(),
+			blocks: BlockState::new(),
+		}
+	}
+	/// Creates a new MarkdownConstructor that renders custom elements with the handlers in the given TagRegistry.
+	pub fn with_registry(out_len: usize, pretty_print: bool, registry: Rc<TagRegistry>) -> MarkdownConstructor {
+		let mut constructor = MarkdownConstructor::new(out_len, pretty_print);
+		constructor.registry = Some(registry);
+		constructor
+	}
+	/// Creates a new MarkdownConstructor configured by the given ParserOptions.
+	pub fn with_options(out_len: usize, options: &ParserOptions) -> MarkdownConstructor {
+		let mut constructor = MarkdownConstructor::with_registry(out_len, options.pretty_print, options.registry.clone());
+		constructor.url_policy = options.url_policy.clone();
+		constructor.link_policy = options.link_policy.clone();
+		constructor.media_policy = options.media_policy.clone();
+		constructor.style_policy = options.style_policy.clone();
+		constructor
+	}
+
+	/// Generates a Markdown string from an ASTElement
+	pub fn construct(&mut self, ast: Node<ASTElement>) -> String {
+		self.traverse(ast);
+		self.resolve_emphasis();
+		self.output_string.clone()
+	}
+
+	/// Opens a Markdown element.
+	fn start_element(&mut self, node: &Node<ASTElement>) {
+		if self.pretty_print && !has_content(node) {
+			self.blocks.skip = Some(node.clone());
+			return;
+		}
+		let element = node.borrow();
+		if self.blocks.block_ended && !self.is_block(element.ele_type()) {
+			self.open_block();
+		}
+		match element.ele_type() {
+			GroupType::Text => {
+				if let Some(text) = element.text_contents() {
+					self.push_text(text);
+				}
+			},
+			GroupType::Paragraph => {self.open_block()},
+			GroupType::Header => {
+				if let Some(arg) = element.argument() {
+					self.open_block();
+					let level = arg.parse::<usize>().unwrap_or(1);
+					self.push(&format!("{} ", "#".repeat(level)));
+					self.in_header = true;
+				}
+			},
+			GroupType::Hr => {
+				self.open_block();
+				self.push("---");
+			},
+			GroupType::Scenebreak => {
+				self.open_block();
+				self.push("* * *");
+			},
+			GroupType::Br => {
+				if self.in_header {
+					self.push(" ");
+				} else {
+					self.push("\\");
+					self.newline();
+				}
+			},
+			GroupType::Quote => {
+				self.open_block();
+				self.output_string.push_str("> ");
+				self.prefixes.push("> ".to_string());
+				if let Some(arg) = element.argument() {
+					self.push(&format!("<cite>{}</cite>", escape_text(arg)));
+				}
+			},
+			GroupType::List => {
+				match element.argument().as_deref() {
+					None => self.list_numbers.push(None),
+					Some("1") => self.list_numbers.push(Some(1)),
+					Some(_) => {
+						self.html_block(node);
+						return;
+					}
+				}
+				self.open_block();
+			},
+			GroupType::ListItem => {
+				if !self.blocks.block_start {
+					self.newline();
+				}
+				let marker = match self.list_numbers.last_mut() {
+					Some(Some(number)) => {
+						*number += 1;
+						format!("{}. ", *number - 1)
+					},
+					_ => "- ".to_string()
+				};
+				self.output_string.push_str(&marker);
+				self.prefixes.push(" ".repeat(marker.len()));
+				self.blocks.block_start = true;
+			},
+			GroupType::CodeBlock => {
+				self.open_block();
+				let text = node_text(node);
+				let code = text.trim_matches('\n');
+				let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+				match element.argument() {
+					Some(arg) => self.push(&format!("{}{}", fence, arg)),
+					None => self.push(&fence)
+				}
+				self.newline();
+				let prefix = self.prefixes.concat();
+				self.push(&code.replace('\n', &format!("\n{}", prefix)));
+				self.newline();
+				self.push(&fence);
+				self.blocks.block_ended = true;
+				self.blocks.skip = Some(node.clone());
+			},
+			GroupType::Code => {
+				let code = node_text(node);
+				let fence = "`".repeat(longest_run(&code, '`') + 1);
+				if code.starts_with('`') || code.ends_with('`') {
+					self.push(&format!("{} {} {}", fence, code, fence));
+				} else {
+					self.push(&format!("{}{}{}", fence, code, fence));
+				}
+				self.blocks.skip = Some(node.clone());
+			},
+			GroupType::Pre |
+			GroupType::MathBlock |
+			GroupType::Center |
+			GroupType::Right |
+			GroupType::Indent |
+			GroupType::Figure |
+			GroupType::Table |
+			GroupType::Embed
+				=> {self.html_block(node)},
+			GroupType::Bold | GroupType::Strong => {self.open_emphasis("**")},
+			GroupType::Italic | GroupType::Emphasis => {self.open_emphasis("*")},
+			GroupType::Url | GroupType::Email if attribute_value(&element, &self.url_policy).is_some() => {self.push("[")},
+			GroupType::Image => {
+				let src = element.argument().as_deref()
+					.filter(|_| attribute_value(&element, &self.url_policy).is_some())
+					.and_then(|arg| Url::parse(arg).ok())
+					.and_then(|url| self.media_policy.source(&url));
+				if let Some(src) = src {
+					self.push(&format!("![]({})", link_destination(&src)));
+				}
+			},
+			GroupType::Underline => {self.push("<span class=\"underline\">")},
+			GroupType::Smallcaps => {self.push("<span class=\"smallcaps\">")},
+			GroupType::Monospace => {self.push("<span class=\"monospace\">")},
+			GroupType::Spoiler => {self.push("<span class=\"spoiler\">")},
+			GroupType::Strikethrough => {self.push("<s>")},
+			GroupType::Subscript => {self.push("<sub>")},
+			GroupType::Superscript => {self.push("<sup>")},
+			GroupType::Math => {self.push("<span class=\"math_container\">")},
+			GroupType::Colour => {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					self.push_span(self.style_policy.colour(&arg));
+				}
+			},
+			GroupType::Opacity => {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					self.push_span(self.style_policy.opacity(&arg));
+				}
+			},
+			GroupType::Size => {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					self.push_span(self.style_policy.size(&arg));
+				}
+			},
+			GroupType::Footnote => {
+				if let Some(arg) = attribute_value(&element, &self.url_policy) {
+					self.push(&format!("<span class=\"footnote\" data-symbol=\"{}\">", arg));
+				} else {
+					self.push("<span class=\"footnote\">");
+				}
+			},
+			GroupType::Custom(tag) => {
+				if let Some(handler) = self.registry.as_ref().and_then(|registry| registry.handler(tag)) {
+					if handler.is_block() {
+						self.html_block(node);
+					} else {
+						self.push(&handler.render_open(element.argument().as_deref().map(escape_attribute).as_deref()));
+					}
+				}
+			},
+			GroupType::Broken(_, tag) if !self.pretty_print => {
+				match element.argument() {
+					Some(arg) => self.push_text(&format!("[{}={}]", tag, arg)),
+					None => self.push_text(&format!("[{}]", tag))
+				}
+				if let Some(text) = element.text_contents() {
+					self.push_text(text);
+				}
+			},
+			_ => {}
+		};
+	}
+
+	/// Closes a Markdown element.
+	fn end_element(&mut self, node: &Node<ASTElement>) {
+		let element = node.borrow();
+		if self.is_block(element.ele_type()) {
+			self.blocks.block_ended = true;
+		}
+		match element.ele_type() {
+			GroupType::Header => {self.in_header = false},
+			GroupType::Quote => {
+				self.prefixes.pop();
+				self.blocks.block_start = false;
+			},
+			GroupType::List => {self.list_numbers.pop();},
+			GroupType::ListItem => {
+				self.prefixes.pop();
+				self.blocks.block_start = false;
+			},
+			GroupType::Bold | GroupType::Strong => {self.close_emphasis("**")},
+			GroupType::Italic | GroupType::Emphasis => {self.close_emphasis("*")},
+			GroupType::Url | GroupType::Email => {
+				if let Some(arg) = element.argument().as_ref().filter(|_| attribute_value(&element, &self.url_policy).is_some()) {
+					self.push(&format!("]({})", link_destination(arg)));
+				}
+			},
+			GroupType::Strikethrough => {self.push("</s>")},
+			GroupType::Subscript => {self.push("</sub>")},
+			GroupType::Superscript => {self.push("</sup>")},
+			GroupType::Colour |
+			GroupType::Opacity |
+			GroupType::Size
+				if attribute_value(&element, &self.url_policy).is_none() => {},
+			GroupType::Underline |
+			GroupType::Smallcaps |
+			GroupType::Monospace |
+			GroupType::Spoiler |
+			GroupType::Colour |
+			GroupType::Opacity |
+			GroupType::Size |
+			GroupType::Footnote |
+			GroupType::Math
+				=> {self.push("</span>")},
+			GroupType::Custom(tag) => {
+				if let Some(handler) = self.registry.as_ref().and_then(|registry| registry.handler(tag)) {
+					self.push(&handler.render_close(element.argument().as_deref().map(escape_attribute).as_deref()));
+				}
+			},
+			GroupType::Broken(_, tag) if !self.pretty_print && !element.is_void() => {
+				self.push_text(&format!("[/{}]", tag));
+			},
+			_ => {}
+		};
+	}
+
+	/// Renders an element Markdown has no equivalent for as an HTML block.
+	fn html_block(&mut self, node: &Node<ASTElement>) {
+		self.open_block();
+		let constructor = match self.registry {
+			Some(ref registry) => HTMLConstructor::with_registry(0, self.pretty_print, registry.clone()),
+			None => HTMLConstructor::new(0, self.pretty_print)
+		};
+		let html = constructor.url_policy(self.url_policy.clone())
+			.link_policy(self.link_policy.clone())
+			.media_policy(self.media_policy.clone())
+			.style_policy(self.style_policy.clone())
+			.construct(node.clone());
+		let prefix = self.prefixes.concat();
+		self.push(&html.replace('\n', &format!("\n{}", prefix)));
+		self.blocks.block_ended = true;
+		self.blocks.skip = Some(node.clone());
+	}
+
+	/// Starts a new line within the current block.
+	fn newline(&mut self) {
+		self.output_string.push('\n');
+		self.output_string.push_str(&self.prefixes.concat());
+		self.line_start = true;
+	}
+
+	/// Writes Markdown as-is.
+	fn push(&mut self, markdown: &str) {
+		self.output_string.push_str(markdown);
+		self.blocks.block_start = false;
+		self.line_start = false;
+	}
+
+	/// Writes an emphasis delimiter, `*` or `**`, keeping track of where it is.
+	fn open_emphasis(&mut self, delimiter: &'static str) {
+		self.open_emphasis.push((self.output_string.len(), self.line_start));
+		self.push(delimiter);
+	}
+
+	/// Writes the delimiter that closes the most recent emphasis, moving whitespace at either end of
+	/// its contents outside of it, as CommonMark doesn't read delimiters next to whitespace as emphasis.
+	fn close_emphasis(&mut self, delimiter: &'static str) {
+		let (mut open, line_start) = match self.open_emphasis.pop() {
+			Some(open) => open,
+			None => return
+		};
+		let contents = &self.output_string[open + delimiter.len()..];
+		if contents.trim().is_empty() {
+			self.push(delimiter);
+			self.emphasis.push((delimiter, open, self.output_string.len() - delimiter.len()));
+			return;
+		}
+		let leading = contents.len() - contents.trim_start().len();
+		let trailing = contents.len() - contents.trim_end().len();
+		if leading > 0 {
+			self.output_string.replace_range(open..open + delimiter.len(), "");
+			if line_start {
+				self.output_string.replace_range(open..open + leading, "");
+				for (_, inner_open, inner_close) in self.emphasis.iter_mut().filter(|(_, inner_open, _)| *inner_open > open) {
+					*inner_open -= leading;
+					*inner_close -= leading;
+				}
+			} else {
+				open += leading;
+			}
+			self.output_string.insert_str(open, delimiter);
+		}
+		let close = self.output_string.len() - trailing;
+		self.output_string.insert_str(close, delimiter);
+		self.emphasis.push((delimiter, open, close));
+		self.blocks.block_start = false;
+	}
+
+	/// Replaces the delimiters of each emphasis that CommonMark wouldn't read as one, because a
+	/// delimiter isn't flanking or runs into another, with inline HTML.
+	fn resolve_emphasis(&mut self) {
+		let mut replacements = Vec::new();
+		for (delimiter, open, close) in std::mem::take(&mut self.emphasis) {
+			let before = |position: usize| self.output_string[..position].chars().next_back();
+			let after = |position: usize| self.output_string[position + delimiter.len()..].chars().next();
+			let opens = left_flanking(before(open), after(open)) && before(open) != Some('*') && after(open) != Some('*');
+			let closes = left_flanking(after(close), before(close)) && before(close) != Some('*') && after(close) != Some('*')
+				&& before(close) != Some('\\');
+			if !opens || !closes {
+				let tag = if delimiter == "**" {"strong"} else {"em"};
+				replacements.push((open, delimiter.len(), format!("<{}>", tag)));
+				replacements.push((close, delimiter.len(), format!("</{}>", tag)));
+			}
+		}
+		replacements.sort_unstable_by_key(|(position, _, _)| std::cmp::Reverse(*position));
+		for (position, length, html) in replacements {
+			self.output_string.replace_range(position..position + length, &html);
+		}
+	}
+
+	/// Opens an inline HTML span with the given style attribute, or without one if the StylePolicy gave none.
+	fn push_span(&mut self, attribute: Option<String>) {
+		match attribute {
+			Some(attribute) => self.push(&format!("<span {}>", attribute)),
+			None => self.push("<span>")
+		}
+	}
+
+	/// Writes text, escaping anything that would otherwise be read as Markdown.
+	fn push_text(&mut self, text: &str) {
+		let mut text = text;
+		let mut escaped = String::with_capacity(text.len());
+		if self.line_start {
+			text = text.trim_start();
+			if text.is_empty() {
+				return;
+			}
+			if text.starts_with(['-', '+', '=']) {
+				escaped.push('\\');
+			}
+			let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+			if digits > 0 && text[digits..].starts_with(['.', ')']) {
+				escaped.push_str(&text[..digits]);
+				escaped.push('\\');
+				text = &text[digits..];
+			}
+		}
+		for c in text.chars() {
+			if MARKDOWN_CHARS.contains(&c) {
+				escaped.push('\\');
+			}
+			escaped.push(c);
+		}
+		self.push(&escaped);
+	}
+}
+impl BlockConstructor for MarkdownConstructor {
+	fn blocks(&mut self) -> &mut BlockState {
+		&mut self.blocks
+	}
+	fn registry(&self) -> Option<&Rc<TagRegistry>> {
+		self.registry.as_ref()
+	}
+	fn start(&mut self, node: &Node<ASTElement>) {
+		self.start_element(node);
+	}
+	fn end(&mut self, node: &Node<ASTElement>) {
+		self.end_element(node);
+	}
+	fn separate_blocks(&mut self) {
+		let prefix = self.prefixes.concat();
+		self.output_string.push('\n');
+		self.output_string.push_str(prefix.trim_end());
+		self.output_string.push('\n');
+		self.output_string.push_str(&prefix);
+	}
+	fn block_opened(&mut self) {
+		self.line_start = true;
+	}
+}
+
+/// Characters that are always escaped in Markdown text.
+static MARKDOWN_CHARS: [char; 10] = ['\\', '`', '*', '_', '[', ']', '<', '>', '#', '&'];
+
+/// Gets whether a delimiter run between two characters, or the start or end of the text, is
+/// left-flanking. Swapping the characters gives whether it is right-flanking.
+fn left_flanking(before: Option<char>, after: Option<char>) -> bool {
+	let whitespace = |c: Option<char>| c.is_none_or(char::is_whitespace);
+	let punctuation = |c: Option<char>| c.is_some_and(|c| !c.is_alphanumeric() && !c.is_whitespace());
+	!whitespace(after) && (!punctuation(after) || whitespace(before) || punctuation(before))
+}
+
+/// Gets the length of the longest run of a character in some text.
+fn longest_run(text: &str, character: char) -> usize {
+	text.split(|c| c != character).map(|run| run.len()).max().unwrap_or(0)
+}
+
+/// Prepares a URL for use as a link destination.
+fn link_destination(url: &str) -> String {
+	url.replace(' ', "%20")
+		.replace('(', "%28")
+		.replace(')', "%29")
+		.replace('<', "%3C")
+		.replace('>', "%3E")
+}
@@ -0,0 +1,274 @@
+use std::collections::{HashMap, HashSet};
+use pulldown_cmark::{Event, Tag, TagEnd, CodeBlockKind, LinkType, Options, Parser};
+use super::{render, html_to_bbcode, ParserOptions, OutputFlavour, UrlPolicy, Url};
+use crate::html_to_bbcode::argument;
+use crate::bbcode_lexer::is_accepted_image;
+
+/// Converts CommonMark to BBCode.
+///
+/// Besides CommonMark, tables, footnotes and `~~strikethrough~~` are read as GitHub Flavored
+/// Markdown writes them. Fenced and indented code become `[codeblock]`, with the fence's language
+/// as its argument, block quotes become `[quote]`, ordered lists become `[list=1]`, tables become
+/// `[table]`, and footnotes are written inline at their first reference, as `[footnote]`. Links and
+/// images are checked against the default UrlPolicy, and only their text is kept if it rejects them.
+/// HTML blocks are converted with `html_to_bbcode`, and inline HTML tags are dropped. The result is normalised, so it
+/// is the same canonical BBCode that `OutputFlavour::BBCode` writes.
+///
+/// ```
+///use bbclash::markdown_to_bbcode;
+///
+///assert_eq!(markdown_to_bbcode("Some **bold** and *italic* text.\n\n> Quoted"),
+///        "Some [b]bold[/b] and [i]italic[/i] text.[quote]Quoted[/quote]");
+///assert_eq!(markdown_to_bbcode("1. One\n2. Two"), "[list=1][*]One[*]Two[/list]");
+/// ```
+pub fn markdown_to_bbcode(markdown: &str) -> String {
+	let options = Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH;
+	let mut importer = Importer::default();
+	let mut events = Vec::new();
+	let mut definition: Option<(String, Vec<Event>)> = None;
+	for event in Parser::new_ext(markdown, options) {
+		match (event, &mut definition) {
+			(Event::Start(Tag::FootnoteDefinition(label)), _) => definition = Some((label.to_string(), Vec::new())),
+			(Event::End(TagEnd::FootnoteDefinition), _) => {
+				if let Some((label, events)) = definition.take() {
+					importer.footnotes.entry(label).or_insert(events);
+				}
+			},
+			(event, Some((_, events))) => events.push(event),
+			(event, None) => events.push(event),
+		}
+	}
+	importer.events(&events);
+	render(&importer.output, &ParserOptions::new().flavour(OutputFlavour::BBCode).pretty_print(false))
+}
+
+/// Writes BBCode for a stream of Markdown events.
+#[derive(Default)]
+struct Importer<'a> {
+	output: String,
+	/// The events of each footnote's definition, by label.
+	footnotes: HashMap<String, Vec<Event<'a>>>,
+	/// The footnotes already written, so that each definition is only written once, however often it is referred to.
+	written_footnotes: HashSet<String>,
+	/// How deeply nested the footnotes being written are.
+	footnote_depth: usize,
+	/// The closing tags of the open links.
+	links: Vec<&'static str>,
+	/// The text of the open code block.
+	code: Option<String>,
+	/// The language of the open code block, from its fence's info string.
+	code_language: Option<String>,
+	/// The HTML of the open HTML block.
+	html: Option<String>,
+	/// How deeply nested the events being skipped are, such as the alt text of an image.
+	skip_depth: usize,
+	in_table_head: bool,
+	/// Whether inline content has been written since the last block started or ended.
+	inline_written: bool,
+}
+impl<'a> Importer<'a> {
+	fn events(&mut self, events: &[Event<'a>]) {
+		for event in events {
+			self.event(event);
+		}
+	}
+
+	fn event(&mut self, event: &Event<'a>) {
+		if self.skip_depth > 0 {
+			match event {
+				Event::Start(_) => self.skip_depth += 1,
+				Event::End(_) => self.skip_depth -= 1,
+				_ => {}
+			}
+			return;
+		}
+		match event {
+			Event::Start(tag) => self.start_tag(tag),
+			Event::End(tag) => self.end_tag(*tag),
+			Event::Text(text) => match (&mut self.code, &mut self.html) {
+				(Some(code), _) => code.push_str(text),
+				(_, Some(html)) => html.push_str(text),
+				_ => {
+					let escaped = escape(text, false);
+					self.push_inline(&escaped);
+				}
+			},
+			Event::Html(html) => {
+				if let Some(block) = &mut self.html {
+					block.push_str(html);
+				}
+			},
+			Event::Code(code) => self.push_inline(&format!("[code]{}[/code]", escape(code, true))),
+			Event::InlineMath(math) => self.push_inline(&format!("[math]{}[/math]", escape(math, true))),
+			Event::DisplayMath(math) => self.push_inline(&format!("[mathblock]{}[/mathblock]", escape(math, true))),
+			Event::FootnoteReference(label) => self.footnote(label),
+			Event::SoftBreak => self.push_inline(" "),
+			Event::HardBreak => self.push_inline("\n"),
+			Event::Rule => self.push_block("[hr]"),
+			Event::InlineHtml(_) | Event::TaskListMarker(_) => {}
+		}
+	}
+
+	fn start_tag(&mut self, tag: &Tag<'a>) {
+		match tag {
+			Tag::Paragraph | Tag::DefinitionListTitle | Tag::DefinitionListDefinition => {
+				if self.inline_written {
+					self.output.push_str(if self.footnote_depth == 0 {"\n\n"} else {" "});
+				}
+				self.inline_written = false;
+			},
+			Tag::Heading {level, ..} => self.push_block(&format!("[{}]", level)),
+			Tag::BlockQuote(_) => self.push_block("[quote]"),
+			Tag::CodeBlock(kind) => {
+				self.code_language = match kind {
+					CodeBlockKind::Fenced(info) => info.split_whitespace().next().and_then(argument).map(str::to_string),
+					CodeBlockKind::Indented => None
+				};
+				self.code = Some(String::new());
+			},
+			Tag::HtmlBlock => self.html = Some(String::new()),
+			Tag::List(Some(_)) => self.push_block("[list=1]"),
+			Tag::List(None) => self.push_block("[list]"),
+			Tag::Item => self.push_block("[*]"),
+			Tag::Table(_) => self.push_block("[table]"),
+			Tag::TableHead => {
+				self.in_table_head = true;
+				self.push_block("[tr]");
+			},
+			Tag::TableRow => self.push_block("[tr]"),
+			Tag::TableCell => self.push_block(if self.in_table_head {"[th]"} else {"[td]"}),
+			Tag::Emphasis => self.push_inline("[i]"),
+			Tag::Strong => self.push_inline("[b]"),
+			Tag::Strikethrough => self.push_inline("[s]"),
+			Tag::Superscript => self.push_inline("[sup]"),
+			Tag::Subscript => self.push_inline("[sub]"),
+			Tag::Link {link_type, dest_url, ..} => {
+				let email = dest_url.strip_prefix("mailto:")
+					.or(if *link_type == LinkType::Email {Some(dest_url)} else {None})
+					.and_then(argument);
+				match email {
+					Some(address) => {
+						self.push_inline(&format!("[email]{}[/email]", address));
+						self.skip_depth = 1;
+					},
+					None => match url(dest_url) {
+						Some(url) if *link_type == LinkType::Autolink => {
+							self.push_inline(&format!("[url]{}[/url]", url));
+							self.skip_depth = 1;
+						},
+						Some(url) => {
+							self.push_inline(&format!("[url={}]", url));
+							self.links.push("[/url]");
+						},
+						None => self.links.push("")
+					}
+				}
+			},
+			Tag::Image {dest_url, ..} => {
+				if let Some(src) = url(dest_url).filter(is_accepted_image) {
+					self.push_inline(&format!("[img]{}[/img]", src));
+				}
+				self.skip_depth = 1;
+			},
+			Tag::DefinitionList | Tag::FootnoteDefinition(_) => {},
+			Tag::MetadataBlock(_) => self.skip_depth = 1,
+		}
+	}
+
+	fn end_tag(&mut self, tag: TagEnd) {
+		match tag {
+			TagEnd::Heading(level) => self.push_block(&format!("[/{}]", level)),
+			TagEnd::BlockQuote(_) => self.push_block("[/quote]"),
+			TagEnd::CodeBlock => {
+				if let Some(code) = self.code.take() {
+					let language = match self.code_language.take() {
+						Some(language) => format!("={}", language),
+						None => String::new()
+					};
+					let code = code.strip_suffix('\n').unwrap_or(&code);
+					self.push_block(&format!("[codeblock{}]{}[/codeblock]", language, escape(code, true)));
+				}
+			},
+			TagEnd::HtmlBlock => {
+				if let Some(html) = self.html.take() {
+					let bbcode = html_to_bbcode(&html);
+					if !bbcode.is_empty() {
+						if self.inline_written {
+							self.output.push_str("\n\n");
+						}
+						self.output.push_str(&bbcode);
+						self.inline_written = true;
+					}
+				}
+			},
+			TagEnd::List(true) | TagEnd::List(false) => self.push_block("[/list]"),
+			TagEnd::Table => self.push_block("[/table]"),
+			TagEnd::TableHead => {
+				self.in_table_head = false;
+				self.push_block("[/tr]");
+			},
+			TagEnd::TableRow => self.push_block("[/tr]"),
+			TagEnd::TableCell => self.push_block(if self.in_table_head {"[/th]"} else {"[/td]"}),
+			TagEnd::Emphasis => self.output.push_str("[/i]"),
+			TagEnd::Strong => self.output.push_str("[/b]"),
+			TagEnd::Strikethrough => self.output.push_str("[/s]"),
+			TagEnd::Superscript => self.output.push_str("[/sup]"),
+			TagEnd::Subscript => self.output.push_str("[/sub]"),
+			TagEnd::Link => {
+				if let Some(close) = self.links.pop() {
+					self.output.push_str(close);
+				}
+			},
+			_ => {}
+		}
+	}
+
+	/// Writes a footnote's definition inline, where it is first referred to. Later references are written as text.
+	fn footnote(&mut self, label: &str) {
+		let events = match self.footnotes.get(label) {
+			Some(events) if !self.written_footnotes.contains(label) => events.clone(),
+			_ => return self.push_inline(&escape(&format!("[^{}]", label), false))
+		};
+		self.push_inline("[footnote]");
+		self.written_footnotes.insert(label.to_string());
+		let inline_written = self.inline_written;
+		self.inline_written = false;
+		self.footnote_depth += 1;
+		self.events(&events);
+		self.footnote_depth -= 1;
+		self.inline_written = inline_written;
+		self.output.push_str("[/footnote]");
+	}
+
+	/// Writes inline content.
+	fn push_inline(&mut self, bbcode: &str) {
+		self.output.push_str(bbcode);
+		self.inline_written = true;
+	}
+
+	/// Writes a block tag, which needs no paragraph break around it.
+	fn push_block(&mut self, bbcode: &str) {
+		self.output.push_str(bbcode);
+		self.inline_written = false;
+	}
+}
+
+/// Gets a link's URL if the default UrlPolicy accepts it and it can be written as a tag's argument.
+fn url(dest_url: &str) -> Option<Url> {
+	argument(dest_url).and_then(|url| UrlPolicy::new().check(url).ok())
+}
+
+/// Escapes text that would otherwise be read as a tag, as BBCodeConstructor does. In verbatim tags,
+/// such as `[code]`, only the start of a closing tag needs escaping.
+fn escape(text: &str, verbatim: bool) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	let mut chars = text.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => escaped.push_str("\\\\"),
+			'[' if !verbatim || chars.peek() == Some(&'/') => escaped.push_str("\\["),
+			_ => escaped.push(c)
+		}
+	}
+	escaped
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+use super::Url;
+use crate::url::normalise_domain;
+
+/// Restricts the hosts that images and embedded content can be loaded from.
+///
+/// Hosts match themselves and their subdomains. URLs without a host, such as relative URLs, load from
+/// the page's own site and are always permitted.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum HostFilter {
+	/// Every host is permitted.
+	#[default]
+	All,
+	/// Only the listed hosts are permitted.
+	Allow(HashSet<String>),
+	/// Every host except the listed ones is permitted.
+	Deny(HashSet<String>),
+}
+impl HostFilter {
+	/// Creates a HostFilter that permits every host.
+	pub fn all() -> HostFilter {
+		HostFilter::All
+	}
+	/// Creates a HostFilter that permits only the given hosts.
+	pub fn allow(hosts: &[&str]) -> HostFilter {
+		HostFilter::Allow(hosts.iter().map(|host| normalise_domain(host)).collect())
+	}
+	/// Creates a HostFilter that permits every host except the given ones.
+	pub fn deny(hosts: &[&str]) -> HostFilter {
+		HostFilter::Deny(hosts.iter().map(|host| normalise_domain(host)).collect())
+	}
+	/// Gets whether content can be loaded from a URL's host.
+	pub fn permits(&self, url: &Url) -> bool {
+		if url.host().is_none() {
+			return true;
+		}
+		let listed = |hosts: &HashSet<String>| hosts.iter().any(|listed| url.host_matches(listed));
+		match self {
+			HostFilter::All => true,
+			HostFilter::Allow(hosts) => listed(hosts),
+			HostFilter::Deny(hosts) => !listed(hosts),
+		}
+	}
+}
+
+/// A user-defined hook that rewrites the URLs of images and embedded content when they are rendered,
+/// such as to route them through an image proxy.
+pub trait UrlRewriter {
+	/// Gets the URL to load content from in place of the given one.
+	fn rewrite(&self, url: &Url) -> String;
+}
+
+/// Rules for the images and embedded content of `[img]` and `[embed]` tags.
+///
+/// Tags whose URL's host is not permitted by the HostFilter are treated as broken. The UrlRewriter,
+/// if there is one, is applied when rendering, so the Ast and BBCode output keep the original URLs.
+///
+/// ```
+///use bbclash::{render, ParserOptions, MediaPolicy, HostFilter, UrlRewriter, Url};
+///
+///struct Proxy;
+///impl UrlRewriter for Proxy {
+///    fn rewrite(&self, url: &Url) -> String {
+///        format!("https://camo.penclash.com/?url={}", url.as_str().replace(':', "%3A").replace('/', "%2F"))
+///    }
+///}
+///
+///let policy = MediaPolicy::new().hosts(HostFilter::allow(&["imgur.com"])).rewriter(Proxy);
+///let options = ParserOptions::new().media_policy(policy);
+///
+///assert_eq!(render("[img]https://i.imgur.com/cat.png[/img]", &options),
+///        "<p><img src=\"https://camo.penclash.com/?url=https%3A%2F%2Fi.imgur.com%2Fcat.png\"></p>");
+///assert_eq!(render("[img]https://tracker.example/pixel.gif[/img]", &options), "");
+/// ```
+#[derive(Clone, Default)]
+pub struct MediaPolicy {
+	hosts: HostFilter,
+	rewriter: Option<Rc<dyn UrlRewriter>>,
+}
+impl MediaPolicy {
+	/// Creates a new MediaPolicy, which permits every host and doesn't rewrite URLs.
+	pub fn new() -> MediaPolicy {
+		Default::default()
+	}
+	/// Sets the hosts that content can be loaded from.
+	pub fn hosts(mut self, hosts: HostFilter) -> MediaPolicy {
+		self.hosts = hosts;
+		self
+	}
+	/// Sets a UrlRewriter to rewrite the URLs of content when it is rendered.
+	pub fn rewriter<R: UrlRewriter + 'static>(mut self, rewriter: R) -> MediaPolicy {
+		self.rewriter = Some(Rc::new(rewriter));
+		self
+	}
+	/// Gets whether content can be loaded from a URL's host.
+	pub fn permits(&self, url: &Url) -> bool {
+		self.hosts.permits(url)
+	}
+	/// Gets the URL to render for content at the given URL, or None if its host is not permitted.
+	pub fn source(&self, url: &Url) -> Option<String> {
+		if !self.permits(url) {
+			return None;
+		}
+		match self.rewriter {
+			Some(ref rewriter) => Some(rewriter.rewrite(url)),
+			None => Some(url.to_string())
+		}
+	}
+}
+impl fmt::Debug for MediaPolicy {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("MediaPolicy")
+			.field("hosts", &self.hosts)
+			.field("rewriter", &self.rewriter.is_some())
+			.finish()
+	}
+}
@@ -0,0 +1,265 @@
+use std::rc::Rc;
+use super::{TagRegistry, TagFilter, Url, UrlError, LinkPolicy, MediaPolicy, StylePolicy};
+use crate::tag_registry::default_registry;
+
+/// Output formats that `render` can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[non_exhaustive]
+pub enum OutputFlavour {
+	#[default]
+	Html,
+	/// Canonical BBCode, with unclosed tags closed and misnested tags reordered.
+	BBCode,
+	/// CommonMark, with inline HTML for constructs Markdown lacks.
+	Markdown,
+	/// Plain text, with all formatting removed.
+	PlainText,
+	/// The body of a LaTeX document, for print.
+	Latex,
+	/// Well-formed XHTML for EPUB, with self-closing void elements, semantic scene breaks and footnote asides.
+	Xhtml,
+	/// Word-wrapped text for terminals, styled with ANSI escape codes.
+	Ansi,
+}
+
+/// Ways of handling single newlines outside of preformatted elements.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LinebreakMode {
+	/// Single newlines become line breaks (`<br>`).
+	#[default]
+	Br,
+	/// Single newlines become spaces.
+	Space,
+	/// Single newlines are removed.
+	Ignore,
+}
+
+/// Ways of writing colours and styles in terminal output.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColourMode {
+	/// No escape codes are written, for terminals and logs without colour.
+	Plain,
+	/// Colours are written as the nearest of the 256 standard terminal colours.
+	#[default]
+	Palette256,
+	/// Colours are written exactly, as 24-bit RGB.
+	TrueColour,
+}
+
+/// Rules for accepting URLs in `[url]`, `[img]` and `[embed]` tags.
+///
+/// By default, `http` and `https` URLs are accepted, URLs without a scheme are given `http://`, and
+/// relative URLs are rejected.
+///
+/// ```
+///use bbclash::{UrlPolicy, UrlError};
+///
+///let policy = UrlPolicy::new().allow_scheme("mailto").allow_relative(true);
+///assert!(policy.check("mailto:enda@penclash.com").is_ok());
+///assert!(policy.check("/stories#top").is_ok());
+///assert_eq!(policy.check("javascript:alert(1)"), Err(UrlError::DisallowedScheme("javascript".into())));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlPolicy {
+	require_scheme: bool,
+	allow_relative: bool,
+	schemes: Vec<String>,
+}
+impl UrlPolicy {
+	/// Creates a new UrlPolicy.
+	pub fn new() -> UrlPolicy {
+		Default::default()
+	}
+	/// Sets whether URLs must be written with an explicit scheme, rather than being given `http://`.
+	pub fn require_scheme(mut self, require_scheme: bool) -> UrlPolicy {
+		self.require_scheme = require_scheme;
+		self
+	}
+	/// Sets whether relative URLs, starting with `/`, `.`, `?` or `#`, are accepted.
+	pub fn allow_relative(mut self, allow_relative: bool) -> UrlPolicy {
+		self.allow_relative = allow_relative;
+		self
+	}
+	/// Sets the schemes that are accepted, replacing the defaults.
+	pub fn schemes(mut self, schemes: &[&str]) -> UrlPolicy {
+		self.schemes = schemes.iter().map(|scheme| scheme.to_ascii_lowercase()).collect();
+		self
+	}
+	/// Adds a scheme to those that are accepted.
+	pub fn allow_scheme(mut self, scheme: &str) -> UrlPolicy {
+		self.schemes.push(scheme.to_ascii_lowercase());
+		self
+	}
+	/// Parses a URL argument and checks it against the policy, giving the reason if it is not accepted.
+	/// URLs without a scheme that are not explicitly relative are read as a host and given `http://`,
+	/// or `https://` if only that is allowed.
+	pub fn check(&self, url: &str) -> Result<Url, UrlError> {
+		let parsed = Url::parse(url)?;
+		match parsed.scheme() {
+			Some(scheme) if self.schemes.iter().any(|allowed| allowed == scheme) => Ok(parsed),
+			Some(scheme) => Err(UrlError::DisallowedScheme(scheme.to_string())),
+			None if url.starts_with(['/', '.', '?', '#']) => {
+				if self.allow_relative {Ok(parsed)} else {Err(UrlError::RelativeNotAllowed)}
+			},
+			None if self.require_scheme => Err(UrlError::MissingScheme),
+			None => {
+				let scheme = ["http", "https"].iter()
+					.find(|scheme| self.schemes.iter().any(|allowed| allowed == **scheme))
+					.ok_or(UrlError::MissingScheme)?;
+				Url::parse(&format!("{}://{}", scheme, url))
+			}
+		}
+	}
+	/// Resolves a URL argument to the URL to write, or the reason it is not accepted.
+	pub(crate) fn resolve(&self, url: &str) -> Result<String, UrlError> {
+		self.check(url).map(|url| url.to_string())
+	}
+}
+impl Default for UrlPolicy {
+	fn default() -> Self {
+		UrlPolicy {
+			require_scheme: false,
+			allow_relative: false,
+			schemes: vec!["http".to_string(), "https".to_string()],
+		}
+	}
+}
+
+/// Builder for the options used to parse and render BBCode.
+///
+/// ```
+///use bbclash::{render, ParserOptions, LinebreakMode};
+///
+///let options = ParserOptions::new()
+///    .pretty_print(false)
+///    .linebreaks(LinebreakMode::Space);
+///assert_eq!(render("One\ntwo [b]three[/b]", &options),
+///        "<p>One two <b>three</b></p>");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParserOptions {
+	pub(crate) pretty_print: bool,
+	pub(crate) registry: Rc<TagRegistry>,
+	pub(crate) tag_filter: TagFilter,
+	pub(crate) linebreaks: LinebreakMode,
+	pub(crate) url_policy: UrlPolicy,
+	pub(crate) link_policy: LinkPolicy,
+	pub(crate) media_policy: MediaPolicy,
+	pub(crate) style_policy: StylePolicy,
+	pub(crate) max_depth: Option<usize>,
+	pub(crate) max_nodes: Option<usize>,
+	pub(crate) max_quote_depth: Option<usize>,
+	pub(crate) max_input_length: Option<usize>,
+	pub(crate) verify_html: bool,
+	pub(crate) flavour: OutputFlavour,
+	pub(crate) colour_mode: ColourMode,
+	pub(crate) terminal_width: usize,
+}
+impl ParserOptions {
+	/// Creates a new ParserOptions with BBClash's defaults: pretty output, the default tag set, and HTML output.
+	pub fn new() -> ParserOptions {
+		ParserOptions {
+			pretty_print: true,
+			registry: default_registry(),
+			tag_filter: TagFilter::default(),
+			linebreaks: LinebreakMode::default(),
+			url_policy: UrlPolicy::default(),
+			link_policy: LinkPolicy::default(),
+			media_policy: MediaPolicy::default(),
+			style_policy: StylePolicy::default(),
+			max_depth: None,
+			max_nodes: None,
+			max_quote_depth: None,
+			max_input_length: None,
+			verify_html: false,
+			flavour: OutputFlavour::default(),
+			colour_mode: ColourMode::default(),
+			terminal_width: 80,
+		}
+	}
+	/// Sets whether output is *pretty* (broken BBCode and empty elements removed) or *ugly* (left as written).
+	pub fn pretty_print(mut self, pretty_print: bool) -> ParserOptions {
+		self.pretty_print = pretty_print;
+		self
+	}
+	/// Sets the tags that are recognised.
+	pub fn tags(mut self, registry: TagRegistry) -> ParserOptions {
+		self.registry = Rc::new(registry);
+		self
+	}
+	/// Restricts which of the recognised tags are honoured.
+	pub fn tag_filter(mut self, tag_filter: TagFilter) -> ParserOptions {
+		self.tag_filter = tag_filter;
+		self
+	}
+	/// Sets how single newlines are handled.
+	pub fn linebreaks(mut self, linebreaks: LinebreakMode) -> ParserOptions {
+		self.linebreaks = linebreaks;
+		self
+	}
+	/// Sets the rules for accepting URLs.
+	pub fn url_policy(mut self, url_policy: UrlPolicy) -> ParserOptions {
+		self.url_policy = url_policy;
+		self
+	}
+	/// Sets the rules for the attributes of links.
+	pub fn link_policy(mut self, link_policy: LinkPolicy) -> ParserOptions {
+		self.link_policy = link_policy;
+		self
+	}
+	/// Sets the rules for images and embedded content.
+	pub fn media_policy(mut self, media_policy: MediaPolicy) -> ParserOptions {
+		self.media_policy = media_policy;
+		self
+	}
+	/// Sets how colours, sizes, opacities and list styles are written in HTML.
+	pub fn style_policy(mut self, style_policy: StylePolicy) -> ParserOptions {
+		self.style_policy = style_policy;
+		self
+	}
+	/// Sets the maximum nesting depth of elements. Tags opened beyond it, and their closing tags, are left as text.
+	pub fn max_depth(mut self, max_depth: usize) -> ParserOptions {
+		self.max_depth = Some(max_depth);
+		self
+	}
+	/// Sets the maximum number of elements the lexer creates. Once it is reached, the rest of the input is left as text.
+	pub fn max_nodes(mut self, max_nodes: usize) -> ParserOptions {
+		self.max_nodes = Some(max_nodes);
+		self
+	}
+	/// Sets the maximum nesting depth of quotes. Quotes opened beyond it, and their closing tags, are left as text.
+	pub fn max_quote_depth(mut self, max_quote_depth: usize) -> ParserOptions {
+		self.max_quote_depth = Some(max_quote_depth);
+		self
+	}
+	/// Sets the maximum length of input, in bytes, that `parse` and `render` read as BBCode. The rest is left as text.
+	pub fn max_input_length(mut self, max_input_length: usize) -> ParserOptions {
+		self.max_input_length = Some(max_input_length);
+		self
+	}
+	/// Sets whether HTML output is checked with `verify_html` after rendering. HTML that fails is
+	/// replaced with the escaped plain text of the input.
+	pub fn verify_html(mut self, verify_html: bool) -> ParserOptions {
+		self.verify_html = verify_html;
+		self
+	}
+	/// Sets the output format.
+	pub fn flavour(mut self, flavour: OutputFlavour) -> ParserOptions {
+		self.flavour = flavour;
+		self
+	}
+	/// Sets how colours and styles are written in terminal output. By default, this is `ColourMode::Palette256`.
+	pub fn colour_mode(mut self, colour_mode: ColourMode) -> ParserOptions {
+		self.colour_mode = colour_mode;
+		self
+	}
+	/// Sets the width, in characters, that terminal output is word-wrapped to. By default, this is 80;
+	/// 0 turns wrapping off.
+	pub fn terminal_width(mut self, terminal_width: usize) -> ParserOptions {
+		self.terminal_width = terminal_width;
+		self
+	}
+}
+impl Default for ParserOptions {
+	fn default() -> Self {ParserOptions::new()}
+}
@@ -0,0 +1,271 @@
+use rctree::Node;
+use std::rc::Rc;
+use super::GroupType;
+use super::ASTElement;
+use super::{TagRegistry, ParserOptions};
+use crate::ast::has_content;
+use crate::block_constructor::{BlockConstructor, BlockState, node_text};
+
+/// Struct for generation of plain text strings, for previews, notifications and search indexing.
+///
+/// All formatting is removed. Blocks are separated by blank lines, list items become bulleted or
+/// numbered lines, links are followed by their address, and each table row becomes a line of cells.
+pub struct PlainTextConstructor {
+	output_string: String,
+	pretty_print: bool,
+	registry: Option<Rc<TagRegistry>>,
+	prefixes: Vec<String>,
+	lists: Vec<(Option<String>, usize)>,
+	cell_start: Option<bool>,
+	blocks: BlockState,
+}
+impl PlainTextConstructor {
+	/// Creates a new PlainTextConstructor.
+	pub fn new(out_len: usize, pretty_print: bool) -> PlainTextConstructor {
+		let output_string = String::with_capacity(out_len);
+		PlainTextConstructor {
+			output_string,
+			pretty_print,
+			registry: None,
+			prefixes: Vec::new(),
+			lists: Vec::new(),
+			cell_start: None,
+			blocks: BlockState::new(),
+		}
+	}
+	/// Creates a new PlainTextConstructor that treats custom elements as blocks or inline according to the handlers in the given TagRegistry.
+	pub fn with_registry(out_len: usize, pretty_print: bool, registry: Rc<TagRegistry>) -> PlainTextConstructor {
+		let mut constructor = PlainTextConstructor::new(out_len, pretty_print);
+		constructor.registry = Some(registry);
+		constructor
+	}
+	/// Creates a new PlainTextConstructor configured by the given ParserOptions.
+	pub fn with_options(out_len: usize, options: &ParserOptions) -> PlainTextConstructor {
+		PlainTextConstructor::with_registry(out_len, options.pretty_print, options.registry.clone())
+	}
+
+	/// Generates a plain text string from an ASTElement
+	pub fn construct(&mut self, ast: Node<ASTElement>) -> String {
+		self.traverse(ast);
+		self.output_string.trim_end().to_string()
+	}
+
+	/// Starts an element.
+	fn start_element(&mut self, node: &Node<ASTElement>) {
+		if self.pretty_print && !has_content(node) {
+			self.blocks.skip = Some(node.clone());
+			return;
+		}
+		let element = node.borrow();
+		if self.blocks.block_ended && !self.is_block(element.ele_type()) {
+			self.open_block();
+		}
+		match element.ele_type() {
+			GroupType::Text => {
+				if let Some(text) = element.text_contents() {
+					self.push_text(text);
+				}
+			},
+			GroupType::Paragraph => {
+				match self.cell_start {
+					Some(true) => {},
+					Some(false) => self.push(" "),
+					None => self.open_block()
+				}
+			},
+			GroupType::Header |
+			GroupType::Hr |
+			GroupType::Scenebreak |
+			GroupType::CodeBlock |
+			GroupType::Pre |
+			GroupType::MathBlock |
+			GroupType::Center |
+			GroupType::Right |
+			GroupType::Indent |
+			GroupType::Figure |
+			GroupType::Embed
+				=> {self.open_block()},
+			GroupType::Br => {self.newline()},
+			GroupType::Quote => {
+				self.open_block();
+				self.output_string.push_str("> ");
+				self.prefixes.push("> ".to_string());
+			},
+			GroupType::List => {
+				self.open_block();
+				self.lists.push((element.argument().clone(), 0));
+			},
+			GroupType::ListItem => {
+				if !self.blocks.block_start {
+					self.newline();
+				}
+				let marker = match self.lists.last_mut() {
+					Some((list_type, number)) => {
+						*number += 1;
+						list_marker(list_type.as_deref(), *number)
+					},
+					None => list_marker(None, 1)
+				};
+				self.output_string.push_str(&marker);
+				self.prefixes.push(" ".repeat(marker.chars().count()));
+				self.blocks.block_start = true;
+			},
+			GroupType::Table => {self.open_block()},
+			GroupType::TableRow | GroupType::TableCaption => {
+				if !self.blocks.block_start {
+					self.newline();
+				}
+				self.blocks.block_ended = false;
+				if element.ele_type() == &GroupType::TableCaption {
+					self.cell_start = Some(true);
+				}
+			},
+			GroupType::TableHeader | GroupType::TableData => {
+				let first_cell = node.previous_sibling().is_none();
+				if !first_cell {
+					self.push(" | ");
+				}
+				self.cell_start = Some(true);
+			},
+			GroupType::Broken(_, tag) if !self.pretty_print => {
+				match element.argument() {
+					Some(arg) => self.push_text(&format!("[{}={}]", tag, arg)),
+					None => self.push_text(&format!("[{}]", tag))
+				}
+				if let Some(text) = element.text_contents() {
+					self.push_text(text);
+				}
+			},
+			GroupType::Custom(_) if self.is_block(element.ele_type()) => {self.open_block()},
+			_ => {}
+		};
+	}
+
+	/// Ends an element.
+	fn end_element(&mut self, node: &Node<ASTElement>) {
+		let element = node.borrow();
+		if self.is_block(element.ele_type()) && self.cell_start.is_none() {
+			self.blocks.block_ended = true;
+		}
+		match element.ele_type() {
+			GroupType::Quote => {
+				self.prefixes.pop();
+				self.blocks.block_start = false;
+			},
+			GroupType::List => {self.lists.pop();},
+			GroupType::ListItem => {
+				self.prefixes.pop();
+				self.blocks.block_start = false;
+			},
+			GroupType::TableHeader | GroupType::TableData | GroupType::TableCaption => {
+				self.cell_start = None;
+			},
+			GroupType::Url | GroupType::Email => {
+				if let Some(arg) = element.argument() {
+					let href = arg.as_str();
+					let text = node_text(node);
+					let bare_href = ["mailto:", "https://", "http://"].iter()
+						.find_map(|scheme| href.strip_prefix(scheme))
+						.unwrap_or(href);
+					if text.trim() != href && text.trim() != bare_href {
+						self.push(&format!(" ({})", href));
+					}
+				}
+			},
+			GroupType::Broken(_, tag) if !self.pretty_print && !element.is_void() => {
+				self.push_text(&format!("[/{}]", tag));
+			},
+			_ => {}
+		};
+	}
+
+	/// Starts a new line within the current block.
+	fn newline(&mut self) {
+		self.output_string.push('\n');
+		self.output_string.push_str(&self.prefixes.concat());
+	}
+
+	/// Writes text as-is.
+	fn push(&mut self, text: &str) {
+		self.output_string.push_str(text);
+		self.blocks.block_start = false;
+		if self.cell_start.is_some() {
+			self.cell_start = Some(false);
+		}
+	}
+
+	/// Writes text from the AST, continuing any prefix onto new lines.
+	fn push_text(&mut self, text: &str) {
+		let text = if self.blocks.block_start {text.trim_start_matches([' ', '\n'])} else {text};
+		if text.is_empty() {
+			return;
+		}
+		let prefix = self.prefixes.concat();
+		if prefix.is_empty() {
+			self.push(text);
+		} else {
+			self.push(&text.replace('\n', &format!("\n{}", prefix)));
+		}
+	}
+}
+impl BlockConstructor for PlainTextConstructor {
+	fn blocks(&mut self) -> &mut BlockState {
+		&mut self.blocks
+	}
+	fn registry(&self) -> Option<&Rc<TagRegistry>> {
+		self.registry.as_ref()
+	}
+	fn start(&mut self, node: &Node<ASTElement>) {
+		self.start_element(node);
+	}
+	fn end(&mut self, node: &Node<ASTElement>) {
+		self.end_element(node);
+	}
+	fn separate_blocks(&mut self) {
+		let prefix = self.prefixes.concat();
+		self.output_string.push('\n');
+		self.output_string.push_str(prefix.trim_end());
+		self.output_string.push('\n');
+		self.output_string.push_str(&prefix);
+	}
+}
+
+/// Gets the marker for the given item of a list of the given type.
+pub(crate) fn list_marker(list_type: Option<&str>, number: usize) -> String {
+	match list_type {
+		Some("1") => format!("{}. ", number),
+		Some("a") => format!("{}. ", alphabetic(number)),
+		Some("A") => format!("{}. ", alphabetic(number).to_uppercase()),
+		Some("i") => format!("{}. ", roman(number)),
+		Some("I") => format!("{}. ", roman(number).to_uppercase()),
+		Some("none") => String::new(),
+		_ => "• ".to_string()
+	}
+}
+
+/// Gets a list number as lowercase letters: a, b, ... z, aa, ab, ...
+fn alphabetic(mut number: usize) -> String {
+	let mut letters = Vec::new();
+	while number > 0 {
+		number -= 1;
+		letters.push((b'a' + (number % 26) as u8) as char);
+		number /= 26;
+	}
+	letters.iter().rev().collect()
+}
+
+/// Gets a list number as lowercase roman numerals.
+fn roman(mut number: usize) -> String {
+	let numerals = [
+		(1000, "m"), (900, "cm"), (500, "d"), (400, "cd"), (100, "c"), (90, "xc"),
+		(50, "l"), (40, "xl"), (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i"),
+	];
+	let mut output = String::new();
+	for (value, numeral) in numerals.iter() {
+		while number >= *value {
+			output.push_str(numeral);
+			number -= value;
+		}
+	}
+	output
+}
@@ -0,0 +1,261 @@
+use std::error::Error;
+use std::fmt;
+use phf::{phf_map, phf_set};
+use super::{ParserOptions, Url};
+
+/// The ways an attribute's value is checked.
+#[derive(Clone, Copy)]
+enum Value {
+	/// Any properly escaped text.
+	Text,
+	/// A URL that is relative, or whose scheme is allowed.
+	Url,
+	/// Declarations of the properties BBClash styles with, and nothing else.
+	Style,
+	/// One of the list types an `<ol>` can have.
+	ListType,
+}
+
+/// Static compile-time map of the elements HTMLConstructor writes to the attributes each can have.
+static ELEMENTS: phf::Map<&'static str, &'static [(&'static str, Value)]> = phf_map! {
+	"a" => &[("href", Value::Url), ("rel", Value::Text), ("target", Value::Text), ("referrerpolicy", Value::Text), ("class", Value::Text)],
+	"b" => &[],
+	"blockquote" => &[("data-author", Value::Text)],
+	"br" => &[],
+	"caption" => &[],
+	"code" => &[],
+	"div" => &[("class", Value::Text), ("data-content", Value::Url)],
+	"em" => &[],
+	"figure" => &[("class", Value::Text)],
+	"h1" => &[],
+	"h2" => &[],
+	"h3" => &[],
+	"h4" => &[],
+	"h5" => &[],
+	"h6" => &[],
+	"hr" => &[],
+	"i" => &[],
+	"img" => &[("src", Value::Url)],
+	"li" => &[],
+	"ol" => &[("type", Value::ListType)],
+	"p" => &[],
+	"pre" => &[("data-language", Value::Text)],
+	"s" => &[],
+	"span" => &[("class", Value::Text), ("style", Value::Style), ("data-symbol", Value::Text)],
+	"strong" => &[],
+	"sub" => &[],
+	"sup" => &[],
+	"table" => &[],
+	"td" => &[],
+	"th" => &[],
+	"tr" => &[],
+	"ul" => &[("class", Value::Text), ("style", Value::Style)],
+};
+
+/// Static compile-time set of the elements that have no closing tag.
+static VOID_ELEMENTS: phf::Set<&'static str> = phf_set! {
+	"br",
+	"hr",
+	"img",
+};
+
+/// Static compile-time set of the CSS properties that `style` attributes can set.
+static STYLE_PROPERTIES: phf::Set<&'static str> = phf_set! {
+	"color",
+	"opacity",
+	"font-size",
+	"list-style-type",
+};
+
+/// The reason HTML failed verification.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum HtmlViolation {
+	/// An element that isn't on the allowlist.
+	DisallowedElement(String),
+	/// An attribute that isn't on the allowlist for its element, given as the element and the attribute.
+	DisallowedAttribute(String, String),
+	/// An attribute that is given more than once, given as the element and the attribute.
+	DuplicateAttribute(String, String),
+	/// An attribute whose value isn't allowed, given as the attribute and its value.
+	InvalidAttributeValue(String, String),
+	/// A character that should have been escaped.
+	UnescapedCharacter(char),
+	/// A closing tag that doesn't match the open element, or an element that is never closed.
+	UnbalancedTag(String),
+	/// A tag that isn't written the way HTMLConstructor writes tags.
+	MalformedTag,
+}
+impl fmt::Display for HtmlViolation {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			HtmlViolation::DisallowedElement(element) => write!(f, "`<{}>` is not an allowed element", element),
+			HtmlViolation::DisallowedAttribute(element, attribute) => write!(f, "`{}` is not an allowed attribute of `<{}>`", attribute, element),
+			HtmlViolation::DuplicateAttribute(element, attribute) => write!(f, "`<{}>` has more than one `{}` attribute", element, attribute),
+			HtmlViolation::InvalidAttributeValue(attribute, value) => write!(f, "`{}` is not an allowed value of `{}`", value, attribute),
+			HtmlViolation::UnescapedCharacter(c) => write!(f, "`{}` is not escaped", c),
+			HtmlViolation::UnbalancedTag(element) => write!(f, "`<{}>` is not closed in order", element),
+			HtmlViolation::MalformedTag => write!(f, "a tag is malformed"),
+		}
+	}
+}
+impl Error for HtmlViolation {}
+
+/// Checks that HTML only contains the elements and attributes that HTMLConstructor writes, each at
+/// most once, that every URL is accepted by the ParserOptions' UrlPolicy (or is a `mailto:` link),
+/// once its character references are decoded, that `style` attributes only set the properties BBClash uses, that text and attribute
+/// values are escaped, and that every element is closed in order.
+///
+/// HTML written by a TagHandler is held to the same allowlist.
+///
+/// ```
+///use bbclash::{render, verify_html, ParserOptions, HtmlViolation};
+///
+///let options = ParserOptions::new();
+///assert_eq!(verify_html(&render("[b]Bold[/b] & [url=https://penclash.com]a link[/url]", &options), &options), Ok(()));
+///assert_eq!(verify_html("<p><script>alert(1)</script></p>", &options),
+///        Err(HtmlViolation::DisallowedElement("script".into())));
+///assert_eq!(verify_html("<a href=\"javascript:alert(1)\">x</a>", &options),
+///        Err(HtmlViolation::InvalidAttributeValue("href".into(), "javascript:alert(1)".into())));
+/// ```
+pub fn verify_html(html: &str, options: &ParserOptions) -> Result<(), HtmlViolation> {
+	let mut open: Vec<&str> = Vec::new();
+	let mut rest = html;
+	while let Some(position) = rest.find(['<', '>', '&']) {
+		let markup = &rest[position..];
+		rest = match markup.as_bytes()[0] {
+			b'<' => {
+				let end = markup.find('>').ok_or(HtmlViolation::MalformedTag)?;
+				verify_tag(&markup[1..end], &mut open, options)?;
+				&markup[end + 1..]
+			},
+			b'&' => {
+				let end = entity_length(markup).ok_or(HtmlViolation::UnescapedCharacter('&'))?;
+				&markup[end..]
+			},
+			_ => return Err(HtmlViolation::UnescapedCharacter('>'))
+		};
+	}
+	match open.pop() {
+		Some(element) => Err(HtmlViolation::UnbalancedTag(element.to_string())),
+		None => Ok(())
+	}
+}
+
+/// Checks the inside of a tag, between its `<` and `>`, and keeps track of the open elements.
+fn verify_tag<'a>(tag: &'a str, open: &mut Vec<&'a str>, options: &ParserOptions) -> Result<(), HtmlViolation> {
+	if !tag.trim_start_matches('/').starts_with(|c: char| c.is_ascii_alphabetic()) {
+		return Err(HtmlViolation::UnescapedCharacter('<'));
+	}
+	if let Some(element) = tag.strip_prefix('/') {
+		return match open.pop() {
+			Some(opened) if opened == element => Ok(()),
+			_ => Err(HtmlViolation::UnbalancedTag(element.to_string()))
+		};
+	}
+	let (element, mut attributes) = tag.split_once(' ').unwrap_or((tag, ""));
+	let allowed = ELEMENTS.get(element).ok_or_else(|| HtmlViolation::DisallowedElement(element.to_string()))?;
+	let mut seen: Vec<&str> = Vec::new();
+	while !attributes.is_empty() {
+		let (name, after_name) = attributes.split_once("=\"").ok_or(HtmlViolation::MalformedTag)?;
+		let (value, after_value) = after_name.split_once('"').ok_or(HtmlViolation::MalformedTag)?;
+		let kind = allowed.iter().find(|(allowed_name, _)| *allowed_name == name)
+			.map(|(_, kind)| *kind)
+			.ok_or_else(|| HtmlViolation::DisallowedAttribute(element.to_string(), name.to_string()))?;
+		if seen.contains(&name) {
+			return Err(HtmlViolation::DuplicateAttribute(element.to_string(), name.to_string()));
+		}
+		seen.push(name);
+		verify_attribute(name, value, kind, options)?;
+		attributes = match after_value.strip_prefix(' ') {
+			Some(next) => next,
+			None if after_value.is_empty() => after_value,
+			None => return Err(HtmlViolation::MalformedTag)
+		};
+	}
+	if !VOID_ELEMENTS.contains(element) {
+		open.push(element);
+	}
+	Ok(())
+}
+
+/// Checks an attribute's value, which is written as it appears between its quotes.
+fn verify_attribute(name: &str, value: &str, kind: Value, options: &ParserOptions) -> Result<(), HtmlViolation> {
+	let invalid = || HtmlViolation::InvalidAttributeValue(name.to_string(), value.to_string());
+	if let Some(c) = value.chars().find(|c| ['<', '>', '\''].contains(c)) {
+		return Err(HtmlViolation::UnescapedCharacter(c));
+	}
+	let mut rest = value;
+	while let Some(position) = rest.find('&') {
+		let end = entity_length(&rest[position..]).ok_or(HtmlViolation::UnescapedCharacter('&'))?;
+		rest = &rest[position + end..];
+	}
+	let valid = match kind {
+		Value::Text => true,
+		Value::Url => {
+			match decode_references(value) {
+				Some(url) => {
+					Url::parse(&url).is_ok_and(|url| url.scheme() == Some("mailto"))
+						|| options.url_policy.check(&url).is_ok_and(|checked| checked.as_str() == url)
+				},
+				None => false
+			}
+		},
+		Value::Style => value.split_terminator(';').all(|declaration| {
+			declaration.split_once(':').is_some_and(|(property, value)| {
+				STYLE_PROPERTIES.contains(property) && !value.is_empty()
+					&& value.chars().all(|c| c.is_ascii_alphanumeric() || c == '#' || c == '.')
+			})
+		}) && value.ends_with(';'),
+		Value::ListType => matches!(value, "1" | "a" | "A" | "i" | "I"),
+	};
+	if valid {Ok(())} else {Err(invalid())}
+}
+
+/// Decodes the character references in an attribute's value, or gives None if it has a named
+/// reference other than those `escape_attribute` writes, or a number that isn't a character.
+fn decode_references(value: &str) -> Option<String> {
+	let mut decoded = String::with_capacity(value.len());
+	let mut rest = value;
+	while let Some(position) = rest.find('&') {
+		decoded.push_str(&rest[..position]);
+		let end = position + entity_length(&rest[position..])?;
+		let name = &rest[position + 1..end - 1];
+		let c = match name.strip_prefix('#') {
+			Some(number) => {
+				let code = match number.strip_prefix(['x', 'X']) {
+					Some(hex) => u32::from_str_radix(hex, 16),
+					None => number.parse()
+				};
+				char::from_u32(code.ok()?).filter(|c| *c != '\0')?
+			},
+			None => match name {
+				"amp" => '&',
+				"lt" => '<',
+				"gt" => '>',
+				"quot" => '"',
+				"apos" => '\'',
+				_ => return None
+			}
+		};
+		decoded.push(c);
+		rest = &rest[end..];
+	}
+	decoded.push_str(rest);
+	Some(decoded)
+}
+
+/// Gets the length of the character reference at the start of some HTML, such as `&amp;` or `&#x27;`,
+/// or None if it doesn't start with one.
+fn entity_length(html: &str) -> Option<usize> {
+	let (end, _) = html.char_indices().take(32).find(|(_, c)| *c == ';')?;
+	let name = &html[1..end];
+	let valid = match name.strip_prefix('#') {
+		Some(number) => match number.strip_prefix(['x', 'X']) {
+			Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+			None => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+		},
+		None => !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric())
+	};
+	if valid {Some(end + 1)} else {None}
+}
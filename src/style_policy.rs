@@ -0,0 +1,163 @@
+use super::Colour;
+
+/// The colours, sizes and opacities that classes are written for by default.
+const DEFAULT_PALETTE: [&str; 16] = [
+	"black", "silver", "gray", "white", "maroon", "red", "purple", "fuchsia",
+	"green", "lime", "olive", "yellow", "navy", "blue", "teal", "aqua",
+];
+const DEFAULT_SIZES: [f32; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+const DEFAULT_OPACITIES: [f32; 4] = [0.25, 0.5, 0.75, 1.0];
+/// List types written with `list-style-type` rather than as an `<ol type>`.
+const LIST_STYLES: [&str; 3] = ["circle", "square", "none"];
+
+/// Rules for how `[colour]`, `[size]`, `[opacity]` and styled `[list]` tags are written in HTML.
+///
+/// By default, they are written with inline `style` attributes. With `StylePolicy::classes`, they are
+/// written as classes instead, such as `class="bb-color-red"` and `class="bb-size-1_5"`, so the
+/// output works under a Content-Security-Policy that blocks inline styles. Colours, sizes and
+/// opacities are quantised to the nearest entry of a palette and scales, and `stylesheet` gets the
+/// CSS rules for every class that can be written.
+///
+/// ```
+///use bbclash::{render, ParserOptions, StylePolicy};
+///
+///let options = ParserOptions::new().style_policy(StylePolicy::classes());
+///assert_eq!(render("[colour=#e01020]Red[/colour] and [size=23]big[/size]", &options),
+///        "<p><span class=\"bb-color-red\">Red</span> and <span class=\"bb-size-1_5\">big</span></p>");
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StylePolicy {
+	classes: Option<Scales>,
+}
+#[derive(Debug, Clone, PartialEq)]
+struct Scales {
+	palette: Vec<Colour>,
+	sizes: Vec<f32>,
+	opacities: Vec<f32>,
+}
+impl StylePolicy {
+	/// Creates a new StylePolicy, which writes inline styles.
+	pub fn new() -> StylePolicy {
+		Default::default()
+	}
+	/// Creates a StylePolicy that writes classes, with the 16 basic web colours as its palette, sizes
+	/// from 0.5rem to 2rem, and opacities in quarters.
+	pub fn classes() -> StylePolicy {
+		StylePolicy {
+			classes: Some(Scales {
+				palette: DEFAULT_PALETTE.iter().filter_map(|colour| Colour::parse(colour)).collect(),
+				sizes: DEFAULT_SIZES.to_vec(),
+				opacities: DEFAULT_OPACITIES.to_vec(),
+			})
+		}
+	}
+	/// Sets the colours that colours are quantised to when writing classes. Entries that aren't valid
+	/// colours are ignored, and an empty palette leaves colours unstyled.
+	pub fn palette(mut self, palette: &[&str]) -> StylePolicy {
+		if let Some(ref mut scales) = self.classes {
+			scales.palette = palette.iter().filter_map(|colour| Colour::parse(colour)).collect();
+		}
+		self
+	}
+	/// Sets the font sizes, in rem, that sizes are quantised to when writing classes.
+	pub fn sizes(mut self, sizes: &[f32]) -> StylePolicy {
+		if let Some(ref mut scales) = self.classes {
+			scales.sizes = sizes.iter().copied().filter(|size| size.is_finite() && *size > 0.0).collect();
+		}
+		self
+	}
+	/// Sets the opacities that opacities are quantised to when writing classes.
+	pub fn opacities(mut self, opacities: &[f32]) -> StylePolicy {
+		if let Some(ref mut scales) = self.classes {
+			scales.opacities = opacities.iter().copied().filter(|opacity| (0.0..=1.0).contains(opacity)).collect();
+		}
+		self
+	}
+	/// Gets whether styles are written as classes.
+	pub fn uses_classes(&self) -> bool {
+		self.classes.is_some()
+	}
+	/// Gets the CSS rules for every class the policy can write, one per line. Inline styles need no
+	/// stylesheet, so this is empty unless the policy writes classes.
+	pub fn stylesheet(&self) -> String {
+		let scales = match self.classes {
+			Some(ref scales) => scales,
+			None => return String::new()
+		};
+		let mut css = String::new();
+		for colour in &scales.palette {
+			css.push_str(&format!(".{}{{color:{};}}\n", colour_class(colour), colour));
+		}
+		for size in &scales.sizes {
+			css.push_str(&format!(".{}{{font-size:{}rem;}}\n", number_class("bb-size", *size), size));
+		}
+		for opacity in &scales.opacities {
+			css.push_str(&format!(".{}{{opacity:{};}}\n", number_class("bb-opacity", *opacity), opacity));
+		}
+		for list_style in LIST_STYLES.iter() {
+			css.push_str(&format!(".bb-list-{}{{list-style-type:{};}}\n", list_style, list_style));
+		}
+		css
+	}
+
+	/// Gets the attribute that styles text with a colour, given as the colour's canonical form.
+	pub(crate) fn colour(&self, value: &str) -> Option<String> {
+		match self.classes {
+			None => Some(format!("style=\"color:{};\"", value)),
+			Some(ref scales) => {
+				let colour = Colour::parse(value)?;
+				scales.palette.iter()
+					.min_by_key(|candidate| colour_distance(&colour, candidate))
+					.map(|nearest| format!("class=\"{}\"", colour_class(nearest)))
+			}
+		}
+	}
+	/// Gets the attribute that styles text with a font size in rem.
+	pub(crate) fn size(&self, value: &str) -> Option<String> {
+		match self.classes {
+			None => Some(format!("style=\"font-size:{}rem;\"", value)),
+			Some(ref scales) => nearest(&scales.sizes, value.parse().ok()?)
+				.map(|size| format!("class=\"{}\"", number_class("bb-size", size)))
+		}
+	}
+	/// Gets the attribute that styles text with an opacity.
+	pub(crate) fn opacity(&self, value: &str) -> Option<String> {
+		match self.classes {
+			None => Some(format!("style=\"opacity:{};\"", value)),
+			Some(ref scales) => nearest(&scales.opacities, value.parse().ok()?)
+				.map(|opacity| format!("class=\"{}\"", number_class("bb-opacity", opacity)))
+		}
+	}
+	/// Gets the attribute that styles an unordered list's markers.
+	pub(crate) fn list_style(&self, value: &str) -> String {
+		match self.classes {
+			None => format!("style=\"list-style-type:{};\"", value),
+			Some(_) => format!("class=\"bb-list-{}\"", value)
+		}
+	}
+}
+
+/// Gets the class for a palette colour: its name, or its hex digits if it has none.
+fn colour_class(colour: &Colour) -> String {
+	match colour.name() {
+		Some(name) => format!("bb-color-{}", name),
+		None => format!("bb-color-{}", colour.to_string().trim_start_matches('#'))
+	}
+}
+
+/// Gets the class for a number on a scale, with `_` in place of the decimal point.
+fn number_class(prefix: &str, value: f32) -> String {
+	format!("{}-{}", prefix, value.to_string().replace('.', "_"))
+}
+
+/// Gets the squared distance between two colours' red, green and blue components.
+fn colour_distance(a: &Colour, b: &Colour) -> u32 {
+	[(a.red(), b.red()), (a.green(), b.green()), (a.blue(), b.blue())].iter()
+		.map(|&(a, b)| (i32::from(a) - i32::from(b)).pow(2) as u32)
+		.sum()
+}
+
+/// Gets the entry of a scale nearest to a value.
+fn nearest(scale: &[f32], value: f32) -> Option<f32> {
+	scale.iter().copied().min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use crate::bbcode_lexer::canonical_tag;
+
+/// Restricts which of the registered tags are honoured in a given context, such as signatures or titles.
+///
+/// Tags are named without a leading slash, in any case, and the filter applies to both their opening and closing
+/// forms, and to every spelling of the tag, such as `[color]` and `[colour]`.
+/// Tags that are filtered out are left as text in ugly output and removed in pretty output.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum TagFilter {
+	/// Every registered tag is honoured.
+	#[default]
+	All,
+	/// Only the listed tags are honoured.
+	Allow(HashSet<String>),
+	/// Every registered tag except the listed ones is honoured.
+	Deny(HashSet<String>),
+}
+impl TagFilter {
+	/// Creates a TagFilter that honours every registered tag.
+	pub fn all() -> TagFilter {
+		TagFilter::All
+	}
+	/// Creates a TagFilter that honours only the given tags.
+	pub fn allow(tags: &[&str]) -> TagFilter {
+		TagFilter::Allow(tags.iter().map(|tag| normalise(tag)).collect())
+	}
+	/// Creates a TagFilter that honours every registered tag except the given ones.
+	pub fn deny(tags: &[&str]) -> TagFilter {
+		TagFilter::Deny(tags.iter().map(|tag| normalise(tag)).collect())
+	}
+	/// Creates a TagFilter that honours only inline formatting and links, suitable for signatures and titles.
+	pub fn inline_only() -> TagFilter {
+		TagFilter::allow(&INLINE_TAGS)
+	}
+	/// Creates a TagFilter that honours every registered tag except images and embeds.
+	pub fn no_media() -> TagFilter {
+		TagFilter::deny(&MEDIA_TAGS)
+	}
+	/// Gets whether a tag is honoured. A leading slash is ignored.
+	pub fn permits(&self, tag: &str) -> bool {
+		let tag = normalise(tag.trim_start_matches('/'));
+		match self {
+			TagFilter::All => true,
+			TagFilter::Allow(tags) => tags.contains(&tag),
+			TagFilter::Deny(tags) => !tags.contains(&tag),
+		}
+	}
+}
+
+/// Gets the lowercase, canonical name a tag is filtered by.
+fn normalise(tag: &str) -> String {
+	canonical_tag(&tag.to_lowercase()).to_string()
+}
+
+/// Tags honoured by `TagFilter::inline_only`.
+static INLINE_TAGS: [&str; 15] = [
+	"b",
+	"i",
+	"s",
+	"u",
+	"strong",
+	"em",
+	"smcaps",
+	"mono",
+	"sub",
+	"sup",
+	"spoiler",
+	"colour",
+	"url",
+	"email",
+	"code",
+];
+
+/// Tags refused by `TagFilter::no_media`.
+static MEDIA_TAGS: [&str; 2] = [
+	"img",
+	"embed",
+];
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use super::BBCodeLexer;
+use crate::bbcode_lexer::{NO_ARG_CMD, ONE_ARG_CMD};
+
+/// A lexer command for a tag written without an argument, such as `[b]` or `[/b]`.
+pub type NoArgCommand = fn(&mut BBCodeLexer);
+/// A lexer command for a tag written with an argument, such as `[url=...]`.
+pub type OneArgCommand = fn(&mut BBCodeLexer, &str);
+
+/// A user-defined tag, handled from argument validation through to HTML output.
+///
+/// Elements created by a TagHandler are given the type `GroupType::Custom`, carrying the tag's name.
+pub trait TagHandler {
+	/// Checks whether a tag's argument is valid. `None` means the tag was written without one.
+	/// Tags with invalid arguments are treated as broken. By default, only bare tags are accepted.
+	fn validate_arg(&self, arg: Option<&str>) -> bool {
+		arg.is_none()
+	}
+	/// Gets whether the tag is a block element, which closes the current paragraph, rather than an inline one.
+	fn is_block(&self) -> bool {
+		false
+	}
+	/// Renders the tag's opening HTML. The argument has already been escaped for use in an attribute.
+	fn render_open(&self, arg: Option<&str>) -> String;
+	/// Renders the tag's closing HTML. The argument has already been escaped for use in an attribute.
+	fn render_close(&self, arg: Option<&str>) -> String;
+}
+
+/// Runtime map of BBCode tags to the lexer commands that handle them.
+///
+/// Opening and closing tags are registered separately, so a tag such as `[spoiler]`
+/// needs entries for both `"spoiler"` and `"/spoiler"`. `TagRegistry::default()`
+/// reproduces BBClash's built-in tag set.
+///
+/// Tags registered with a TagHandler are the exception: the handler is registered once,
+/// under the opening tag's name, and handles both forms.
+///
+/// The lexer matches tags case-insensitively, so names should be registered in lowercase.
+#[derive(Clone)]
+pub struct TagRegistry {
+	no_arg: HashMap<String, NoArgCommand>,
+	one_arg: HashMap<String, OneArgCommand>,
+	handlers: HashMap<String, Rc<dyn TagHandler>>,
+}
+impl TagRegistry {
+	/// Creates a new, empty TagRegistry.
+	pub fn new() -> TagRegistry {
+		TagRegistry {
+			no_arg: HashMap::new(),
+			one_arg: HashMap::new(),
+			handlers: HashMap::new(),
+		}
+	}
+	/// Registers a TagHandler for a tag, replacing any commands or handler previously registered for it.
+	pub fn register_handler<H: TagHandler + 'static>(&mut self, tag: &str, handler: H) {
+		self.remove(tag);
+		self.remove(&format!("/{}", tag));
+		self.handlers.insert(tag.to_string(), Rc::new(handler));
+	}
+	/// Gets the TagHandler registered for a tag.
+	pub fn handler(&self, tag: &str) -> Option<Rc<dyn TagHandler>> {
+		self.handlers.get(tag).cloned()
+	}
+	/// Registers a command for a tag without an argument, returning the command it replaced, if any.
+	pub fn register(&mut self, tag: &str, command: NoArgCommand) -> Option<NoArgCommand> {
+		self.no_arg.insert(tag.to_string(), command)
+	}
+	/// Registers a command for a tag with an argument, returning the command it replaced, if any.
+	pub fn register_with_arg(&mut self, tag: &str, command: OneArgCommand) -> Option<OneArgCommand> {
+		self.one_arg.insert(tag.to_string(), command)
+	}
+	/// Removes both forms of a tag, and any handler registered for it, from the registry. 
+	/// Returns whether anything was removed.
+	pub fn remove(&mut self, tag: &str) -> bool {
+		let no_arg = self.no_arg.remove(tag).is_some();
+		let one_arg = self.one_arg.remove(tag).is_some();
+		let handler = self.handlers.remove(tag).is_some();
+		no_arg || one_arg || handler
+	}
+	/// Gets whether either form of a tag, or a handler for it, is registered.
+	pub fn contains(&self, tag: &str) -> bool {
+		self.no_arg.contains_key(tag) || self.one_arg.contains_key(tag) || self.handlers.contains_key(tag)
+	}
+	/// Gets the command registered for a tag without an argument.
+	pub fn no_arg_command(&self, tag: &str) -> Option<NoArgCommand> {
+		self.no_arg.get(tag).copied()
+	}
+	/// Gets the command registered for a tag with an argument.
+	pub fn one_arg_command(&self, tag: &str) -> Option<OneArgCommand> {
+		self.one_arg.get(tag).copied()
+	}
+}
+thread_local! {
+	static DEFAULT_REGISTRY: Rc<TagRegistry> = Rc::new(TagRegistry::default());
+}
+/// Gets a shared copy of the default TagRegistry, so that it is only built once per thread.
+pub(crate) fn default_registry() -> Rc<TagRegistry> {
+	DEFAULT_REGISTRY.with(Rc::clone)
+}
+
+impl fmt::Debug for TagRegistry {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("TagRegistry")
+			.field("no_arg", &self.no_arg)
+			.field("one_arg", &self.one_arg)
+			.field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+			.finish()
+	}
+}
+impl Default for TagRegistry {
+	/// Creates a TagRegistry containing BBClash's built-in tags.
+	fn default() -> TagRegistry {
+		let mut registry = TagRegistry::new();
+		for (tag, command) in NO_ARG_CMD.entries() {
+			registry.register(tag, *command);
+		}
+		for (tag, command) in ONE_ARG_CMD.entries() {
+			registry.register_with_arg(tag, *command);
+		}
+		registry
+	}
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+
+/// A URL parsed from a tag's argument.
+///
+/// The URL is kept as written; its scheme, host, port, path, query and fragment are separated out so
+/// that policies can make decisions about them. URLs without a scheme are relative.
+///
+/// ```
+///use bbclash::Url;
+///
+///let url = Url::parse("https://www.penclash.com:8080/stories?page=2#chapter-3").unwrap();
+///assert_eq!(url.scheme(), Some("https"));
+///assert_eq!(url.host(), Some("www.penclash.com"));
+///assert_eq!(url.port(), Some(8080));
+///assert_eq!(url.path(), "/stories");
+///assert_eq!(url.query(), Some("page=2"));
+///assert_eq!(url.fragment(), Some("chapter-3"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+	serialised: String,
+	scheme: Option<String>,
+	host: Option<String>,
+	port: Option<u16>,
+	path: String,
+	query: Option<String>,
+	fragment: Option<String>,
+}
+impl Url {
+	/// Parses an absolute or relative URL.
+	///
+	/// Schemes are matched case-insensitively. `http`, `https`, `ftp`, `ws` and `wss` URLs must have a
+	/// host; other schemes, such as `mailto`, need not. A name followed by a colon and a port number, such
+	/// as `localhost:8080`, is read as a host rather than a scheme.
+	pub fn parse(input: &str) -> Result<Url, UrlError> {
+		if input.is_empty() {
+			return Err(UrlError::Empty);
+		}
+		if let Some(c) = input.chars().find(|c| c.is_whitespace() || c.is_control() || FORBIDDEN_CHARS.contains(c)) {
+			return Err(UrlError::InvalidCharacter(c));
+		}
+		let (scheme, rest) = match split_scheme(input) {
+			Some((scheme, rest)) => (Some(scheme.to_ascii_lowercase()), rest),
+			None => (None, input)
+		};
+		let (rest, fragment) = split_off(rest, '#');
+		let (rest, query) = split_off(rest, '?');
+		let mut url = Url {
+			serialised: input.to_string(),
+			scheme,
+			host: None,
+			port: None,
+			path: String::new(),
+			query,
+			fragment,
+		};
+		let needs_host = url.scheme.as_deref().is_some_and(|scheme| HOSTED_SCHEMES.contains(&scheme));
+		if let Some(after_slashes) = rest.strip_prefix("//") {
+			let (authority, path) = after_slashes.split_at(after_slashes.find('/').unwrap_or(after_slashes.len()));
+			let (host, port) = parse_authority(authority)?;
+			url.host = Some(host);
+			url.port = port;
+			url.path = path.to_string();
+		} else if needs_host {
+			return Err(UrlError::MissingHost);
+		} else if url.scheme.is_some() && rest.is_empty() {
+			return Err(UrlError::Empty);
+		} else {
+			url.path = rest.to_string();
+		}
+		Ok(url)
+	}
+	/// Gets the URL's scheme, in lowercase, if it has one.
+	pub fn scheme(&self) -> Option<&str> {
+		self.scheme.as_deref()
+	}
+	/// Gets the URL's host, in lowercase, if it has one.
+	pub fn host(&self) -> Option<&str> {
+		self.host.as_deref()
+	}
+	/// Gets the URL's port, if one was written.
+	pub fn port(&self) -> Option<u16> {
+		self.port
+	}
+	/// Gets the URL's path. For URLs without a host, such as `mailto:` URLs, this is everything after the scheme.
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+	/// Gets the URL's query, without the leading `?`, if it has one.
+	pub fn query(&self) -> Option<&str> {
+		self.query.as_deref()
+	}
+	/// Gets the URL's fragment, without the leading `#`, if it has one.
+	pub fn fragment(&self) -> Option<&str> {
+		self.fragment.as_deref()
+	}
+	/// Gets whether the URL is relative, having no scheme.
+	pub fn is_relative(&self) -> bool {
+		self.scheme.is_none()
+	}
+	/// Gets the URL as written.
+	pub fn as_str(&self) -> &str {
+		&self.serialised
+	}
+	/// Gets whether the URL's host, ignoring a trailing dot, is a domain or one of its subdomains.
+	/// The domain must be normalised with `normalise_domain`.
+	pub(crate) fn host_matches(&self, domain: &str) -> bool {
+		self.host().is_some_and(|host| {
+			let host = host.trim_end_matches('.');
+			host == domain || host.strip_suffix(domain).is_some_and(|subdomain| subdomain.ends_with('.'))
+		})
+	}
+}
+impl fmt::Display for Url {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.serialised)
+	}
+}
+
+/// Reasons a URL can be rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UrlError {
+	/// The URL, or the part after its scheme, is empty.
+	Empty,
+	/// The URL contains a character that is not allowed in URLs.
+	InvalidCharacter(char),
+	/// The URL has no scheme, and the policy requires one.
+	MissingScheme,
+	/// The URL's scheme is not on the policy's allowlist.
+	DisallowedScheme(String),
+	/// The URL is relative, and the policy does not allow relative URLs.
+	RelativeNotAllowed,
+	/// The URL's scheme needs a host, but it has none.
+	MissingHost,
+	/// The URL's host is not a valid domain name or IP address.
+	InvalidHost(String),
+	/// The URL's port is not a number from 0 to 65535.
+	InvalidPort(String),
+	/// The URL contains a username or password.
+	Credentials,
+}
+impl fmt::Display for UrlError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			UrlError::Empty => write!(f, "the URL is empty"),
+			UrlError::InvalidCharacter(c) => write!(f, "`{}` is not allowed in a URL", c),
+			UrlError::MissingScheme => write!(f, "the URL has no scheme, such as `https://`"),
+			UrlError::DisallowedScheme(scheme) => write!(f, "the `{}` scheme is not allowed", scheme),
+			UrlError::RelativeNotAllowed => write!(f, "relative URLs are not allowed"),
+			UrlError::MissingHost => write!(f, "the URL has no host"),
+			UrlError::InvalidHost(host) => write!(f, "`{}` is not a valid host", host),
+			UrlError::InvalidPort(port) => write!(f, "`{}` is not a valid port", port),
+			UrlError::Credentials => write!(f, "URLs may not contain a username or password"),
+		}
+	}
+}
+impl std::error::Error for UrlError {}
+
+/// Normalises a domain for matching against URLs' hosts: lowercased, without a trailing dot.
+pub(crate) fn normalise_domain(domain: &str) -> String {
+	domain.trim_end_matches('.').to_lowercase()
+}
+
+/// Schemes whose URLs must have a host.
+static HOSTED_SCHEMES: [&str; 5] = ["http", "https", "ftp", "ws", "wss"];
+
+/// Characters that are never allowed in a URL, as well as whitespace and control characters.
+static FORBIDDEN_CHARS: [char; 9] = ['"', '<', '>', '`', '{', '}', '|', '\\', '^'];
+
+/// Splits a URL into its scheme and the rest, if it has a scheme.
+fn split_scheme(input: &str) -> Option<(&str, &str)> {
+	let (scheme, rest) = input.split_once(':')?;
+	let mut chars = scheme.chars();
+	let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+		&& chars.all(|c| c.is_ascii_alphanumeric() || ['+', '-', '.'].contains(&c));
+	let port_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+	let is_port = port_end > 0 && rest[..port_end].chars().all(|c| c.is_ascii_digit());
+	if valid && !is_port {Some((scheme, rest))} else {None}
+}
+
+/// Splits off the part of a URL after a delimiter, if the delimiter is present.
+fn split_off(input: &str, delimiter: char) -> (&str, Option<String>) {
+	match input.split_once(delimiter) {
+		Some((before, after)) => (before, Some(after.to_string())),
+		None => (input, None)
+	}
+}
+
+/// Parses the host and port of a URL.
+fn parse_authority(authority: &str) -> Result<(String, Option<u16>), UrlError> {
+	if authority.contains('@') {
+		return Err(UrlError::Credentials);
+	}
+	let (host, port) = match authority.strip_prefix('[') {
+		Some(ipv6) => {
+			let (address, after) = ipv6.split_once(']').ok_or_else(|| UrlError::InvalidHost(authority.to_string()))?;
+			if address.is_empty() || !address.chars().all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.') {
+				return Err(UrlError::InvalidHost(authority.to_string()));
+			}
+			match after {
+				"" => (&authority[..address.len() + 2], None),
+				_ => match after.strip_prefix(':') {
+					Some(port) => (&authority[..address.len() + 2], Some(port)),
+					None => return Err(UrlError::InvalidHost(authority.to_string()))
+				}
+			}
+		},
+		None => {
+			match authority.split_once(':') {
+				Some((host, port)) => (host, Some(port)),
+				None => (authority, None)
+			}
+		}
+	};
+	if host.is_empty() {
+		return Err(UrlError::MissingHost);
+	}
+	if !host.starts_with('[') && !is_valid_domain(host) {
+		return Err(UrlError::InvalidHost(host.to_string()));
+	}
+	let port = match port {
+		None | Some("") => None,
+		Some(port) if port.chars().all(|c| c.is_ascii_digit()) => {
+			Some(port.parse::<u16>().map_err(|_| UrlError::InvalidPort(port.to_string()))?)
+		},
+		Some(port) => return Err(UrlError::InvalidPort(port.to_string()))
+	};
+	Ok((host.to_lowercase(), port))
+}
+
+/// Gets whether a host is a valid domain name or IPv4 address: dot-separated labels of letters,
+/// digits, hyphens and underscores, with no label starting or ending with a hyphen.
+fn is_valid_domain(host: &str) -> bool {
+	let host = host.strip_suffix('.').unwrap_or(host);
+	!host.is_empty() && host.split('.').all(|label| {
+		!label.is_empty() && !label.starts_with('-') && !label.ends_with('-')
+			&& label.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+	})
+}
@@ -0,0 +1,77 @@
+use bbclash::{render, ParserOptions, OutputFlavour, ColourMode};
+
+fn ansi(input: &str) -> String {
+	render(input, &ParserOptions::new().flavour(OutputFlavour::Ansi))
+}
+fn plain(input: &str, width: usize) -> String {
+	render(input, &ParserOptions::new().flavour(OutputFlavour::Ansi).colour_mode(ColourMode::Plain).terminal_width(width))
+}
+
+#[test]
+fn inline_styles() {
+	assert_eq!(ansi("I'm [b]bold[/b], [i]italic[/i], [u]under[/u] and [s]struck[/s]."),
+		"I'm \x1b[1mbold\x1b[0m, \x1b[3mitalic\x1b[0m, \x1b[4munder\x1b[0m and \x1b[9mstruck\x1b[0m.");
+}
+#[test]
+fn nested_styles() {
+	assert_eq!(ansi("[b]bold [i]both[/i] bold[/b] none"),
+		"\x1b[1mbold \x1b[3mboth\x1b[0m\x1b[1m bold\x1b[0m none");
+}
+#[test]
+fn palette_colours() {
+	assert_eq!(ansi("[color=#ff0000]red[/color], [color=#808080]grey[/color] and [color=#5f87af]blue[/color]"),
+		"\x1b[38;5;196mred\x1b[0m, \x1b[38;5;244mgrey\x1b[0m and \x1b[38;5;67mblue\x1b[0m");
+}
+#[test]
+fn true_colours() {
+	let options = ParserOptions::new().flavour(OutputFlavour::Ansi).colour_mode(ColourMode::TrueColour);
+	assert_eq!(render("[color=#123456]deep[/color]", &options), "\x1b[38;2;18;52;86mdeep\x1b[0m");
+}
+#[test]
+fn plain_mode() {
+	assert_eq!(plain("[b]Bold[/b] and [color=red]red[/color] at [url=https://example.com]site[/url]", 0),
+		"Bold and red at site (https://example.com)");
+}
+#[test]
+fn word_wrapping() {
+	assert_eq!(plain("The quick brown fox jumps over the lazy dog", 15),
+		"The quick brown\nfox jumps over\nthe lazy dog");
+	assert_eq!(plain("The quick brown fox jumps over the lazy dog", 0),
+		"The quick brown fox jumps over the lazy dog");
+}
+#[test]
+fn styles_reset_at_line_ends() {
+	let options = ParserOptions::new().flavour(OutputFlavour::Ansi).terminal_width(10);
+	assert_eq!(render("[b]one two three[/b]", &options), "\x1b[1mone two\x1b[0m\n\x1b[1mthree\x1b[0m");
+}
+#[test]
+fn quotes() {
+	assert_eq!(plain("[quote=Anna]Hello there, how are you?\n\nFine.[/quote]after", 20),
+		"│ Anna wrote:\n│ Hello there, how\n│ are you?\n│\n│ Fine.\n\nafter");
+}
+#[test]
+fn lists() {
+	assert_eq!(plain("[list][*]One[*]Two[/list]", 80), "• One\n• Two");
+	assert_eq!(plain("[list=1][*]First item here[*]Second[/list]", 10), "1. First\n   item\n   here\n2. Second");
+}
+#[test]
+fn code_blocks_not_wrapped() {
+	assert_eq!(plain("Code:\n\n[codeblock]let value = compute(a, b);[/codeblock]", 10),
+		"Code:\n\n    let value = compute(a, b);");
+}
+#[test]
+fn headers_and_rules() {
+	assert_eq!(ansi("[h1]Title[/h1]Body"), "\x1b[1;4mTitle\x1b[0m\n\nBody");
+	assert_eq!(plain("[hr]", 8), "────────");
+}
+#[test]
+fn tables() {
+	assert_eq!(plain("[table][tr][th]A[/th][th]B[/th][/tr][tr][td]1[/td][td]2[/td][/tr][/table]", 80),
+		"A │ B\n1 │ 2");
+}
+#[test]
+fn control_characters_escaped() {
+	assert_eq!(plain("Ring\x07 \x1bcclear", 80), "Ring^G ^[cclear");
+	assert_eq!(plain("[quote=\x1b[2JAnna\x07]Hi[/quote]", 80), "│ ^[[2JAnna^G wrote:\n│ Hi");
+	assert_eq!(ansi("[b]\u{9b}31m[/b]"), "\x1b[1m^[[31m\x1b[0m");
+}
@@ -0,0 +1,71 @@
+use bbclash::{parse, render_ast, Ast, AstNode, Attribute, GroupType, ParserOptions};
+
+#[test]
+fn root_is_document() {
+	let ast = parse("Hello", &ParserOptions::new());
+	assert_eq!(ast.root().kind(), GroupType::Document);
+	assert!(ast.root().parent().is_none());
+}
+#[test]
+fn children_in_order() {
+	let ast = parse("One\n\nTwo[hr]Three", &ParserOptions::new());
+	let kinds: Vec<GroupType> = ast.root().children().map(|node| node.kind()).collect();
+	assert_eq!(kinds, vec![GroupType::Paragraph, GroupType::Paragraph, GroupType::Hr, GroupType::Paragraph]);
+}
+#[test]
+fn parent_access() {
+	let ast = parse("[quote]Quoted [i]text[/i][/quote]", &ParserOptions::new());
+	let italic = ast.descendants().find(|node| node.kind() == GroupType::Italic).unwrap();
+	let ancestors: Vec<GroupType> = std::iter::successors(italic.parent(), |node| node.parent())
+		.map(|node| node.kind())
+		.collect();
+	assert_eq!(ancestors, vec![GroupType::Paragraph, GroupType::Quote, GroupType::Document]);
+}
+#[test]
+fn typed_attributes() {
+	let ast = parse("[h2]Title[/h2][opacity=50%]Faint[/opacity] [quote=Enda]Hi[/quote]", &ParserOptions::new());
+	let attributes: Vec<Attribute> = ast.descendants().filter_map(|node| node.attribute()).collect();
+	assert_eq!(attributes, vec![
+		Attribute::HeaderLevel(2),
+		Attribute::Opacity(0.5),
+		Attribute::Author("Enda".to_string()),
+	]);
+}
+#[test]
+fn text_content() {
+	let ast = parse("[b]Bold[/b] and [i]italic[/i]", &ParserOptions::new());
+	assert_eq!(ast.root().text_content(), "Bold and italic");
+}
+#[test]
+fn build_programmatically() {
+	let ast = Ast::new();
+	let mut paragraph = AstNode::new(GroupType::Paragraph);
+	let mut link = AstNode::new(GroupType::Url);
+	link.set_argument("https://www.penclash.com");
+	link.append(AstNode::new_text("Penclash"));
+	paragraph.append(link);
+	ast.root().append(paragraph);
+	assert_eq!(render_ast(&ast, &ParserOptions::new()),
+		"<p><a href=\"https://www.penclash.com\" rel=\"nofollow\">Penclash</a></p>");
+}
+#[test]
+fn modify_parsed() {
+	let options = ParserOptions::new();
+	let ast = parse("[b]Keep[/b][spoiler]Remove[/spoiler]", &options);
+	for mut node in ast.descendants().filter(|node| node.kind() == GroupType::Spoiler).collect::<Vec<_>>() {
+		node.detach();
+	}
+	assert_eq!(render_ast(&ast, &options), "<p><b>Keep</b></p>");
+}
+#[test]
+fn built_image_type_checked() {
+	let ast = Ast::new();
+	let mut paragraph = AstNode::new(GroupType::Paragraph);
+	for src in ["https://www.penclash.com/cat.png", "https://www.penclash.com/cat.svg"].iter() {
+		let mut image = AstNode::new(GroupType::Image);
+		image.set_argument(src);
+		paragraph.append(image);
+	}
+	ast.root().append(paragraph);
+	assert_eq!(render_ast(&ast, &ParserOptions::new()), "<p><img src=\"https://www.penclash.com/cat.png\"></p>");
+}
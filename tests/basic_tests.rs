@@ -20,43 +20,48 @@ fn no_tags() {
 #[test]
 fn one_tag() {
     assert_eq!(bbcode_to_html("I'm [b]bold![/b]"), 
-    	"<p>I&#x27m <b>bold!</b></p>");
+    	"<p>I'm <b>bold!</b></p>");
 }
 #[test]
 fn two_tags() {
 	assert_eq!(bbcode_to_html("I'm [i]italic[/i] and [b]bold![/b]"), 
-		"<p>I&#x27m <i>italic</i> and <b>bold!</b></p>");
+		"<p>I'm <i>italic</i> and <b>bold!</b></p>");
 }
 #[test]
 fn nested_tags() {
 	assert_eq!(bbcode_to_html("I'm [i][b]both italic and bold![/b][/i]"), 
-		"<p>I&#x27m <i><b>both italic and bold!</b></i></p>");
+		"<p>I'm <i><b>both italic and bold!</b></i></p>");
 }
 #[test]
 fn partially_nested_tags() {
 	assert_eq!(bbcode_to_html("I'm [i]partly italic [b]and bold![/b][/i]"), 
-		"<p>I&#x27m <i>partly italic <b>and bold!</b></i></p>");
+		"<p>I'm <i>partly italic <b>and bold!</b></i></p>");
 }
 #[test]
 fn improperly_nested_tags() {
 	assert_eq!(bbcode_to_html("I'm [i][b]very[/i] broken![/b]"), 
-		"<p>I&#x27m <i><b>very</b></i><b> broken!</b></p>");
+		"<p>I'm <i><b>very</b></i><b> broken!</b></p>");
+}
+#[test]
+fn uppercase_tags() {
+	assert_eq!(bbcode_to_html("I'm [I]italic[/i] and [B]bold![/B]"), 
+		"<p>I'm <i>italic</i> and <b>bold!</b></p>");
 }
 #[test]
 fn missing_close_tag() {
 	assert_eq!(bbcode_to_html("I'm [b]missing a closing tag!"), 
-		"<p>I&#x27m <b>missing a closing tag!</b></p><b></b>");
+		"<p>I'm <b>missing a closing tag!</b></p><b></b>");
 }
 #[test]
 fn missing_open_tag() {
 	assert_eq!(bbcode_to_html("I'm missing an opening[/i] tag!"), 
-		"<p>I&#x27m missing an opening tag!</p>");
+		"<p>I'm missing an opening tag!</p>");
 }
 #[test]
 fn tag_across_paragraphs() {
 	assert_eq!(bbcode_to_html("I'm [b]split
 	across multiple[/b] lines!"), 
-		"<p>I&#x27m <b>split</b></p><p><b>across multiple</b> lines!</p>");
+		"<p>I'm <b>split</b></p><p><b>across multiple</b> lines!</p>");
 }
 #[test]
 fn empty_tag() {
@@ -86,7 +91,7 @@ fn multiple_closing_tags() {
 #[test]
 fn nested_missing_open_tag() {
 	assert_eq!(bbcode_to_html("[b]I'm missing an opening[/i] tag![/b]"), 
-		"<p><b>I&#x27m missing an opening</b><b> tag!</b></p>");
+		"<p><b>I'm missing an opening</b><b> tag!</b></p>");
 }
 #[test]
 fn bad_tag() {
@@ -122,5 +127,5 @@ fn not_a_tag() {
 #[test]
 fn ugly_output() {
 	assert_eq!(bbcode_to_html_ugly("I'm [colour]missing an argument![/colour]"), 
-		"<p>I&#x27m [colour]missing an argument![/colour]</p>");
+		"<p>I'm [colour]missing an argument![/colour]</p>");
 }
\ No newline at end of file
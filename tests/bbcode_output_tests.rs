@@ -0,0 +1,83 @@
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+fn normalise(input: &str) -> String {
+	render(input, &ParserOptions::new().flavour(OutputFlavour::BBCode))
+}
+fn normalise_ugly(input: &str) -> String {
+	render(input, &ParserOptions::new().pretty_print(false).flavour(OutputFlavour::BBCode))
+}
+
+#[test]
+fn well_formed_unchanged() {
+	let input = "[quote=Enda]To be, or [i]not[/i] to be.\n\nThat is the question.[/quote][list=1][*]One[*]Two[/list]";
+	assert_eq!(normalise(input), input);
+}
+#[test]
+fn lowercase_tags() {
+	assert_eq!(normalise("[B]Bold[/b] and [I]italic[/I]"), "[b]Bold[/b] and [i]italic[/i]");
+}
+#[test]
+fn close_unclosed() {
+	assert_eq!(normalise("Start [b]bold [i]both"), "Start [b]bold [i]both[/i][/b]");
+}
+#[test]
+fn reorder_misnested() {
+	assert_eq!(normalise("[b]Bold [i]both[/b] italic[/i]"), "[b]Bold [i]both[/i][/b][i] italic[/i]");
+}
+#[test]
+fn canonical_arguments() {
+	assert_eq!(normalise("[colour=red]Red[/colour], [opacity=50%]faint[/opacity], [size=24]big[/size]"),
+		"[color=red]Red[/color], [opacity=0.5]faint[/opacity], [size=1.5em]big[/size]");
+}
+#[test]
+fn links_and_media() {
+	assert_eq!(normalise("[url=www.penclash.com]Penclash[/url] cat: [img]https://www.penclash.com/cat.png[/img]"),
+		"[url=http://www.penclash.com]Penclash[/url] cat: [img]https://www.penclash.com/cat.png[/img]");
+}
+#[test]
+fn bracket_in_argument() {
+	assert_eq!(normalise("[url]https://penclash.com/a]b[/url]"), "[url=https://penclash.com/a%5Db]https://penclash.com/a]b[/url]");
+}
+#[test]
+fn drop_broken_pretty() {
+	assert_eq!(normalise("I'm [colour]missing an argument![/colour]"), "I'm missing an argument!");
+}
+#[test]
+fn escape_broken_ugly() {
+	assert_eq!(normalise_ugly("I'm [colour]missing an argument![/colour]"), "I'm \\[colour]missing an argument!\\[/colour]");
+}
+#[test]
+fn escape_text_brackets() {
+	assert_eq!(normalise("This is [not a tag], \\[b]"), "This is \\[not a tag], \\[b]");
+}
+#[test]
+fn code_left_verbatim() {
+	assert_eq!(normalise("[code]let x = a[0]; [b]y[/b][/code]"), "[code]let x = a[0]; [b]y\\[/b][/code]");
+}
+#[test]
+fn paragraphs_and_breaks() {
+	assert_eq!(normalise("One\ntwo\n\nthree[hr]four"), "One\ntwo\n\nthree[hr]four");
+}
+#[test]
+fn idempotent() {
+	let inputs = [
+		"I'm [i][b]very[/i] broken![/b]",
+		"[quote]Unclosed [u]quote",
+		"[list][*]Item [b]bold[/list] after",
+		"[code]a [/b] b[/code] [colour=nope]x[/colour]",
+		"[table][tr][td]One[td]Two[/table]",
+		"[url]https://penclash.com/a]b[/url]",
+	];
+	for input in inputs.iter() {
+		let once = normalise(input);
+		assert_eq!(normalise(&once), once);
+		let once_ugly = normalise_ugly(input);
+		assert_eq!(normalise_ugly(&once_ugly), once_ugly);
+	}
+}
+#[test]
+fn same_html() {
+	let input = "I'm [I][b]very[/i] broken![/B] [url=www.penclash.com]Link[/url]";
+	let options = ParserOptions::new();
+	assert_eq!(render(&normalise(input), &options), render(input, &options));
+}
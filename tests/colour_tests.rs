@@ -0,0 +1,88 @@
+use bbclash::{bbcode_to_html, check, parse, render_ast, Ast, Attribute, AstNode, Colour, GroupType, ParserOptions};
+
+fn colour(value: &str) -> Option<String> {
+	Colour::parse(value).map(|colour| colour.to_string())
+}
+
+#[test]
+fn hex_lengths() {
+	assert_eq!(colour("#F0a"), Some("#ff00aa".into()));
+	assert_eq!(colour("#f0a8"), Some("#ff00aa88".into()));
+	assert_eq!(colour("#FF00AA"), Some("#ff00aa".into()));
+	assert_eq!(colour("#ff00aa80"), Some("#ff00aa80".into()));
+	assert_eq!(colour("#ff00aaff"), Some("#ff00aa".into()));
+}
+#[test]
+fn malformed_hex() {
+	assert_eq!(colour("#ff00a"), None);
+	assert_eq!(colour("#ff00a;x"), None);
+	assert_eq!(colour("#gg0000"), None);
+	assert_eq!(colour("#"), None);
+}
+#[test]
+fn names_case_insensitive() {
+	assert_eq!(colour("RebeccaPurple"), Some("rebeccapurple".into()));
+	assert_eq!(colour("indianred"), Some("indianred".into()));
+	assert_eq!(Colour::parse("Transparent").map(|colour| colour.alpha()), Some(0));
+	assert_eq!(colour("transparant"), None);
+	assert_eq!(colour("indianred "), None);
+}
+#[test]
+fn rgb_functions() {
+	assert_eq!(colour("rgb(255, 0, 128)"), Some("#ff0080".into()));
+	assert_eq!(colour("RGB(255 0 128)"), Some("#ff0080".into()));
+	assert_eq!(colour("rgb(100%, 0%, 50%)"), Some("#ff0080".into()));
+	assert_eq!(colour("rgba(255, 0, 128, 0.5)"), Some("#ff008080".into()));
+	assert_eq!(colour("rgb(255 0 128 / 25%)"), Some("#ff008040".into()));
+	assert_eq!(colour("rgb(300, -5, 0)"), Some("#ff0000".into()));
+}
+#[test]
+fn hsl_functions() {
+	assert_eq!(colour("hsl(0, 100%, 50%)"), Some("#ff0000".into()));
+	assert_eq!(colour("hsl(240deg 100% 50%)"), Some("#0000ff".into()));
+	assert_eq!(colour("hsla(120, 100%, 25%, 1)"), Some("#008000".into()));
+	assert_eq!(colour("hsl(-120, 0%, 100%)"), Some("#ffffff".into()));
+}
+#[test]
+fn malformed_functions() {
+	assert_eq!(colour("rgb(255, 0)"), None);
+	assert_eq!(colour("rgb(255, 0, 0, 0.5, 1)"), None);
+	assert_eq!(colour("rgb(1e3, 0, 0)"), None);
+	assert_eq!(colour("hsl(0, 100, 50)"), None);
+	assert_eq!(colour("rgb(255, 0, 0) ; background: url(x)"), None);
+	assert_eq!(colour("url(x)"), None);
+	assert_eq!(colour("expression(alert(1))"), None);
+}
+#[test]
+fn rendered_canonical() {
+	assert_eq!(bbcode_to_html("[colour=RGB(0, 128, 0)]Green[/colour]"), "<p><span style=\"color:#008000;\">Green</span></p>");
+	assert_eq!(bbcode_to_html("[colour=Red]Red[/colour]"), "<p><span style=\"color:red;\">Red</span></p>");
+}
+#[test]
+fn typed_attribute() {
+	let ast = parse("[colour=hsl(0, 100%, 50%)]Red[/colour]", &ParserOptions::new());
+	let node = ast.descendants().find(|node| node.kind() == GroupType::Colour).unwrap();
+	assert_eq!(node.attribute(), Some(Attribute::Colour(Colour::rgb(255, 0, 0))));
+}
+#[test]
+fn programmatic_colour_validated() {
+	let ast = Ast::new();
+	let mut paragraph = AstNode::new(GroupType::Paragraph);
+	for arg in ["RED", "red;background:url(x)"].iter() {
+		let mut colour = AstNode::new(GroupType::Colour);
+		colour.set_argument(arg);
+		colour.append(AstNode::new_text("Red"));
+		paragraph.append(colour);
+	}
+	ast.root().append(paragraph);
+	assert_eq!(render_ast(&ast, &ParserOptions::new()), "<p><span style=\"color:red;\">Red</span>Red</p>");
+}
+#[test]
+fn strict_opacity_and_size() {
+	for input in ["[opacity=NaN]x[/opacity]", "[opacity=inf]x[/opacity]", "[opacity=1e2%]x[/opacity]", "[size=NaNem]x[/size]", "[size=+2]x[/size]", "[size=24px]x[/size]"].iter() {
+		assert_eq!(bbcode_to_html(input), "<p>x</p>", "{}", input);
+		assert_eq!(check(input, &ParserOptions::new()).len(), 1, "{}", input);
+	}
+	assert_eq!(bbcode_to_html("[size=24]Big[/size]"), "<p><span style=\"font-size:1.5rem;\">Big</span></p>");
+	assert_eq!(bbcode_to_html("[opacity=.5]Faint[/opacity]"), "<p><span style=\"opacity:0.5;\">Faint</span></p>");
+}
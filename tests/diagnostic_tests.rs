@@ -0,0 +1,89 @@
+use bbclash::{check, parse, ParserOptions, DiagnosticKind, TagFilter};
+
+fn kinds(input: &str, options: &ParserOptions) -> Vec<DiagnosticKind> {
+	check(input, options).into_iter().map(|diagnostic| diagnostic.kind).collect()
+}
+
+#[test]
+fn clean_input() {
+	assert!(check("[b]Bold[/b] and [url=https://www.penclash.com]a link[/url]", &ParserOptions::new()).is_empty());
+	assert!(check("[img]https://www.penclash.com/cat.png[/img]", &ParserOptions::new()).is_empty());
+	assert!(check("[list][*]One[*]Two[/list]", &ParserOptions::new()).is_empty());
+}
+#[test]
+fn unknown_tag() {
+	let diagnostics = check("Some [blink]text[/blink]", &ParserOptions::new());
+	assert_eq!(diagnostics.len(), 2);
+	assert_eq!(diagnostics[0].kind, DiagnosticKind::UnknownTag);
+	assert_eq!(diagnostics[0].span.unwrap().start, 5);
+	assert_eq!(diagnostics[1].kind, DiagnosticKind::UnknownTag);
+}
+#[test]
+fn prose_in_brackets_is_not_a_tag() {
+	assert!(check("This is [not a tag] and [sic.]", &ParserOptions::new()).is_empty());
+}
+#[test]
+fn disallowed_tag() {
+	let options = ParserOptions::new().tag_filter(TagFilter::inline_only());
+	assert_eq!(kinds("[quote]Quoted[/quote]", &options),
+		vec![DiagnosticKind::DisallowedTag, DiagnosticKind::DisallowedTag]);
+}
+#[test]
+fn invalid_argument() {
+	let diagnostics = check("[colour=notacolour]Text[/colour]", &ParserOptions::new());
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].kind, DiagnosticKind::InvalidArgument);
+	assert_eq!(diagnostics[0].span.unwrap().start, 0);
+}
+#[test]
+fn missing_argument() {
+	assert_eq!(kinds("[img][/img]", &ParserOptions::new()), vec![DiagnosticKind::MissingArgument]);
+}
+#[test]
+fn unclosed_tag() {
+	let diagnostics = check("Start [b]bold [i]both", &ParserOptions::new());
+	assert_eq!(diagnostics.len(), 2);
+	assert_eq!(diagnostics[0].kind, DiagnosticKind::UnclosedTag);
+	assert_eq!(diagnostics[0].message, "`[b]` is never closed");
+	assert_eq!(diagnostics[0].span.unwrap().start, 6);
+	assert_eq!(diagnostics[1].message, "`[i]` is never closed");
+}
+#[test]
+fn stray_close_tag() {
+	let diagnostics = check("Text[/b]", &ParserOptions::new());
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].kind, DiagnosticKind::StrayCloseTag);
+	assert_eq!(diagnostics[0].to_string(), "1:5: `[/b]` has no matching opening tag");
+}
+#[test]
+fn misnested_tag() {
+	let diagnostics = check("[b]Bold [i]both[/b] italic[/i]", &ParserOptions::new());
+	assert_eq!(diagnostics[0].kind, DiagnosticKind::MisnestedTag);
+	assert_eq!(diagnostics[0].message, "`[/b]` closes `[i]`, which is still open");
+}
+#[test]
+fn misplaced_list_item() {
+	assert_eq!(kinds("[*]Item", &ParserOptions::new()), vec![DiagnosticKind::MisplacedListItem]);
+}
+#[test]
+fn limit_exceeded() {
+	let options = ParserOptions::new().max_depth(2);
+	assert!(kinds("[b][i][u]Deep[/u][/i][/b]", &options).contains(&DiagnosticKind::LimitExceeded));
+}
+#[test]
+fn ast_carries_diagnostics() {
+	let ast = parse("Line one\n[b]Line two", &ParserOptions::new());
+	let span = ast.diagnostics()[0].span.unwrap();
+	assert_eq!((span.line, span.column), (2, 1));
+}
+#[test]
+fn invalid_email() {
+	for address in ["a b@penclash.com", "enda", "@penclash.com", "enda@", "a@b@penclash.com", "enda@pen\u{7}clash.com"].iter() {
+		let input = format!("[email]{}[/email]", address);
+		let diagnostics = check(&input, &ParserOptions::new());
+		assert_eq!(diagnostics.len(), 1, "{}", address);
+		assert_eq!(diagnostics[0].kind, DiagnosticKind::InvalidArgument);
+		assert_eq!(bbclash::render(&input, &ParserOptions::new().pretty_print(false)), format!("<p>[email]{}[/email]</p>", address));
+	}
+	assert!(check("[email]enda@penclash.com[/email]", &ParserOptions::new()).is_empty());
+}
@@ -0,0 +1,105 @@
+use bbclash::{bbcode_to_html, bbcode_to_html_ugly, render, render_ast, parse, Ast, AstNode, GroupType, ParserOptions, OutputFlavour, escape_text, escape_attribute};
+
+/// Renders a paragraph containing a single element with the given argument and some text.
+fn render_built(kind: GroupType, arg: &str, flavour: OutputFlavour) -> String {
+	let ast = Ast::new();
+	let mut paragraph = AstNode::new(GroupType::Paragraph);
+	let mut element = AstNode::new(kind);
+	element.set_argument(arg);
+	element.append(AstNode::new_text("Text"));
+	paragraph.append(element);
+	ast.root().append(paragraph);
+	render_ast(&ast, &ParserOptions::new().flavour(flavour))
+}
+
+#[test]
+fn text_entities_terminated() {
+	assert_eq!(bbcode_to_html("Fish & chips <3 > 2"), "<p>Fish &amp; chips &lt;3 &gt; 2</p>");
+}
+#[test]
+fn quotes_in_text_unescaped() {
+	assert_eq!(bbcode_to_html("\"Don't,\" she said"), "<p>\"Don't,\" she said</p>");
+}
+#[test]
+fn literal_entity_escaped_once() {
+	assert_eq!(bbcode_to_html("A literal &amp; and &lt"), "<p>A literal &amp;amp; and &amp;lt</p>");
+}
+#[test]
+fn backslash_escape() {
+	assert_eq!(bbcode_to_html("C:\\\\Users"), "<p>C:\\Users</p>");
+}
+#[test]
+fn escaped_tag_keeps_preceding_text() {
+	assert_eq!(bbcode_to_html("Write \\[b] for bold"), "<p>Write [b] for bold</p>");
+}
+#[test]
+fn attribute_quotes_escaped() {
+	assert_eq!(bbcode_to_html("[quote=a\"b 'c']Hi[/quote]"),
+		"<blockquote data-author=\"a&quot;b &#x27;c&#x27;\"><p>Hi</p></blockquote>");
+}
+#[test]
+fn attribute_ampersand_escaped() {
+	assert_eq!(bbcode_to_html("[url=https://example.com/?a=1&b=2]Link[/url]"),
+		"<p><a href=\"https://example.com/?a=1&amp;b=2\" rel=\"nofollow\">Link</a></p>");
+}
+#[test]
+fn broken_tag_escaped_ugly() {
+	assert_eq!(bbcode_to_html_ugly("[colour]<i>[/colour]"), "<p>[colour]&lt;i&gt;[/colour]</p>");
+}
+#[test]
+fn bbcode_output_unescaped() {
+	let options = ParserOptions::new().flavour(OutputFlavour::BBCode);
+	assert_eq!(render("Fish & chips <3 \"peas\"", &options), "Fish & chips <3 \"peas\"");
+}
+#[test]
+fn escape_functions_borrow() {
+	assert!(matches!(escape_text("plain"), std::borrow::Cow::Borrowed(_)));
+	assert_eq!(escape_attribute("<'&'>"), "&lt;&#x27;&amp;&#x27;&gt;");
+}
+#[test]
+fn built_url_scheme_rejected() {
+	assert_eq!(render_built(GroupType::Url, "javascript:alert(1)", OutputFlavour::Html), "<p>Text</p>");
+	assert_eq!(render_built(GroupType::Url, "javascript:alert(1)", OutputFlavour::Markdown), "Text");
+}
+#[test]
+fn built_url_control_character_rejected() {
+	assert_eq!(render_built(GroupType::Url, "https://a\nb", OutputFlavour::Html), "<p>Text</p>");
+}
+#[test]
+fn built_url_escaped() {
+	assert_eq!(render_built(GroupType::Url, "https://a/'onmouseover='x", OutputFlavour::Html),
+		"<p><a href=\"https://a/&#x27;onmouseover=&#x27;x\" rel=\"nofollow\">Text</a></p>");
+}
+#[test]
+fn built_css_rejected() {
+	assert_eq!(render_built(GroupType::Colour, "red;background:url(x)", OutputFlavour::Html), "<p>Text</p>");
+	assert_eq!(render_built(GroupType::Opacity, "1;position:fixed", OutputFlavour::Html), "<p>Text</p>");
+	assert_eq!(render_built(GroupType::Size, "1;x:y", OutputFlavour::Markdown), "Text");
+}
+#[test]
+fn built_class_and_level_rejected() {
+	assert_eq!(render_built(GroupType::Figure, "left other", OutputFlavour::Html), "<p>Text</p>");
+	assert_eq!(render_built(GroupType::Indent, "9", OutputFlavour::Html), "<p>Text</p>");
+	assert_eq!(render_built(GroupType::Header, "1 onclick", OutputFlavour::Html), "<p>Text</p>");
+}
+#[test]
+fn built_free_text_escaped() {
+	assert_eq!(render_built(GroupType::Footnote, "\"><script>", OutputFlavour::Html),
+		"<p><span class=\"footnote\" data-symbol=\"&quot;&gt;&lt;script&gt;\">Text</span></p>");
+	assert_eq!(render_built(GroupType::Footnote, "\"><script>", OutputFlavour::Markdown),
+		"<span class=\"footnote\" data-symbol=\"&quot;&gt;&lt;script&gt;\">Text</span>");
+}
+#[test]
+fn modified_argument_revalidated() {
+	let options = ParserOptions::new();
+	let ast = parse("[colour=red]Red[/colour]", &options);
+	for mut node in ast.descendants().filter(|node| node.kind() == GroupType::Colour) {
+		node.set_argument("red;display:none");
+	}
+	assert_eq!(render_ast(&ast, &options), "<p>Red</p>");
+}
+#[test]
+fn malformed_hex_colour_rejected() {
+	assert_eq!(bbcode_to_html("[colour=#zz;x:y]Text[/colour]"), "<p>Text</p>");
+	assert_eq!(bbcode_to_html("[colour=#f0a]Text[/colour]"), "<p><span style=\"color:#ff00aa;\">Text</span></p>");
+}
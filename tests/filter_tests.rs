@@ -0,0 +1,57 @@
+use bbclash::{render, ParserOptions, TagFilter};
+
+#[test]
+fn inline_only_keeps_inline() {
+	let options = ParserOptions::new().tag_filter(TagFilter::inline_only());
+	assert_eq!(render("[b]Bold[/b] and [url=https://www.penclash.com]link[/url]", &options),
+		"<p><b>Bold</b> and <a href=\"https://www.penclash.com\" rel=\"nofollow\">link</a></p>");
+}
+#[test]
+fn inline_only_drops_blocks() {
+	let options = ParserOptions::new().tag_filter(TagFilter::inline_only());
+	assert_eq!(render("[quote]Not a quote[/quote][h1]Not a header[/h1]", &options),
+		"<p>Not a quoteNot a header</p>");
+}
+#[test]
+fn inline_only_ugly() {
+	let options = ParserOptions::new().pretty_print(false).tag_filter(TagFilter::inline_only());
+	assert_eq!(render("[quote]Not a quote[/quote]", &options),
+		"<p>[quote]Not a quote[/quote]</p>");
+}
+#[test]
+fn no_media() {
+	let options = ParserOptions::new().tag_filter(TagFilter::no_media());
+	assert_eq!(render("[b]Look:[/b] here [img]https://www.penclash.com/cat.png[/img]", &options),
+		"<p><b>Look:</b> here https://www.penclash.com/cat.png</p>");
+}
+#[test]
+fn allow_list() {
+	let options = ParserOptions::new().tag_filter(TagFilter::allow(&["i"]));
+	assert_eq!(render("[i]Italic[/i] and [b]plain[/b]", &options),
+		"<p><i>Italic</i> and plain</p>");
+}
+#[test]
+fn deny_list() {
+	let options = ParserOptions::new().tag_filter(TagFilter::deny(&["spoiler"]));
+	assert_eq!(render("[spoiler]Visible[/spoiler] and [b]bold[/b]", &options),
+		"<p>Visible and <b>bold</b></p>");
+}
+#[test]
+fn filter_leaves_unknown_tags() {
+	let options = ParserOptions::new().tag_filter(TagFilter::allow(&["b"]));
+	assert_eq!(render("This is [not a tag]", &options),
+		"<p>This is [not a tag]</p>");
+}
+#[test]
+fn filter_matches_aliases() {
+	let options = ParserOptions::new().tag_filter(TagFilter::deny(&["colour"]));
+	assert_eq!(render("[color=red]Red[/color] and [colour=red]red[/colour]", &options), "<p>Red and red</p>");
+	let options = ParserOptions::new().tag_filter(TagFilter::allow(&["color"]));
+	assert_eq!(render("[colour=red]Red[/colour]", &options), "<p><span style=\"color:red;\">Red</span></p>");
+}
+#[test]
+fn filter_ignores_case() {
+	let options = ParserOptions::new().tag_filter(TagFilter::allow(&["B"]));
+	assert_eq!(render("[b]Bold[/b] and [I]plain[/I]", &options), "<p><b>Bold</b> and plain</p>");
+	assert!(!TagFilter::deny(&["Spoiler"]).permits("/SPOILER"));
+}
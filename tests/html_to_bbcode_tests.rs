@@ -0,0 +1,80 @@
+use bbclash::{render, html_to_bbcode, ParserOptions, StylePolicy};
+
+fn round_trip(input: &str, options: &ParserOptions) {
+	let html = render(input, options);
+	let bbcode = html_to_bbcode(&html);
+	assert_eq!(render(&bbcode, options), html, "{} became {}", input, bbcode);
+}
+
+#[test]
+fn round_trips_own_output() {
+	let corpus = [
+		"[b]Bold[/b] [strong]strong[/strong] [i]italic[/i] [em]em[/em] [u]under[/u] [s]struck[/s] [smcaps]caps[/smcaps] [mono]mono[/mono]",
+		"[sub]sub[/sub] and [sup]sup[/sup] and [spoiler]secret[/spoiler]",
+		"[colour=red]Red[/colour] [size=1.5em]big[/size] [opacity=0.5]faint[/opacity]",
+		"[url=https://penclash.com]A link[/url] and [email]enda@penclash.com[/email]",
+		"[img]https://penclash.com/a.png[/img]",
+		"[quote=Enda]Quoted [b]text[/b][/quote]",
+		"[quote]Anonymous[/quote]",
+		"[code]let x = [1];[/code]",
+		"[codeblock=rust]fn main() {\n\tprintln!(\"[x]\");\n}[/codeblock]",
+		"[pre]Spaced   out\ntext[/pre]",
+		"[h2]Title[/h2]",
+		"[list][*]One[*]Two[/list]",
+		"[list=a][*]One[*]Two[/list]",
+		"[list=square][*]One[/list]",
+		"[table][tr][th]Head[/th][/tr][tr][td]Data[/td][/tr][caption]Cap[/caption][/table]",
+		"[center]Centred[/center]",
+		"[right]Right[/right]",
+		"[indent=2]Indented[/indent]",
+		"[figure=left][img]https://penclash.com/a.png[/img][/figure]",
+		"[footnote=*]Note[/footnote]",
+		"[math]x^2[/math]",
+		"First paragraph\n\nSecond paragraph",
+		"A line\nbreak",
+		"Escaped \\[b] and a backslash \\\\ & <html>",
+	];
+	for pretty_print in [true, false].iter() {
+		let options = ParserOptions::new().pretty_print(*pretty_print);
+		for input in corpus.iter() {
+			round_trip(input, &options);
+		}
+	}
+}
+#[test]
+fn round_trips_classes() {
+	let options = ParserOptions::new().style_policy(StylePolicy::classes());
+	round_trip("[colour=red]Red[/colour] [size=1.5em]big[/size] [opacity=0.5]faint[/opacity] [list=circle][*]One[/list]", &options);
+}
+#[test]
+fn pasted_rich_text() {
+	assert_eq!(html_to_bbcode("<p><span style=\"font-weight: 700\">Bold</span> and <span style=\"font-style:italic;color:#FF0000\">red</span></p>"),
+		"[b]Bold[/b] and [i][color=#ff0000]red[/color][/i]");
+	assert_eq!(html_to_bbcode("<p><font color=\"blue\">Blue</font> <span style=\"text-decoration: underline\">under</span></p>"),
+		"[color=blue]Blue[/color] [u]under[/u]");
+	assert_eq!(html_to_bbcode("<p><span style=\"font-size: 24px\">Big</span></p>"), "[size=1.5em]Big[/size]");
+}
+#[test]
+fn unknown_elements_dropped() {
+	assert_eq!(html_to_bbcode("<p><abbr title=\"x\">Kept</abbr> <blink>text</blink></p><iframe src=\"x\">Gone</iframe>"), "Kept text");
+	assert_eq!(html_to_bbcode("<script>alert('[b]')</script><style>p {}</style>Text<!-- comment -->"), "Text");
+}
+#[test]
+fn whitespace_collapsed() {
+	assert_eq!(html_to_bbcode("<div>\n  <p>One\n   two</p>\n  <p>Three</p>\n</div>"), "One two\n\nThree");
+}
+#[test]
+fn entities_decoded() {
+	assert_eq!(html_to_bbcode("Fish &amp; chips&nbsp;&#x27;n&#39; &lt;peas&gt; &unknown;"), "Fish & chips 'n' <peas> &unknown;");
+}
+#[test]
+fn brackets_escaped() {
+	assert_eq!(html_to_bbcode("<p>[b]Not bold[/b]</p>"), "\\[b]Not bold\\[/b]");
+	assert_eq!(html_to_bbcode("<a href=\"https://x.example/[a]\">Link</a>"), "Link");
+}
+#[test]
+fn rejected_urls_dropped() {
+	assert_eq!(html_to_bbcode("<a href=\"/page\">Page</a> <a href=\"javascript:alert(1)\">Script</a>"), "Page Script");
+	assert_eq!(html_to_bbcode("<p>An <img src=\"javascript:alert(1)\"> image <img src=\"https://x.example/a.exe\"> here</p>"), "An image here");
+	assert_eq!(html_to_bbcode("<p><span style=\"opacity: NaN\">Faint</span></p>"), "Faint");
+}
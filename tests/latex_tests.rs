@@ -0,0 +1,81 @@
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+fn latex(input: &str) -> String {
+	render(input, &ParserOptions::new().flavour(OutputFlavour::Latex))
+}
+
+#[test]
+fn inline_formatting() {
+	assert_eq!(latex("[b]Bold[/b], [i]italic[/i], [u]under[/u], [smcaps]caps[/smcaps] and [mono]mono[/mono]"),
+		"\\textbf{Bold}, \\textit{italic}, \\underline{under}, \\textsc{caps} and \\texttt{mono}");
+	assert_eq!(latex("[s]struck[/s], [sub]sub[/sub], [sup]sup[/sup] and [code]x[0][/code]"),
+		"\\sout{struck}, \\textsubscript{sub}, \\textsuperscript{sup} and \\texttt{x{[}0{]}}");
+}
+#[test]
+fn special_characters_escaped() {
+	assert_eq!(latex("50% of $5 & #1 a_b ^ ~ \\\\ {x} <y> | [not a tag]"),
+		"50\\% of \\$5 \\& \\#1 a\\_b \\textasciicircum{} \\textasciitilde{} \\textbackslash{} \\{x\\} \\textless{}y\\textgreater{} \\textbar{} {[}not a tag{]}");
+}
+#[test]
+fn paragraphs_and_breaks() {
+	assert_eq!(latex("One\ntwo\n\nThree\n\n\nFour"),
+		"One\\newline\ntwo\n\nThree\n\n\\begin{center}*\\quad*\\quad*\\end{center}\n\nFour");
+}
+#[test]
+fn headers() {
+	assert_eq!(latex("[h1]Title[/h1][h2]Part[/h2][h6]Small[/h6]"),
+		"\\section*{Title}\n\n\\subsection*{Part}\n\n\\subparagraph*{Small}");
+}
+#[test]
+fn quotes() {
+	assert_eq!(latex("[quote]Quoted[/quote]"), "\\begin{quote}\nQuoted\n\\end{quote}");
+	assert_eq!(latex("[quote=Enda]Quoted[/quote]"), "\\begin{quote}\nQuoted\n\n\\hfill--- Enda\n\\end{quote}");
+}
+#[test]
+fn lists() {
+	assert_eq!(latex("[list][*]One[*]Two[/list]"), "\\begin{itemize}\n\\item One\n\\item Two\n\\end{itemize}");
+	assert_eq!(latex("[list=1][*]One[/list]"), "\\begin{enumerate}\n\\item One\n\\end{enumerate}");
+	assert_eq!(latex("[list=I][*]One[/list]"), "\\begin{enumerate}[label=\\Roman*.]\n\\item One\n\\end{enumerate}");
+	assert_eq!(latex("[list=none][*]One[/list]"), "\\begin{itemize}[label={}]\n\\item One\n\\end{itemize}");
+}
+#[test]
+fn tables() {
+	assert_eq!(latex("[table][tr][th]A[/th][th]B[/th][/tr][tr][td]1[/td][td]2[/td][/tr][caption]Cap[/caption][/table]"),
+		"\\begin{center}\n\\begin{tabular}{ll}\n\\hline\n\\textbf{A} & \\textbf{B} \\\\\n1 & 2 \\\\\n\\hline\n\\end{tabular}\n\nCap\n\\end{center}");
+}
+#[test]
+fn footnotes() {
+	assert_eq!(latex("Text.[footnote=*]A [i]note[/i].[/footnote]"), "Text.\\footnote{A \\textit{note}.}");
+}
+#[test]
+fn maths_verbatim() {
+	assert_eq!(latex("[math]x^2 & y_1[/math]"), "$x^2 & y_1$");
+	assert_eq!(latex("[math]\\\\frac{\\\\alpha}{2}\\\\, \\\\begin{pmatrix}a & b\\\\\\\\ c & d\\\\end{pmatrix}[/math]"),
+		"$\\frac{\\alpha}{2}\\, \\begin{pmatrix}a & b\\\\ c & d\\end{pmatrix}$");
+	assert_eq!(latex("[mathblock]\\\\frac{1}{2}[/mathblock]"), "\\[\\frac{1}{2}\\]");
+}
+#[test]
+fn unsafe_maths_escaped() {
+	assert_eq!(latex("[math]x$ \\\\input{secret}$[/math]"), "x\\$ \\textbackslash{}input\\{secret\\}\\$");
+	assert_eq!(latex("[mathblock]x \\\\] \\\\write18{ls}[/mathblock]"), "x \\textbackslash{}{]} \\textbackslash{}write18\\{ls\\}");
+	assert_eq!(latex("[math]\\\\csname input\\\\endcsname{/etc/passwd}[/math]"),
+		"\\textbackslash{}csname input\\textbackslash{}endcsname\\{/etc/passwd\\}");
+	assert_eq!(latex("[math]\\\\catcode`\\\\@=0 @input{/etc/passwd}[/math]"),
+		"\\textbackslash{}catcode`\\textbackslash{}@=0 @input\\{/etc/passwd\\}");
+	for maths in ["a\\\\include{b}", "\\\\openin1=x", "^^5cinput{x}", "\\\\def\\\\x{y}", "\\\\let\\\\x=y", "\\\\edef\\\\x{y}",
+		"\\\\expandafter x", "\\\\immediate x", "a~b", "x % comment", "\\\\begin{input}x", "\\\\alpha\\\\[x"].iter() {
+		assert!(!latex(&format!("[math]{}[/math]", maths)).starts_with('$'));
+	}
+}
+#[test]
+fn code_blocks() {
+	assert_eq!(latex("[codeblock=rust]let x = \"[b]\" & y;[/codeblock]"), "\\begin{verbatim}\nlet x = \"[b]\" & y;\n\\end{verbatim}");
+	assert_eq!(latex("[codeblock]\\\\end{verbatim}[/codeblock]"), "\\begin{verbatim}\n\\end {verbatim}\n\\end{verbatim}");
+}
+#[test]
+fn links_and_colours() {
+	assert_eq!(latex("[url=https://penclash.com/a%20b#top]Link[/url] or [email]enda@penclash.com[/email]"),
+		"\\href{https://penclash.com/a\\%20b\\#top}{Link} or \\href{mailto:enda@penclash.com}{enda@penclash.com}");
+	assert_eq!(latex("[colour=red]Red[/colour] and [size=2em]big[/size] and [url=javascript:alert(1)]bad[/url]"),
+		"\\textcolor[RGB]{255,0,0}{Red} and {\\huge big} and bad");
+}
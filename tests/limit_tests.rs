@@ -0,0 +1,90 @@
+use bbclash::{render, check, parse, ParserOptions, DiagnosticKind};
+
+fn limit_messages(input: &str, options: &ParserOptions) -> Vec<String> {
+	check(input, options).into_iter()
+		.filter(|diagnostic| diagnostic.kind == DiagnosticKind::LimitExceeded)
+		.map(|diagnostic| diagnostic.message)
+		.collect()
+}
+
+#[test]
+fn depth_closing_tag_as_text() {
+	let options = ParserOptions::new().max_depth(2);
+	assert_eq!(render("[b][i]Deep[/i] still bold[/b]", &options), "<p><b>[i]Deep[/i] still bold</b></p>");
+	assert_eq!(limit_messages("[b][i]Deep[/i][/b]", &options), vec!["`[i]` is nested too deeply"]);
+}
+#[test]
+fn quote_depth() {
+	let options = ParserOptions::new().max_quote_depth(2);
+	assert_eq!(render("[quote][quote][quote]Three[/quote]Two[/quote][/quote]", &options),
+		"<blockquote><blockquote><p>[quote]Three[/quote]Two</p></blockquote></blockquote>");
+	assert_eq!(limit_messages("[quote][quote][quote]Three[/quote][/quote][/quote]", &options),
+		vec!["`[quote]` is nested more than 2 deep"]);
+}
+#[test]
+fn quote_depth_zero() {
+	assert_eq!(render("[quote]Quoted[/quote]", &ParserOptions::new().max_quote_depth(0)), "<p>[quote]Quoted[/quote]</p>");
+}
+#[test]
+fn node_limit() {
+	let options = ParserOptions::new().max_nodes(4);
+	assert_eq!(render("[b]One[/b] and [i]two[/i]\n\n[u]three[/u]", &options),
+		"<p><b>One</b> and [i]two[/i]\n\n[u]three[/u]</p>");
+	assert_eq!(limit_messages("[b]One[/b] [i]two[/i]", &options),
+		vec!["the input has more than 4 elements, so the rest is shown as text"]);
+}
+#[test]
+fn node_limit_bounds_misnesting() {
+	let input = format!("{}{}", "[b][i][u][s]".repeat(50), "x\n\n".repeat(5000));
+	let options = ParserOptions::new().max_nodes(1000);
+	let ast = parse(&input, &options);
+	assert!(ast.descendants().count() < 2000);
+	assert_eq!(ast.diagnostics().iter().filter(|diagnostic| diagnostic.kind == DiagnosticKind::LimitExceeded).count(), 1);
+}
+#[test]
+fn input_length() {
+	let options = ParserOptions::new().max_input_length(12);
+	assert_eq!(render("[b]Bold[/b]. [i]not italic[/i]", &options), "<p><b>Bold</b>. [i]not italic[/i]</p>");
+	let diagnostics = check("[b]Bold[/b]\n[i]not italic[/i]", &options);
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].kind, DiagnosticKind::LimitExceeded);
+	assert_eq!(diagnostics[0].to_string(), "2:1: the input is longer than 12 bytes, so the rest is shown as text");
+}
+#[test]
+fn input_length_char_boundary() {
+	assert_eq!(render("héllo", &ParserOptions::new().max_input_length(2)), "<p>héllo</p>");
+}
+#[test]
+fn within_limits_unchanged() {
+	let options = ParserOptions::new().max_depth(8).max_nodes(100).max_quote_depth(3).max_input_length(1000);
+	let input = "[quote=Enda][b]Bold[/b] and [i]italic[/i][/quote]";
+	assert_eq!(render(input, &options), render(input, &ParserOptions::new()));
+	assert!(check(input, &options).is_empty());
+}
+#[test]
+fn suppressed_alias_closed() {
+	let options = ParserOptions::new().max_depth(2);
+	assert_eq!(render("[b][color=red]x[/colour] y[/b]", &options), "<p><b>[color=red]x[/colour] y</b></p>");
+	assert_eq!(limit_messages("[b][color=red]x[/colour] y[/b]", &options), vec!["`[color]` is nested too deeply"]);
+}
+#[test]
+fn suppressed_void_tags_not_tracked() {
+	let options = ParserOptions::new().max_depth(2);
+	assert_eq!(render("[b][hr]x[/b] and [i][img]https://d/a.png[/img][/i]", &options),
+		"<p><b>[hr]x</b> and <i>[img]https://d/a.png[/img]</i></p>");
+	assert_eq!(render("[i][*]One[/i][list][*]Two[/list]", &options), "<p><i>[*]One</i></p><ul><li><p>Two</p></li></ul>");
+}
+#[test]
+fn node_limit_bounds_reopening() {
+	let options = ParserOptions::new().max_nodes(6);
+	assert_eq!(render("[b][i][u]x[/b] y", &options), "<p><b><i><span class=\"underline\">x</span></i></b><i> y</i></p>");
+	assert!(parse("[b][i][u][s]x[/b] y", &options).descendants().count() <= 8);
+}
+#[test]
+fn unknown_tags_not_limited() {
+	let options = ParserOptions::new().max_depth(2);
+	let diagnostics = check("[b][foo]x[/foo] and [i]y[/i][/b]", &options);
+	assert_eq!(diagnostics.iter().map(|diagnostic| diagnostic.kind).collect::<Vec<_>>(),
+		vec![DiagnosticKind::UnknownTag, DiagnosticKind::UnknownTag, DiagnosticKind::LimitExceeded]);
+	assert_eq!(render("[b][foo]x[/foo] and [i]y[/i][/b]", &options), "<p><b>[foo]x[/foo] and [i]y[/i]</b></p>");
+}
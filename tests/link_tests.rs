@@ -0,0 +1,73 @@
+use bbclash::{render, bbcode_to_html, ParserOptions, LinkPolicy, LinkAttributes, LinkHandler, Url};
+
+fn with_policy(input: &str, policy: LinkPolicy) -> String {
+	render(input, &ParserOptions::new().link_policy(policy))
+}
+
+fn community_policy() -> LinkPolicy {
+	LinkPolicy::new()
+		.internal_domains(&["penclash.com"])
+		.external(LinkAttributes::new().rel("nofollow noopener ugc").target("_blank").referrer_policy("no-referrer").class("external"))
+}
+
+#[test]
+fn default_external_nofollow() {
+	assert_eq!(bbcode_to_html("[url=https://www.penclash.com]Link[/url]"),
+		"<p><a href=\"https://www.penclash.com\" rel=\"nofollow\">Link</a></p>");
+}
+#[test]
+fn default_email_no_attributes() {
+	assert_eq!(bbcode_to_html("[email]enda@penclash.com[/email]"),
+		"<p><a href=\"mailto:enda@penclash.com\">enda@penclash.com</a></p>");
+}
+#[test]
+fn internal_domain() {
+	assert_eq!(with_policy("[url=https://penclash.com/stories]Stories[/url]", community_policy()),
+		"<p><a href=\"https://penclash.com/stories\">Stories</a></p>");
+}
+#[test]
+fn internal_subdomain() {
+	assert_eq!(with_policy("[url=https://Blog.Penclash.com]Blog[/url]", community_policy()),
+		"<p><a href=\"https://Blog.Penclash.com\">Blog</a></p>");
+}
+#[test]
+fn lookalike_domain_external() {
+	assert_eq!(with_policy("[url=https://notpenclash.com]Phish[/url]", community_policy()),
+		"<p><a href=\"https://notpenclash.com\" rel=\"nofollow noopener ugc\" target=\"_blank\" referrerpolicy=\"no-referrer\" class=\"external\">Phish</a></p>");
+}
+#[test]
+fn internal_attributes() {
+	let policy = LinkPolicy::new().internal_domains(&["penclash.com"]).internal(LinkAttributes::new().class("internal"));
+	assert_eq!(with_policy("[url=penclash.com]Home[/url]", policy),
+		"<p><a href=\"http://penclash.com\" class=\"internal\">Home</a></p>");
+}
+#[test]
+fn attributes_escaped() {
+	let policy = LinkPolicy::new().external(LinkAttributes::new().class("a\" onclick=\"x"));
+	assert_eq!(with_policy("[url=https://example.com]Link[/url]", policy),
+		"<p><a href=\"https://example.com\" class=\"a&quot; onclick=&quot;x\">Link</a></p>");
+}
+
+struct SecureOnly;
+impl LinkHandler for SecureOnly {
+	fn attributes(&self, url: &Url, internal: bool) -> LinkAttributes {
+		match (internal, url.scheme()) {
+			(true, _) => LinkAttributes::new(),
+			(false, Some("https")) => LinkAttributes::new().rel("ugc"),
+			(false, _) => LinkAttributes::new().rel("nofollow ugc").class("insecure"),
+		}
+	}
+}
+
+#[test]
+fn handler() {
+	let policy = LinkPolicy::new().internal_domains(&["penclash.com"]).handler(SecureOnly);
+	assert_eq!(with_policy("[url=https://example.com]Secure[/url] and [url=http://example.com]Insecure[/url]", policy),
+		"<p><a href=\"https://example.com\" rel=\"ugc\">Secure</a> and <a href=\"http://example.com\" rel=\"nofollow ugc\" class=\"insecure\">Insecure</a></p>");
+}
+#[test]
+fn markdown_html_block_uses_policy() {
+	let options = ParserOptions::new().link_policy(community_policy()).flavour(bbclash::OutputFlavour::Markdown);
+	assert_eq!(render("[center][url=https://penclash.com]Home[/url][/center]", &options),
+		"<div class=\"center\"><p><a href=\"https://penclash.com\">Home</a></p></div>");
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "markdown")]
+
+use bbclash::markdown_to_bbcode;
+
+#[test]
+fn inline_formatting() {
+	assert_eq!(markdown_to_bbcode("**Bold**, *italic*, __strong__, _em_, ~~struck~~ and `code [b]`"),
+		"[b]Bold[/b], [i]italic[/i], [b]strong[/b], [i]em[/i], [s]struck[/s] and [code]code [b][/code]");
+}
+#[test]
+fn paragraphs_and_breaks() {
+	assert_eq!(markdown_to_bbcode("One\ncontinued\n\nTwo  \nbroken"), "One continued\n\nTwo\nbroken");
+}
+#[test]
+fn headings_and_rules() {
+	assert_eq!(markdown_to_bbcode("# Title\n\nText\n\n---\n\n### Sub"), "[h1]Title[/h1]Text[hr][h3]Sub[/h3]");
+}
+#[test]
+fn fenced_code() {
+	assert_eq!(markdown_to_bbcode("```rust\nfn main() {\n\tlet x = [1];\n}\n```"),
+		"[codeblock=rust]fn main() {\n\tlet x = [1];\n}[/codeblock]");
+	assert_eq!(markdown_to_bbcode("    indented [/code]"), "[codeblock]indented \\[/code][/codeblock]");
+}
+#[test]
+fn quotes() {
+	assert_eq!(markdown_to_bbcode("> Quoted\n>\n> > Nested"), "[quote]Quoted[quote]Nested[/quote][/quote]");
+}
+#[test]
+fn lists() {
+	assert_eq!(markdown_to_bbcode("- One\n- Two\n\n3. Three\n4. Four"), "[list][*]One[*]Two[/list][list=1][*]Three[*]Four[/list]");
+	assert_eq!(markdown_to_bbcode("- One\n  - Nested"), "[list][*]One[list][*]Nested[/list][/list]");
+}
+#[test]
+fn links_and_images() {
+	assert_eq!(markdown_to_bbcode("[Penclash](https://penclash.com) <https://penclash.com> <enda@penclash.com>"),
+		"[url=https://penclash.com]Penclash[/url] [url=https://penclash.com]https://penclash.com[/url] [email]enda@penclash.com[/email]");
+	assert_eq!(markdown_to_bbcode("![Alt text](https://penclash.com/a.png)"), "[img]https://penclash.com/a.png[/img]");
+	assert_eq!(markdown_to_bbcode("[x](/page) and [y](javascript:alert(1)) ![Alt](https://penclash.com/a.exe)"), "x and y ");
+}
+#[test]
+fn tables() {
+	assert_eq!(markdown_to_bbcode("| A | B |\n|---|---|\n| 1 | 2 |"),
+		"[table][tr][th]A[/th][th]B[/th][/tr][tr][td]1[/td][td]2[/td][/tr][/table]");
+}
+#[test]
+fn footnotes() {
+	assert_eq!(markdown_to_bbcode("Text.[^1] More.[^missing]\n\n[^1]: The *note*."),
+		"Text.[footnote]The [i]note[/i].[/footnote] More.\\[^missing]");
+	assert_eq!(markdown_to_bbcode("Loop.[^a]\n\n[^a]: Again[^a]"), "Loop.[footnote]Again\\[^a][/footnote]");
+}
+#[test]
+fn footnotes_written_once() {
+	assert_eq!(markdown_to_bbcode("One.[^a] Two.[^a]\n\n[^a]: Note"), "One.[footnote]Note[/footnote] Two.\\[^a]");
+	let mut markdown = String::from("Start.[^0]\n\n");
+	for i in 0..30 {
+		markdown.push_str(&format!("[^{}]: Note[^{}][^{}]\n\n", i, i + 1, i + 1));
+	}
+	assert!(markdown_to_bbcode(&markdown).len() < markdown.len() * 4);
+}
+#[test]
+fn html_and_escapes() {
+	assert_eq!(markdown_to_bbcode("<div><b>Bold</b></div>\n\nText with <span>inline</span> tags"),
+		"[b]Bold[/b]\n\nText with inline tags");
+	assert_eq!(markdown_to_bbcode("Not a [b]tag[/b] \\\\ backslash"), "Not a \\[b]tag\\[/b] \\\\ backslash");
+}
@@ -0,0 +1,90 @@
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+fn markdown(input: &str) -> String {
+	render(input, &ParserOptions::new().flavour(OutputFlavour::Markdown))
+}
+
+#[test]
+fn emphasis() {
+	assert_eq!(markdown("I'm [i]italic[/i] and [b]bold![/b]"), "I'm *italic* and **bold!**");
+}
+#[test]
+fn emphasis_whitespace_outside() {
+	assert_eq!(markdown("[b] bold [/b]"), "**bold** ");
+	assert_eq!(markdown("x [i]a [/i]y"), "x *a* y");
+	assert_eq!(markdown("x[b] bold[/b]"), "x **bold**");
+	assert_eq!(markdown("[b]     [i]bold[/i] text[/b]"), "<strong><em>bold</em> text</strong>");
+}
+#[test]
+fn emphasis_html_fallback() {
+	assert_eq!(markdown("[i]a[/i][i]b[/i]"), "<em>a</em><em>b</em>");
+	assert_eq!(markdown("[b][i]both[/i][/b]"), "<strong><em>both</em></strong>");
+	assert_eq!(markdown("a[b]\"b\"[/b]c"), "a<strong>\"b\"</strong>c");
+	assert_eq!(markdown("[i]*[/i]"), "<em>\\*</em>");
+}
+#[test]
+fn paragraphs_and_breaks() {
+	assert_eq!(markdown("One\ntwo\n\nThree"), "One\\\ntwo\n\nThree");
+}
+#[test]
+fn escaped_text() {
+	assert_eq!(markdown("*Not* [i]emphasis[/i] & <b>\n\n1. Not a list"),
+		"\\*Not\\* *emphasis* \\& \\<b\\>\n\n1\\. Not a list");
+}
+#[test]
+fn links_and_images() {
+	assert_eq!(markdown("[url=https://www.penclash.com]Penclash[/url], a cat: [img]https://www.penclash.com/cat.png[/img]"),
+		"[Penclash](https://www.penclash.com), a cat: ![](https://www.penclash.com/cat.png)");
+	assert_eq!(markdown("[email]enda@penclash.com[/email]"), "[enda@penclash.com](mailto:enda@penclash.com)");
+}
+#[test]
+fn headers() {
+	assert_eq!(markdown("[h1]Title[/h1]Text[h3]Section[/h3]"), "# Title\n\nText\n\n### Section");
+}
+#[test]
+fn block_quotes() {
+	assert_eq!(markdown("[quote]To be,\n\nor not to be.[/quote]That is the question."),
+		"> To be,\n>\n> or not to be.\n\nThat is the question.");
+	assert_eq!(markdown("[quote=Enda]Quoted[/quote]"), "> <cite>Enda</cite>\n>\n> Quoted");
+}
+#[test]
+fn fenced_code() {
+	assert_eq!(markdown("[codeblock=rust]let x = 1;[/codeblock]"), "```rust\nlet x = 1;\n```");
+	assert_eq!(markdown("[codeblock]Has ``` inside[/codeblock]"), "````\nHas ``` inside\n````");
+}
+#[test]
+fn inline_code() {
+	assert_eq!(markdown("Use [code]a * b[/code] here"), "Use `a * b` here");
+}
+#[test]
+fn unordered_list() {
+	assert_eq!(markdown("[list][*]One[*]Two [b]bold[/b][/list]"), "- One\n- Two **bold**");
+}
+#[test]
+fn ordered_list() {
+	assert_eq!(markdown("[list=1][*]One[*]Two[/list]"), "1. One\n2. Two");
+}
+#[test]
+fn styled_list_as_html() {
+	assert_eq!(markdown("[list=i][*]One[/list]"), "<ol type=\"i\"><li><p>One</p></li></ol>");
+}
+#[test]
+fn nested_blocks() {
+	assert_eq!(markdown("[list][*]Item[quote]Quoted[/quote][/list]"), "- Item\n\n  > Quoted");
+}
+#[test]
+fn inline_html_fallback() {
+	assert_eq!(markdown("[u]Under[/u], [smcaps]small[/smcaps], [spoiler]hidden[/spoiler]"),
+		"<span class=\"underline\">Under</span>, <span class=\"smallcaps\">small</span>, <span class=\"spoiler\">hidden</span>");
+}
+#[test]
+fn block_html_fallback() {
+	assert_eq!(markdown("[center]Centred [b]text[/b][/center][hr]After"),
+		"<div class=\"center\"><p>Centred <b>text</b></p></div>\n\n---\n\nAfter");
+}
+#[test]
+fn broken_tags() {
+	assert_eq!(markdown("I'm [colour]missing an argument![/colour]"), "I'm missing an argument!");
+	assert_eq!(render("I'm [colour]missing an argument![/colour]", &ParserOptions::new().pretty_print(false).flavour(OutputFlavour::Markdown)),
+		"I'm \\[colour\\]missing an argument!\\[/colour\\]");
+}
@@ -0,0 +1,95 @@
+use bbclash::{render, check, parse, render_ast, ParserOptions, OutputFlavour, MediaPolicy, HostFilter, UrlRewriter, Url, UrlPolicy};
+
+struct Proxy;
+impl UrlRewriter for Proxy {
+	fn rewrite(&self, url: &Url) -> String {
+		let hex: String = url.as_str().bytes().map(|byte| format!("{:02x}", byte)).collect();
+		format!("https://camo.penclash.com/{}", hex)
+	}
+}
+
+fn with_policy(input: &str, policy: MediaPolicy) -> String {
+	render(input, &ParserOptions::new().media_policy(policy))
+}
+
+#[test]
+fn allowlist_permits_host_and_subdomains() {
+	let policy = MediaPolicy::new().hosts(HostFilter::allow(&["imgur.com"]));
+	assert_eq!(with_policy("[img]https://imgur.com/a.png[/img]", policy.clone()),
+		"<p><img src=\"https://imgur.com/a.png\"></p>");
+	assert_eq!(with_policy("[img]https://i.imgur.com/a.png[/img]", policy),
+		"<p><img src=\"https://i.imgur.com/a.png\"></p>");
+}
+#[test]
+fn trailing_dots_ignored() {
+	let policy = MediaPolicy::new().hosts(HostFilter::deny(&["Evil.com."]));
+	assert_eq!(with_policy("[img]https://evil.com/a.png[/img]", policy.clone()), "");
+	assert_eq!(with_policy("[img]https://i.evil.com./a.png[/img]", policy), "");
+	let policy = MediaPolicy::new().hosts(HostFilter::allow(&["imgur.com"]));
+	assert_eq!(with_policy("[img]https://imgur.com./a.png[/img]", policy),
+		"<p><img src=\"https://imgur.com./a.png\"></p>");
+}
+#[test]
+fn allowlist_rejects_other_hosts() {
+	let policy = MediaPolicy::new().hosts(HostFilter::allow(&["imgur.com"]));
+	assert_eq!(with_policy("[img]https://notimgur.com/a.png[/img]", policy.clone()), "");
+	assert_eq!(render("[img]https://notimgur.com/a.png[/img]", &ParserOptions::new().media_policy(policy).pretty_print(false)),
+		"<p>[img]https://notimgur.com/a.png[/img]</p>");
+}
+#[test]
+fn denylist() {
+	let policy = MediaPolicy::new().hosts(HostFilter::deny(&["tracker.example"]));
+	assert_eq!(with_policy("[img]https://cdn.tracker.example/pixel.gif[/img]", policy.clone()), "");
+	assert_eq!(with_policy("[img]https://imgur.com/a.png[/img]", policy),
+		"<p><img src=\"https://imgur.com/a.png\"></p>");
+}
+#[test]
+fn relative_always_permitted() {
+	let options = ParserOptions::new()
+		.url_policy(UrlPolicy::new().allow_relative(true))
+		.media_policy(MediaPolicy::new().hosts(HostFilter::allow(&["imgur.com"])));
+	assert_eq!(render("[img]/static/a.png[/img]", &options), "<p><img src=\"/static/a.png\"></p>");
+}
+#[test]
+fn rejected_host_diagnosed() {
+	let options = ParserOptions::new().media_policy(MediaPolicy::new().hosts(HostFilter::deny(&["tracker.example"])));
+	let diagnostics = check("[img]https://tracker.example/pixel.gif[/img]", &options);
+	assert_eq!(diagnostics[0].message,
+		"`https://tracker.example/pixel.gif` is not a valid image URL: content from `tracker.example` is not allowed");
+}
+#[test]
+fn image_rewritten() {
+	assert_eq!(with_policy("[img]https://d/a.png[/img]", MediaPolicy::new().rewriter(Proxy)),
+		"<p><img src=\"https://camo.penclash.com/68747470733a2f2f642f612e706e67\"></p>");
+}
+#[test]
+fn embed_rewritten() {
+	assert_eq!(with_policy("[embed]https://d/v[/embed]", MediaPolicy::new().rewriter(Proxy)),
+		"<div class=\"embed\" data-content=\"https://camo.penclash.com/68747470733a2f2f642f76\"></div>");
+}
+#[test]
+fn embed_host_rejected() {
+	assert_eq!(with_policy("[embed]https://tracker.example/v[/embed]", MediaPolicy::new().hosts(HostFilter::deny(&["tracker.example"]))), "");
+}
+#[test]
+fn rewritten_url_escaped() {
+	struct Quoting;
+	impl UrlRewriter for Quoting {
+		fn rewrite(&self, _url: &Url) -> String {
+			"https://proxy/?a=1&b=\"2\"".to_string()
+		}
+	}
+	assert_eq!(with_policy("[img]https://d/a.png[/img]", MediaPolicy::new().rewriter(Quoting)),
+		"<p><img src=\"https://proxy/?a=1&amp;b=&quot;2&quot;\"></p>");
+}
+#[test]
+fn markdown_image_rewritten() {
+	let options = ParserOptions::new().media_policy(MediaPolicy::new().rewriter(Proxy)).flavour(OutputFlavour::Markdown);
+	assert_eq!(render("[img]https://d/a.png[/img]", &options), "![](https://camo.penclash.com/68747470733a2f2f642f612e706e67)");
+}
+#[test]
+fn ast_and_bbcode_keep_original() {
+	let options = ParserOptions::new().media_policy(MediaPolicy::new().rewriter(Proxy));
+	let ast = parse("[img]https://d/a.png[/img]", &options);
+	assert_eq!(render_ast(&ast, &options.clone().flavour(OutputFlavour::BBCode)), "[img]https://d/a.png[/img]");
+}
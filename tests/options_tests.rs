@@ -0,0 +1,47 @@
+use bbclash::{render, bbcode_to_html, bbcode_to_html_ugly, ParserOptions, LinebreakMode, UrlPolicy, TagRegistry};
+
+#[test]
+fn default_options_are_pretty() {
+	let input = "I'm [b]bold[/b] and [colour]broken[/colour]";
+	assert_eq!(render(input, &ParserOptions::new()), bbcode_to_html(input));
+}
+#[test]
+fn ugly_options() {
+	let input = "I'm [b]bold[/b] and [colour]broken[/colour]";
+	assert_eq!(render(input, &ParserOptions::new().pretty_print(false)), bbcode_to_html_ugly(input));
+}
+#[test]
+fn linebreaks_br() {
+	assert_eq!(render("One\ntwo", &ParserOptions::new().linebreaks(LinebreakMode::Br)),
+		"<p>One<br>two</p>");
+}
+#[test]
+fn linebreaks_space() {
+	assert_eq!(render("One\ntwo", &ParserOptions::new().linebreaks(LinebreakMode::Space)),
+		"<p>One two</p>");
+}
+#[test]
+fn linebreaks_ignore() {
+	assert_eq!(render("One\ntwo", &ParserOptions::new().linebreaks(LinebreakMode::Ignore)),
+		"<p>Onetwo</p>");
+}
+#[test]
+fn url_policy_require_scheme() {
+	let options = ParserOptions::new().url_policy(UrlPolicy::new().require_scheme(true));
+	assert_eq!(render("[url=www.penclash.com]Link[/url]", &options),
+		"<p>Link</p>");
+	assert_eq!(render("[url=https://www.penclash.com]Link[/url]", &options),
+		"<p><a href=\"https://www.penclash.com\" rel=\"nofollow\">Link</a></p>");
+}
+#[test]
+fn max_depth() {
+	assert_eq!(render("[b][i][u]Deep[/u][/i][/b]", &ParserOptions::new().max_depth(3)),
+		"<p><b><i>[u]Deep[/u]</i></b></p>");
+}
+#[test]
+fn restricted_tag_set() {
+	let mut registry = TagRegistry::default();
+	registry.remove("b");
+	assert_eq!(render("[b]Bold?[/b]", &ParserOptions::new().tags(registry)),
+		"<p>[b]Bold?</p>");
+}
@@ -0,0 +1,68 @@
+use bbclash::{render, ParserOptions, OutputFlavour};
+
+fn plain(input: &str) -> String {
+	render(input, &ParserOptions::new().flavour(OutputFlavour::PlainText))
+}
+
+#[test]
+fn strips_formatting() {
+	assert_eq!(plain("I'm [i]italic[/i], [b]bold[/b] and [colour=red]red[/colour]"), "I'm italic, bold and red");
+}
+#[test]
+fn entities_unescaped() {
+	assert_eq!(plain("Fish & chips <3 \"quoted\", and a literal &lt"), "Fish & chips <3 \"quoted\", and a literal &lt");
+}
+#[test]
+fn paragraphs_and_scenebreaks() {
+	assert_eq!(plain("One\ntwo\n\nThree\n\n\nFour"), "One\ntwo\n\nThree\n\nFour");
+}
+#[test]
+fn blocks() {
+	assert_eq!(plain("[h1]Title[/h1]Intro[hr][center]Centred[/center]"), "Title\n\nIntro\n\nCentred");
+}
+#[test]
+fn links() {
+	assert_eq!(plain("See [url=https://www.penclash.com]Penclash[/url] or [url]www.penclash.com[/url]"),
+		"See Penclash (https://www.penclash.com) or www.penclash.com");
+	assert_eq!(plain("Mail [email]enda@penclash.com[/email]"), "Mail enda@penclash.com");
+}
+#[test]
+fn media_removed() {
+	assert_eq!(plain("A cat: [img]https://www.penclash.com/cat.png[/img]"), "A cat:");
+}
+#[test]
+fn bullet_list() {
+	assert_eq!(plain("[list][*]One[*]Two[/list]"), "• One\n• Two");
+}
+#[test]
+fn numbered_lists() {
+	assert_eq!(plain("[list=1][*]One[*]Two[/list]"), "1. One\n2. Two");
+	assert_eq!(plain("[list=A][*]One[*]Two[/list]"), "A. One\nB. Two");
+	assert_eq!(plain("[list=i][*]One[*]Two[*]Three[*]Four[/list]"), "i. One\nii. Two\niii. Three\niv. Four");
+}
+#[test]
+fn nested_list() {
+	assert_eq!(plain("[list][*]One[list=1][*]Inner[/list][*]Two[/list]"), "• One\n\n  1. Inner\n• Two");
+}
+#[test]
+fn quotes() {
+	assert_eq!(plain("[quote=Enda]To be,\n\nor not to be.[/quote]That is the question."),
+		"> To be,\n>\n> or not to be.\n\nThat is the question.");
+}
+#[test]
+fn tables() {
+	assert_eq!(plain("[table][tr][th]Name[/th][th]Age[/th][/tr][tr][td]Enda[/td][td]30[/td][/tr][caption]People[/caption][/table]"),
+		"Name | Age\nEnda | 30\nPeople");
+}
+#[test]
+fn code() {
+	assert_eq!(plain("Run [code]a[0][/code]:[codeblock=rust]let x = 1;\nlet y = 2;[/codeblock]"),
+		"Run a[0]:\n\nlet x = 1;\nlet y = 2;");
+}
+#[test]
+fn broken_tags() {
+	let input = "I'm [colour]missing an argument![/colour]";
+	assert_eq!(plain(input), "I'm missing an argument!");
+	assert_eq!(render(input, &ParserOptions::new().pretty_print(false).flavour(OutputFlavour::PlainText)),
+		"I'm [colour]missing an argument![/colour]");
+}
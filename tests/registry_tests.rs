@@ -0,0 +1,134 @@
+use std::rc::Rc;
+use bbclash::{bbcode_to_html, BBCodeTokenizer, BBCodeLexer, HTMLConstructor, TagRegistry, TagHandler, GroupType};
+
+fn render_with(registry: TagRegistry, input: &str) -> String {
+	render_with_mode(registry, input, true)
+}
+fn render_with_mode(registry: TagRegistry, input: &str, pretty_print: bool) -> String {
+	let registry = Rc::new(registry);
+	let mut tokenizer = BBCodeTokenizer::new();
+	let mut lexer = BBCodeLexer::with_registry(!pretty_print, registry.clone());
+	let mut constructor = HTMLConstructor::with_registry(input.len(), pretty_print, registry);
+	constructor.construct(lexer.lex(tokenizer.tokenize(input)))
+}
+
+struct Abbr;
+impl TagHandler for Abbr {
+	fn validate_arg(&self, arg: Option<&str>) -> bool {
+		arg.is_some_and(|arg| arg.chars().all(char::is_alphanumeric))
+	}
+	fn render_open(&self, arg: Option<&str>) -> String {
+		format!("<abbr title=\"{}\">", arg.unwrap_or_default())
+	}
+	fn render_close(&self, _arg: Option<&str>) -> String {
+		"</abbr>".to_string()
+	}
+}
+
+struct Aside;
+impl TagHandler for Aside {
+	fn is_block(&self) -> bool {
+		true
+	}
+	fn render_open(&self, _arg: Option<&str>) -> String {
+		"<aside>".to_string()
+	}
+	fn render_close(&self, _arg: Option<&str>) -> String {
+		"</aside>".to_string()
+	}
+}
+
+fn strong_open(lexer: &mut BBCodeLexer) {
+	lexer.new_group(GroupType::Strong);
+}
+fn strong_close(lexer: &mut BBCodeLexer) {
+	lexer.end_group(GroupType::Strong);
+}
+fn level_header_open(lexer: &mut BBCodeLexer, arg: &str) {
+	lexer.end_group(GroupType::Paragraph);
+	lexer.new_group(GroupType::Header);
+	lexer.current_element_mut().set_arg(arg);
+}
+fn level_header_close(lexer: &mut BBCodeLexer) {
+	lexer.end_group(GroupType::Header);
+	lexer.new_group(GroupType::Paragraph);
+}
+
+#[test]
+fn default_registry_matches_builtin() {
+	let input = "[b]Bold[/b] [url=www.penclash.com]link[/url] [list][*]one[/list]";
+	assert_eq!(render_with(TagRegistry::default(), input), bbcode_to_html(input));
+}
+#[test]
+fn empty_registry_is_text() {
+	assert_eq!(render_with(TagRegistry::new(), "[b]Not bold[/b]"),
+		"<p>[b]Not bold[/b]</p>");
+}
+#[test]
+fn register_new_tag() {
+	let mut registry = TagRegistry::default();
+	registry.register("loud", strong_open);
+	registry.register("/loud", strong_close);
+	assert_eq!(render_with(registry, "[loud]Hey![/loud]"),
+		"<p><strong>Hey!</strong></p>");
+}
+#[test]
+fn register_tag_with_arg() {
+	let mut registry = TagRegistry::default();
+	registry.register_with_arg("h", level_header_open);
+	registry.register("/h", level_header_close);
+	assert_eq!(render_with(registry, "[h=2]Title[/h]"),
+		"<h2>Title</h2>");
+}
+#[test]
+fn override_tag() {
+	let mut registry = TagRegistry::default();
+	assert!(registry.register("b", strong_open).is_some());
+	registry.register("/b", strong_close);
+	assert_eq!(render_with(registry, "[b]Strong now[/b]"),
+		"<p><strong>Strong now</strong></p>");
+}
+#[test]
+fn remove_tag() {
+	let mut registry = TagRegistry::default();
+	assert!(registry.remove("spoiler"));
+	assert!(registry.remove("/spoiler"));
+	assert!(!registry.contains("spoiler"));
+	assert_eq!(render_with(registry, "[spoiler]Not hidden[/spoiler]"),
+		"<p>[spoiler]Not hidden[/spoiler]</p>");
+}
+#[test]
+fn custom_inline_handler() {
+	let mut registry = TagRegistry::default();
+	registry.register_handler("abbr", Abbr);
+	assert_eq!(render_with(registry, "The [abbr=HTML]markup[/abbr] language"),
+		"<p>The <abbr title=\"HTML\">markup</abbr> language</p>");
+}
+#[test]
+fn custom_handler_bad_arg() {
+	let mut registry = TagRegistry::default();
+	registry.register_handler("abbr", Abbr);
+	assert_eq!(render_with(registry, "The [abbr=no way]markup[/abbr] language"),
+		"<p>The markup language</p>");
+}
+#[test]
+fn custom_handler_bad_arg_ugly() {
+	let mut registry = TagRegistry::default();
+	registry.register_handler("abbr", Abbr);
+	assert_eq!(render_with_mode(registry, "The [abbr]markup[/abbr] language", false),
+		"<p>The [abbr]markup[/abbr] language</p>");
+}
+#[test]
+fn custom_block_handler() {
+	let mut registry = TagRegistry::default();
+	registry.register_handler("aside", Aside);
+	assert_eq!(render_with(registry, "Before[aside]Inside[/aside]After"),
+		"<p>Before</p><aside><p>Inside</p></aside><p>After</p>");
+}
+#[test]
+fn custom_handler_overrides_builtin() {
+	let mut registry = TagRegistry::default();
+	registry.register_handler("quote", Aside);
+	assert_eq!(render_with(registry, "[quote]Inside[/quote]"),
+		"<aside><p>Inside</p></aside>");
+}
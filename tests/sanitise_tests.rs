@@ -0,0 +1,122 @@
+use bbclash::{render, verify_html, ParserOptions, HtmlViolation, TagRegistry, TagHandler, MediaPolicy, UrlRewriter, Url, StylePolicy};
+
+const CORPUS: [&str; 12] = [
+	"[b]Bold [i]both[/b] italic[/i] & <script>alert(1)</script>",
+	"[url=javascript:alert(1)]x[/url] [url]https://penclash.com/?a=1&b=2[/url] [email]enda@penclash.com[/email]",
+	"[img]https://d/a.png\" onerror=\"alert(1)[/img] [img]https://d/a.png[/img]",
+	"[colour=red;background:url(x)]a[/colour] [colour=rgb(1 2 3)]b[/colour] [size=NaN]c[/size] [opacity=50%]d[/opacity]",
+	"[quote=\"><script>]Quoted[/quote] [quote=Enda]Quoted[/quote]",
+	"[code]<b>not bold</b>[/code] [pre]  spaced  [/pre] [codeblock=rust]fn x() {}[/codeblock]",
+	"[list=circle][*]One[*]Two[/list] [list=1][*]One[/list] [list=\"x\"][*]One[/list]",
+	"[table][tr][th]Head[/th][/tr][tr][td]Data[/td][/tr][caption]Cap[/caption][/table]",
+	"[h2]Title[/h2] [h9]Bad[/h9] [center]Centred[/center] [right]Right[/right] [indent=2]In[/indent]",
+	"[figure=left][img]https://d/a.png[/img][/figure] [embed]https://d/v[/embed] [footnote=*]Note[/footnote]",
+	"[math]x^2[/math] [spoiler]s[/spoiler] [u]u[/u] [s]s[/s] [sub]a[/sub][sup]b[/sup] [hr] [br] [/b] [b] [*]",
+	"[quote][quote][b][i]Deep[/quote] unclosed [url=https://a.b]link",
+];
+
+#[test]
+fn rendered_output_verifies() {
+	for pretty_print in [true, false].iter() {
+		for style_policy in [StylePolicy::new(), StylePolicy::classes()].iter() {
+			let options = ParserOptions::new().pretty_print(*pretty_print).style_policy(style_policy.clone());
+			for input in CORPUS.iter() {
+				let html = render(input, &options);
+				assert_eq!(verify_html(&html, &options), Ok(()), "{}", html);
+			}
+		}
+	}
+}
+#[test]
+fn disallowed_markup() {
+	let options = ParserOptions::new();
+	assert_eq!(verify_html("<iframe></iframe>", &options), Err(HtmlViolation::DisallowedElement("iframe".into())));
+	assert_eq!(verify_html("<b onclick=\"x\"></b>", &options), Err(HtmlViolation::DisallowedAttribute("b".into(), "onclick".into())));
+	assert_eq!(verify_html("<span style=\"background:url(x);\"></span>", &options),
+		Err(HtmlViolation::InvalidAttributeValue("style".into(), "background:url(x);".into())));
+	assert_eq!(verify_html("<span style=\"color:red;x\"></span>", &options),
+		Err(HtmlViolation::InvalidAttributeValue("style".into(), "color:red;x".into())));
+	assert_eq!(verify_html("<ol type=\"disc\"></ol>", &options), Err(HtmlViolation::InvalidAttributeValue("type".into(), "disc".into())));
+	assert_eq!(verify_html("<img src=\"data:text/html,x\">", &options),
+		Err(HtmlViolation::InvalidAttributeValue("src".into(), "data:text/html,x".into())));
+}
+#[test]
+fn unescaped_characters() {
+	let options = ParserOptions::new();
+	assert_eq!(verify_html("<p>Fish &amp; chips &#x27;n&#39; peas</p>", &options), Ok(()));
+	assert_eq!(verify_html("<p>Fish & chips</p>", &options), Err(HtmlViolation::UnescapedCharacter('&')));
+	assert_eq!(verify_html("<p>1 < 2</p>", &options), Err(HtmlViolation::UnescapedCharacter('<')));
+	assert_eq!(verify_html("<p>2 > 1</p>", &options), Err(HtmlViolation::UnescapedCharacter('>')));
+	assert_eq!(verify_html("<blockquote data-author=\"a'b\"></blockquote>", &options), Err(HtmlViolation::UnescapedCharacter('\'')));
+}
+#[test]
+fn structure() {
+	let options = ParserOptions::new();
+	assert_eq!(verify_html("<p><b>x</p></b>", &options), Err(HtmlViolation::UnbalancedTag("p".into())));
+	assert_eq!(verify_html("<p>x", &options), Err(HtmlViolation::UnbalancedTag("p".into())));
+	assert_eq!(verify_html("<p>x<br><hr></p>", &options), Ok(()));
+	assert_eq!(verify_html("<a href='x'>", &options), Err(HtmlViolation::MalformedTag));
+	assert_eq!(verify_html("<p", &options), Err(HtmlViolation::MalformedTag));
+}
+#[test]
+fn url_schemes_follow_policy() {
+	let options = ParserOptions::new();
+	assert_eq!(verify_html("<a href=\"mailto:enda@penclash.com\">x</a>", &options), Ok(()));
+	assert!(verify_html("<a href=\"/relative\">x</a>", &options).is_err());
+	assert!(verify_html("<a href=\"ftp://files.penclash.com\">x</a>", &options).is_err());
+	let options = ParserOptions::new().url_policy(bbclash::UrlPolicy::new().allow_scheme("ftp").allow_relative(true));
+	assert_eq!(verify_html("<a href=\"ftp://files.penclash.com\">x</a>", &options), Ok(()));
+	assert_eq!(verify_html("<a href=\"/relative?a=1&amp;b=&#x27;2&#x27;\">x</a>", &options), Ok(()));
+}
+#[test]
+fn encoded_schemes_rejected() {
+	let options = ParserOptions::new();
+	for href in ["javascript&#58;alert(1)", "javascript&#x3A;alert(1)", "javascript&colon;alert(1)", "java&#0;script:alert(1)"].iter() {
+		assert_eq!(verify_html(&format!("<a href=\"{}\">x</a>", href), &options),
+			Err(HtmlViolation::InvalidAttributeValue("href".into(), href.to_string())));
+	}
+}
+#[test]
+fn duplicate_attributes() {
+	let options = ParserOptions::new();
+	assert_eq!(verify_html("<a href=\"https://penclash.com\" href=\"https://penclash.com\">x</a>", &options),
+		Err(HtmlViolation::DuplicateAttribute("a".into(), "href".into())));
+	assert_eq!(verify_html("<span class=\"a\" class=\"b\"></span>", &options),
+		Err(HtmlViolation::DuplicateAttribute("span".into(), "class".into())));
+}
+
+struct Script;
+impl TagHandler for Script {
+	fn render_open(&self, _arg: Option<&str>) -> String {
+		"<script>".to_string()
+	}
+	fn render_close(&self, _arg: Option<&str>) -> String {
+		"</script>".to_string()
+	}
+}
+
+#[test]
+fn failed_verification_falls_back_to_text() {
+	let mut registry = TagRegistry::default();
+	registry.register_handler("run", Script);
+	let options = ParserOptions::new().tags(registry);
+	let input = "[b]Fish[/b] & [run]alert(1)[/run]";
+	assert_eq!(render(input, &options), "<p><b>Fish</b> &amp; <script>alert(1)</script></p>");
+	assert_eq!(render(input, &options.verify_html(true)), "<p>Fish &amp; alert(1)</p>");
+}
+#[test]
+fn rewritten_url_checked() {
+	struct Evil;
+	impl UrlRewriter for Evil {
+		fn rewrite(&self, _url: &Url) -> String {
+			"javascript:alert(1)".to_string()
+		}
+	}
+	let options = ParserOptions::new().media_policy(MediaPolicy::new().rewriter(Evil)).verify_html(true);
+	assert_eq!(render("[img]https://d/a.png[/img]", &options), "");
+}
+#[test]
+fn violation_messages() {
+	assert_eq!(HtmlViolation::DisallowedAttribute("b".into(), "onclick".into()).to_string(), "`onclick` is not an allowed attribute of `<b>`");
+	assert_eq!(HtmlViolation::UnbalancedTag("p".into()).to_string(), "`<p>` is not closed in order");
+}
@@ -0,0 +1,44 @@
+use bbclash::{parse, BBCodeTokenizer, GroupType, Instruction, ParserOptions, Span};
+
+#[test]
+fn tokenizer_spans() {
+	let input = "I'm [b]bold[/b]";
+	let mut tokenizer = BBCodeTokenizer::new();
+	tokenizer.tokenize(input);
+	let slices: Vec<&str> = tokenizer.spans().iter().map(|span| span.slice(input)).collect();
+	assert_eq!(slices, vec!["I'm ", "[b]", "bold", "[/b]"]);
+	assert_eq!(tokenizer.instructions().len(), tokenizer.spans().len());
+}
+#[test]
+fn tokenizer_line_and_column() {
+	let input = "One\ntwo [i]three[/i]";
+	let mut tokenizer = BBCodeTokenizer::new();
+	let instructions = tokenizer.tokenize(input).clone();
+	let italic = instructions.iter().position(|instruction| instruction == &Instruction::Tag("i".to_string(), None)).unwrap();
+	assert_eq!(tokenizer.spans()[italic], Span {start: 8, end: 11, line: 2, column: 5});
+}
+#[test]
+fn multibyte_offsets() {
+	let input = "Café [b]olé[/b]";
+	let ast = parse(input, &ParserOptions::new());
+	let bold = ast.descendants().find(|node| node.kind() == GroupType::Bold).unwrap();
+	let span = bold.span().unwrap();
+	assert_eq!(span.slice(input), "[b]olé[/b]");
+	assert_eq!(span.column, 6);
+}
+#[test]
+fn element_span_covers_close_tag() {
+	let input = "Text [url=https://www.penclash.com]link[/url] more";
+	let ast = parse(input, &ParserOptions::new());
+	let link = ast.descendants().find(|node| node.kind() == GroupType::Url).unwrap();
+	assert_eq!(link.span().unwrap().slice(input), "[url=https://www.penclash.com]link[/url]");
+}
+#[test]
+fn broken_tag_span() {
+	let input = "Fine\n\n[colour=talapia]broken[/colour]";
+	let ast = parse(input, &ParserOptions::new().pretty_print(false));
+	let broken = ast.descendants().find(|node| node.is_broken()).unwrap();
+	let span = broken.span().unwrap();
+	assert_eq!(span.slice(input), "[colour=talapia]broken[/colour]");
+	assert_eq!((span.line, span.column), (3, 1));
+}
@@ -0,0 +1,49 @@
+use bbclash::{parse, ParserOptions, ContentStats};
+
+fn stats(input: &str) -> ContentStats {
+	parse(input, &ParserOptions::new()).stats()
+}
+
+#[test]
+fn empty() {
+	assert_eq!(stats(""), ContentStats::default());
+}
+#[test]
+fn links_and_domains() {
+	let stats = stats("[url=https://Penclash.com/a]a[/url] [url]www.penclash.com[/url] [url=https://penclash.com/b]b[/url] \
+		[url=javascript:alert(1)]c[/url] [email]enda@penclash.com[/email]");
+	assert_eq!(stats.links, 3);
+	assert_eq!(stats.link_domains.iter().collect::<Vec<_>>(), vec!["penclash.com", "www.penclash.com"]);
+	assert_eq!(stats.emails, 1);
+}
+#[test]
+fn media() {
+	let stats = stats("[img]https://d/a.png[/img][img]https://d/b.gif[/img][img]https://d/c.exe[/img][embed]https://d/v[/embed]");
+	assert_eq!(stats.images, 2);
+	assert_eq!(stats.embeds, 1);
+}
+#[test]
+fn text_length_ignores_whitespace() {
+	assert_eq!(stats("Hello [b]wörld[/b]\n\nAgain").text_length, 15);
+}
+#[test]
+fn spoiler_text() {
+	let stats = stats("Visible [spoiler]secret [b]bold[/b][/spoiler]");
+	assert_eq!(stats.spoiler_text, 10);
+	assert_eq!(stats.text_length, 17);
+}
+#[test]
+fn hidden_text() {
+	assert_eq!(stats("[opacity=0]gone[/opacity] [opacity=-5%]also[/opacity] [opacity=0.1]faint[/opacity]").hidden_text, 8);
+	assert_eq!(stats("[colour=transparent]gone[/colour] [colour=#ff000000]also[/colour] [colour=red]red[/colour]").hidden_text, 8);
+}
+#[test]
+fn tiny_text() {
+	let stats = stats("[size=2]tiny[/size] [size=0.5em]also[/size] [size=0.6em]small[/size]");
+	assert_eq!(stats.tiny_text, 8);
+}
+#[test]
+fn nested_counted_once() {
+	let stats = stats("[opacity=0][opacity=0]twice[/opacity][/opacity]");
+	assert_eq!(stats.hidden_text, 5);
+}
@@ -0,0 +1,66 @@
+use bbclash::{render, bbcode_to_html, ParserOptions, OutputFlavour, StylePolicy};
+
+fn with_classes(input: &str) -> String {
+	render(input, &ParserOptions::new().style_policy(StylePolicy::classes()))
+}
+
+#[test]
+fn inline_by_default() {
+	assert_eq!(bbcode_to_html("[colour=red]Red[/colour]"), "<p><span style=\"color:red;\">Red</span></p>");
+	assert!(!StylePolicy::new().uses_classes());
+	assert_eq!(StylePolicy::new().stylesheet(), "");
+}
+#[test]
+fn colour_quantised() {
+	assert_eq!(with_classes("[colour=red]Red[/colour]"), "<p><span class=\"bb-color-red\">Red</span></p>");
+	assert_eq!(with_classes("[colour=#0a0af0]Blue[/colour]"), "<p><span class=\"bb-color-blue\">Blue</span></p>");
+	assert_eq!(with_classes("[colour=hsl(120, 100%, 20%)]Green[/colour]"), "<p><span class=\"bb-color-green\">Green</span></p>");
+}
+#[test]
+fn custom_palette() {
+	let options = ParserOptions::new().style_policy(StylePolicy::classes().palette(&["black", "#ff8800", "not a colour"]));
+	assert_eq!(render("[colour=orange]Orange[/colour]", &options), "<p><span class=\"bb-color-ff8800\">Orange</span></p>");
+	assert_eq!(render("[colour=navy]Navy[/colour]", &options), "<p><span class=\"bb-color-black\">Navy</span></p>");
+}
+#[test]
+fn empty_palette_unstyled() {
+	let options = ParserOptions::new().style_policy(StylePolicy::classes().palette(&[]));
+	assert_eq!(render("[colour=red]Red[/colour]", &options), "<p><span>Red</span></p>");
+}
+#[test]
+fn size_quantised() {
+	assert_eq!(with_classes("[size=1.4em]Big[/size]"), "<p><span class=\"bb-size-1_5\">Big</span></p>");
+	assert_eq!(with_classes("[size=16]Normal[/size]"), "<p><span class=\"bb-size-1\">Normal</span></p>");
+	let options = ParserOptions::new().style_policy(StylePolicy::classes().sizes(&[1.0, 2.0]));
+	assert_eq!(render("[size=1.4em]Big[/size]", &options), "<p><span class=\"bb-size-1\">Big</span></p>");
+}
+#[test]
+fn opacity_quantised() {
+	assert_eq!(with_classes("[opacity=40%]Faint[/opacity]"), "<p><span class=\"bb-opacity-0_5\">Faint</span></p>");
+	let options = ParserOptions::new().style_policy(StylePolicy::classes().opacities(&[0.1, 0.9]));
+	assert_eq!(render("[opacity=0.2]Faint[/opacity]", &options), "<p><span class=\"bb-opacity-0_1\">Faint</span></p>");
+}
+#[test]
+fn list_style_class() {
+	assert_eq!(with_classes("[list=square][*]One[/list]"), "<ul class=\"bb-list-square\"><li><p>One</p></li></ul>");
+	assert_eq!(with_classes("[list=a][*]One[/list]"), "<ol type=\"a\"><li><p>One</p></li></ol>");
+}
+#[test]
+fn no_inline_styles() {
+	let input = "[colour=#123456]a[/colour] [size=30]b[/size] [opacity=0.3]c[/opacity][list=circle][*]d[/list]";
+	assert!(!with_classes(input).contains("style="));
+	let markdown = render(input, &ParserOptions::new().style_policy(StylePolicy::classes()).flavour(OutputFlavour::Markdown));
+	assert!(!markdown.contains("style="));
+}
+#[test]
+fn markdown_classes() {
+	let options = ParserOptions::new().style_policy(StylePolicy::classes()).flavour(OutputFlavour::Markdown);
+	assert_eq!(render("[colour=red]Red[/colour]", &options), "<span class=\"bb-color-red\">Red</span>");
+}
+#[test]
+fn stylesheet() {
+	let css = StylePolicy::classes().palette(&["red", "#ff8800"]).sizes(&[1.5]).opacities(&[0.5]).stylesheet();
+	assert_eq!(css, ".bb-color-red{color:red;}\n.bb-color-ff8800{color:#ff8800;}\n.bb-size-1_5{font-size:1.5rem;}\n\
+		.bb-opacity-0_5{opacity:0.5;}\n.bb-list-circle{list-style-type:circle;}\n.bb-list-square{list-style-type:square;}\n\
+		.bb-list-none{list-style-type:none;}\n");
+}
@@ -15,7 +15,7 @@ fn color_name_arg() {
 #[test]
 fn color_hex_arg() {
     assert_eq!(bbcode_to_html("[color=#FF0000]This should be red[/color]"), 
-    	"<p><span style=\"color:#FF0000;\">This should be red</span></p>");
+    	"<p><span style=\"color:#ff0000;\">This should be red</span></p>");
 }
 #[test]
 fn color_name_bad_arg() {
@@ -55,7 +55,7 @@ fn colour_name_arg() {
 #[test]
 fn colour_hex_arg() {
     assert_eq!(bbcode_to_html("[colour=#FF0000]This should be red[/colour]"), 
-        "<p><span style=\"color:#FF0000;\">This should be red</span></p>");
+        "<p><span style=\"color:#ff0000;\">This should be red</span></p>");
 }
 #[test]
 fn colour_name_bad_arg() {
@@ -142,7 +142,7 @@ fn url_no_text_ugly() {
 #[test]
 fn url_forbidden_char_arg_ugly() {
     assert_eq!(bbcode_to_html_ugly("[url=alert(\"Hacked!\");]This should not be a link[/url]"), 
-        "<p>[url=alert(&quotHacked!&quot);]This should not be a link[/url]</p>");
+        "<p>[url=alert(\"Hacked!\");]This should not be a link[/url]</p>");
 }
 #[test]
 fn url_bad_arg_ugly() {
@@ -152,7 +152,7 @@ fn url_bad_arg_ugly() {
 #[test]
 fn url_forbidden_char_no_arg_ugly() {
     assert_eq!(bbcode_to_html_ugly("[url]alert(\"Hacked!\");[/url]"), 
-        "<p>[url]alert(&quotHacked!&quot);[/url]</p>");
+        "<p>[url]alert(\"Hacked!\");[/url]</p>");
 }
 #[test]
 fn url_bad_no_arg_ugly() {
@@ -281,6 +281,11 @@ fn image_no_argument_ugly() {
         "<p>[img][/img]</p>");
 }
 #[test]
+fn image_https_arg_ugly() {
+    assert_eq!(bbcode_to_html_ugly("[img]https://endahallahan.github.io/Penclash-Splash-Site/resources/logo.png[/img]"), 
+        "<p><img src=\"https://endahallahan.github.io/Penclash-Splash-Site/resources/logo.png\"></p>");
+}
+#[test]
 fn image_bad_filetype_ugly() {
     assert_eq!(bbcode_to_html_ugly("[img]https://d/bad_image.svg[/img]"), 
         "<p>[img]https://d/bad_image.svg[/img]</p>");
@@ -288,7 +293,7 @@ fn image_bad_filetype_ugly() {
 #[test]
 fn image_bad_arg_ugly() {
     assert_eq!(bbcode_to_html_ugly("[img]a onerror=alert('XSS')[/img]"), 
-        "<p>[img]a onerror=alert(&#x27XSS&#x27)[/img]</p>");
+        "<p>[img]a onerror=alert('XSS')[/img]</p>");
 }
 
 /*-- QUOTE --*/
@@ -312,6 +317,11 @@ fn quote_multiline() {
         "<blockquote><p>To be, or not to be.</p><p>That is the question.</p></blockquote>");
 }
 #[test]
+fn quote_nested_ugly() {
+    assert_eq!(bbcode_to_html_ugly("[quote]To be,[quote]or not to be.[/quote][/quote]"),
+        "<blockquote><p>To be,</p><blockquote><p>or not to be.</p></blockquote></blockquote>");
+}
+#[test]
 fn empty_quote() {
     assert_eq!(bbcode_to_html("[quote][/quote]"), 
         "");
@@ -431,6 +441,11 @@ fn code_bbcode() {
     assert_eq!(bbcode_to_html("[code]To be, or [b]not[/b] to be.[/code]"), 
         "<p><code>To be, or [b]not[/b] to be.</code></p>");
 }
+#[test]
+fn code_bbcode_arg() {
+    assert_eq!(bbcode_to_html("[code]To be, or [colour=red]not[/colour] to be.[/code]"), 
+        "<p><code>To be, or [colour=red]not[/colour] to be.</code></p>");
+}
 
 /*-- CODEBLOCK --*/
 #[test]
@@ -507,6 +522,11 @@ fn simple_list() {
         "<ul><li><p>To be</p></li><li><p>or not to be</p></li></ul>");
 }
 #[test]
+fn text_after_list() {
+    assert_eq!(bbcode_to_html("[list][*]To be[*]or not to be[/list]That is the question."), 
+        "<ul><li><p>To be</p></li><li><p>or not to be</p></li></ul><p>That is the question.</p>");
+}
+#[test]
 fn expanded_simple_list() {
     assert_eq!(bbcode_to_html("
         [list]
@@ -657,6 +677,11 @@ fn indent_bad_arg_ugly() {
     assert_eq!(bbcode_to_html_ugly("[indent=7]To be, or not to be.[/indent]"), 
         "<p>[indent=7]To be, or not to be.[/indent]</p>");
 }
+#[test]
+fn indent_followed_by_text() {
+    assert_eq!(bbcode_to_html("[indent=2]To be,[/indent]or not to be."),
+        "<div class=\"indent-2\"><p>To be,</p></div><p>or not to be.</p>");
+}
 
 /*-- MATH --*/
 #[test]
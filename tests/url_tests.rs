@@ -0,0 +1,88 @@
+use bbclash::{render, check, bbcode_to_html, ParserOptions, UrlPolicy, Url, UrlError};
+
+fn with_policy(input: &str, policy: UrlPolicy) -> String {
+	render(input, &ParserOptions::new().url_policy(policy))
+}
+
+#[test]
+fn port() {
+	assert_eq!(bbcode_to_html("[url=https://www.penclash.com:8080/stories]Link[/url]"),
+		"<p><a href=\"https://www.penclash.com:8080/stories\" rel=\"nofollow\">Link</a></p>");
+}
+#[test]
+fn port_without_scheme() {
+	assert_eq!(bbcode_to_html("[url=localhost:3000/stories]Link[/url]"),
+		"<p><a href=\"http://localhost:3000/stories\" rel=\"nofollow\">Link</a></p>");
+}
+#[test]
+fn query_and_fragment_with_colons() {
+	assert_eq!(bbcode_to_html("[url]https://www.penclash.com/watch?t=10:30#part:2[/url]"),
+		"<p><a href=\"https://www.penclash.com/watch?t=10:30#part:2\" rel=\"nofollow\">https://www.penclash.com/watch?t=10:30#part:2</a></p>");
+}
+#[test]
+fn image_with_query() {
+	assert_eq!(bbcode_to_html("[img]https://d/image.PNG?width=200[/img]"),
+		"<p><img src=\"https://d/image.PNG?width=200\"></p>");
+}
+#[test]
+fn mailto_disallowed_by_default() {
+	assert_eq!(bbcode_to_html("[url=mailto:enda@penclash.com]Mail me[/url]"), "<p>Mail me</p>");
+}
+#[test]
+fn mailto_allowed() {
+	assert_eq!(with_policy("[url=mailto:enda@penclash.com]Mail me[/url]", UrlPolicy::new().allow_scheme("mailto")),
+		"<p><a href=\"mailto:enda@penclash.com\">Mail me</a></p>");
+}
+#[test]
+fn ftp_allowed() {
+	assert_eq!(with_policy("[url=ftp://files.penclash.com/a.zip]Files[/url]", UrlPolicy::new().schemes(&["https", "ftp"])),
+		"<p><a href=\"ftp://files.penclash.com/a.zip\" rel=\"nofollow\">Files</a></p>");
+}
+#[test]
+fn relative_allowed() {
+	assert_eq!(with_policy("[url=/stories/1#top]Story[/url]", UrlPolicy::new().allow_relative(true)),
+		"<p><a href=\"/stories/1#top\">Story</a></p>");
+}
+#[test]
+fn relative_disallowed_by_default() {
+	assert_eq!(bbcode_to_html("[url=/stories/1]Story[/url]"), "<p>Story</p>");
+}
+#[test]
+fn https_only() {
+	let policy = UrlPolicy::new().schemes(&["https"]);
+	assert_eq!(policy.check("www.penclash.com").unwrap().as_str(), "https://www.penclash.com");
+	assert_eq!(policy.check("http://www.penclash.com"), Err(UrlError::DisallowedScheme("http".into())));
+}
+#[test]
+fn rejection_reasons() {
+	let policy = UrlPolicy::new();
+	assert_eq!(policy.check(""), Err(UrlError::Empty));
+	assert_eq!(policy.check("https://a b"), Err(UrlError::InvalidCharacter(' ')));
+	assert_eq!(policy.check("javascript:alert(1)"), Err(UrlError::DisallowedScheme("javascript".into())));
+	assert_eq!(policy.check("/page"), Err(UrlError::RelativeNotAllowed));
+	assert_eq!(policy.check("https:page"), Err(UrlError::MissingHost));
+	assert_eq!(policy.check("https://bad_host-/"), Err(UrlError::InvalidHost("bad_host-".into())));
+	assert_eq!(policy.check("https://penclash.com:99999/"), Err(UrlError::InvalidPort("99999".into())));
+	assert_eq!(policy.check("https://penclash.com:http/"), Err(UrlError::InvalidPort("http".into())));
+	assert_eq!(policy.check("https://google.com@evil.com"), Err(UrlError::Credentials));
+	assert_eq!(UrlPolicy::new().require_scheme(true).check("penclash.com"), Err(UrlError::MissingScheme));
+}
+#[test]
+fn rejection_reason_diagnosed() {
+	let diagnostics = check("[url=https://penclash.com:99999]Link[/url]", &ParserOptions::new());
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].message, "`https://penclash.com:99999` is not a valid URL: `99999` is not a valid port");
+}
+#[test]
+fn parse_parts() {
+	let url = Url::parse("HTTPS://[::1]:8443/a?b#c").unwrap();
+	assert_eq!(url.scheme(), Some("https"));
+	assert_eq!(url.host(), Some("[::1]"));
+	assert_eq!(url.port(), Some(8443));
+	assert_eq!(url.path(), "/a");
+	assert_eq!(url.as_str(), "HTTPS://[::1]:8443/a?b#c");
+	let mail = Url::parse("mailto:enda@penclash.com").unwrap();
+	assert_eq!(mail.host(), None);
+	assert_eq!(mail.path(), "enda@penclash.com");
+	assert!(Url::parse("../up").unwrap().is_relative());
+}
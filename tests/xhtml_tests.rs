@@ -0,0 +1,63 @@
+use bbclash::{render, ParserOptions, OutputFlavour, StylePolicy};
+
+const CORPUS: [&str; 12] = [
+	"[b]Bold [i]both[/b] italic[/i] & <script>alert(1)</script> 'quoted' \"double\"",
+	"[url=https://penclash.com/?a=1&b=2]x[/url] [email]enda@penclash.com[/email]",
+	"[img]https://d/a.png\" onerror=\"alert(1)[/img] [img]https://d/a.png[/img]",
+	"[colour=red]a[/colour] [size=1.5em]b[/size] [opacity=50%]c[/opacity]",
+	"[quote=\"><&]Quoted[/quote] [quote]Quoted[quote]Nested[/quote][/quote]",
+	"[code]<b>not bold</b>[/code] [pre]  spaced  [/pre] [codeblock=rust]fn x() {}[/codeblock]",
+	"[list=circle][*]One[*]Two[/list] [list=1][*]One[/list]",
+	"[table][tr][th]Head[/th][/tr][tr][td]Data[/td][/tr][caption]Cap[/caption][/table]",
+	"[h2]Title[/h2] [center]Centred[/center] [indent=2]In[/indent] [hr] Line\nbreak",
+	"[figure=left][img]https://d/a.png[/img][/figure] [embed]https://d/v[/embed] [footnote=*]Note [b]bold[/b][/footnote]",
+	"Scene one.\n\n\nScene two.[footnote]Outer [footnote]inner[/footnote][/footnote]",
+	"Control \u{1}\u{b} characters and [unknown]tags[/unknown] [quote][b][i]Deep[/quote] [url=https://a.b]link",
+];
+
+fn xhtml(input: &str) -> String {
+	render(input, &ParserOptions::new().flavour(OutputFlavour::Xhtml))
+}
+
+#[test]
+fn output_is_well_formed() {
+	for pretty_print in [true, false].iter() {
+		for style_policy in [StylePolicy::new(), StylePolicy::classes()].iter() {
+			let options = ParserOptions::new().flavour(OutputFlavour::Xhtml).pretty_print(*pretty_print).style_policy(style_policy.clone());
+			for input in CORPUS.iter() {
+				let output = render(input, &options);
+				let document = format!("<body xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">{}</body>", output);
+				assert!(roxmltree::Document::parse(&document).is_ok(), "{}", output);
+			}
+		}
+	}
+}
+#[test]
+fn void_elements_self_closed() {
+	assert_eq!(xhtml("One\nTwo[hr][img]https://d/a.png[/img]"),
+		"<p>One<br/>Two</p><hr/><p><img src=\"https://d/a.png\" alt=\"\"/></p>");
+}
+#[test]
+fn scene_breaks() {
+	assert_eq!(xhtml("Scene one.\n\n\nScene two."), "<p>Scene one.</p><hr class=\"scenebreak\"/><p>Scene two.</p>");
+}
+#[test]
+fn footnote_asides() {
+	assert_eq!(xhtml("Text.[footnote]A [i]note[/i].[/footnote] More.[footnote=*]Starred.[/footnote]"),
+		"<p>Text.<a epub:type=\"noteref\" href=\"#fn-1\" id=\"fnref-1\">1</a> More.<a epub:type=\"noteref\" href=\"#fn-2\" id=\"fnref-2\">*</a></p>\
+		<aside epub:type=\"footnote\" id=\"fn-1\"><p>A <i>note</i>.</p></aside>\
+		<aside epub:type=\"footnote\" id=\"fn-2\"><p>Starred.</p></aside>");
+}
+#[test]
+fn invalid_characters_removed() {
+	assert_eq!(xhtml("Bell\u{7} and tab\tkept"), "<p>Bell and tab\tkept</p>");
+	assert_eq!(xhtml("[quote=En\u{1}da]Quoted[/quote]"), "<blockquote data-author=\"Enda\"><p>Quoted</p></blockquote>");
+	assert_eq!(xhtml("Text.[footnote=\u{2}*]Note.[/footnote]"),
+		"<p>Text.<a epub:type=\"noteref\" href=\"#fn-1\" id=\"fnref-1\">*</a></p><aside epub:type=\"footnote\" id=\"fn-1\"><p>Note.</p></aside>");
+	let ugly = ParserOptions::new().flavour(OutputFlavour::Xhtml).pretty_print(false);
+	assert_eq!(render("[colour=\u{1b}]x[/colour]", &ugly), "<p>[colour=]x[/colour]</p>");
+}
+#[test]
+fn html_unchanged() {
+	assert_eq!(render("A\n\n\nB[hr]", &ParserOptions::new()), "<p>A</p><br><br><br><p>B</p><hr>");
+}